@@ -0,0 +1,166 @@
+//! Derive macros for [bonsai-bt](https://crates.io/crates/bonsai-bt).
+//!
+//! `#[derive(Action)]` on an action enum generates:
+//!
+//! - `fn short_name(&self) -> &'static str`, returning the name of the variant
+//! - a `Display` implementation printing the short name
+//!
+//! The visualizers label the nodes with the `Debug` formatting of
+//! `NodeType`, so an action shows with its fields, e.g. `Action(FlyTo(1.0, 2.0))`.
+//! To label the actions by name instead, pass the `Display` of the action to
+//! `BT::to_mermaid_with_labels`:
+//!
+//! ```rust,ignore
+//! let mermaid = bt.to_mermaid_with_labels(|node| match node {
+//!     NodeType::Action(action) => Some(action.to_string()),
+//!     _ => None,
+//! });
+//! ```
+//!
+//! The name of a variant can be overridden with `#[action(label = "...")]`.
+//!
+//! ```rust
+//! use bonsai_derive::Action;
+//!
+//! #[derive(Action)]
+//! enum Drone {
+//!     TakeOff,
+//!     #[action(label = "Fly to waypoint")]
+//!     FlyTo(f64, f64),
+//!     Land { speed: f64 },
+//! }
+//!
+//! assert_eq!(Drone::TakeOff.short_name(), "TakeOff");
+//! assert_eq!(Drone::FlyTo(1.0, 2.0).to_string(), "Fly to waypoint");
+//! assert_eq!(Drone::Land { speed: 0.5 }.to_string(), "Land");
+//! ```
+//!
+//! The derive is dependency free, and only supports enums without generics.
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+#[proc_macro_derive(Action, attributes(action))]
+pub fn derive_action(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(output) => output,
+        Err(msg) => format!("compile_error!({:?});", msg).parse().unwrap(),
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+    let mut tokens = input.into_iter().peekable();
+
+    // Skip attributes and visibility up until `enum`.
+    let mut is_enum = false;
+    for token in tokens.by_ref() {
+        if let TokenTree::Ident(ident) = &token {
+            match ident.to_string().as_str() {
+                "enum" => {
+                    is_enum = true;
+                    break;
+                }
+                "struct" | "union" => break,
+                _ => {}
+            }
+        }
+    }
+    if !is_enum {
+        return Err("#[derive(Action)] is only supported for enums".to_string());
+    }
+
+    let name = match tokens.next() {
+        Some(TokenTree::Ident(ident)) => ident.to_string(),
+        _ => return Err("expected the name of the enum".to_string()),
+    };
+    let body = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group.stream(),
+        Some(TokenTree::Punct(punct)) if punct.as_char() == '<' => {
+            return Err("#[derive(Action)] does not support generic enums".to_string())
+        }
+        _ => return Err("expected the variants of the enum".to_string()),
+    };
+
+    let mut arms = String::new();
+    for (variant, label) in variants(body)? {
+        let label = label.unwrap_or_else(|| format!("{:?}", variant));
+        arms.push_str(&format!("{}::{} {{ .. }} => {},\n", name, variant, label));
+    }
+
+    let output = format!(
+        r#"
+        impl {name} {{
+            /// Short, human readable name of the action.
+            #[allow(unreachable_code)]
+            pub fn short_name(&self) -> &'static str {{
+                match *self {{
+                    {arms}
+                }}
+            }}
+        }}
+
+        impl ::std::fmt::Display for {name} {{
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {{
+                f.write_str(self.short_name())
+            }}
+        }}
+        "#,
+        name = name,
+        arms = arms
+    );
+    output.parse().map_err(|_| "failed to generate the Action implementation".to_string())
+}
+
+// Returns the name and, if overridden, the label literal of each variant.
+fn variants(body: TokenStream) -> Result<Vec<(String, Option<String>)>, String> {
+    let mut variants = vec![];
+    let mut label = None;
+    let mut tokens = body.into_iter();
+    while let Some(token) = tokens.next() {
+        match token {
+            // An attribute, `#` followed by `[...]`.
+            TokenTree::Punct(punct) if punct.as_char() == '#' => {
+                if let Some(TokenTree::Group(attr)) = tokens.next() {
+                    if let Some(lit) = label_of(attr.stream())? {
+                        label = Some(lit);
+                    }
+                }
+            }
+            TokenTree::Ident(ident) => {
+                variants.push((ident.to_string(), label.take()));
+                // Skip fields and discriminant up until the next variant.
+                for token in tokens.by_ref() {
+                    if let TokenTree::Punct(punct) = token {
+                        if punct.as_char() == ',' {
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(variants)
+}
+
+// Parses `action(label = "...")`, ignoring any other attribute.
+fn label_of(attr: TokenStream) -> Result<Option<String>, String> {
+    let mut tokens = attr.into_iter();
+    match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "action" => {}
+        _ => return Ok(None),
+    }
+    let args: Vec<TokenTree> = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+            group.stream().into_iter().collect()
+        }
+        _ => return Err("expected #[action(label = \"...\")]".to_string()),
+    };
+    match args.as_slice() {
+        [TokenTree::Ident(key), TokenTree::Punct(eq), TokenTree::Literal(lit)]
+            if key.to_string() == "label" && eq.as_char() == '=' && lit.to_string().starts_with('"') =>
+        {
+            Ok(Some(lit.to_string()))
+        }
+        _ => Err("expected #[action(label = \"...\")]".to_string()),
+    }
+}
@@ -0,0 +1,33 @@
+use bonsai_derive::Action;
+
+#[allow(dead_code)]
+#[derive(Action, Clone, Debug)]
+enum TestActions {
+    Inc,
+    /// Doc comments are attributes too.
+    #[action(label = "Decrement")]
+    Dec,
+    LessThan(i32),
+    #[action(label = "Move \"fast\"")]
+    MoveTo {
+        x: f64,
+        y: f64,
+    },
+    Wait,
+}
+
+#[test]
+fn test_short_name() {
+    assert_eq!(TestActions::Inc.short_name(), "Inc");
+    assert_eq!(TestActions::Dec.short_name(), "Decrement");
+    assert_eq!(TestActions::LessThan(1).short_name(), "LessThan");
+    assert_eq!(TestActions::MoveTo { x: 0.0, y: 1.0 }.short_name(), "Move \"fast\"");
+    assert_eq!(TestActions::Wait.short_name(), "Wait");
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(TestActions::Inc.to_string(), "Inc");
+    assert_eq!(format!("{}", TestActions::Dec), "Decrement");
+    assert_eq!(format!("{}", TestActions::LessThan(2)), "LessThan");
+}
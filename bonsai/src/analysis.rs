@@ -0,0 +1,217 @@
+use crate::{Behavior, Role};
+
+/// Whether a behavior can ever return `Success`, and whether it can ever
+/// return `Failure`, assuming that every action can return either.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Outcomes {
+    pub(crate) success: bool,
+    pub(crate) failure: bool,
+}
+
+impl Outcomes {
+    fn new(success: bool, failure: bool) -> Self {
+        Outcomes { success, failure }
+    }
+
+    pub(crate) fn can_terminate(self) -> bool {
+        self.success || self.failure
+    }
+}
+
+// Children run one by one, until one of them returns `stop`.
+//
+// Returns whether the run can end by a child returning `stop`, and whether
+// all the children can return the other status.
+fn one_by_one<A>(seq: &[Behavior<A>], stop: fn(Outcomes) -> bool, go: fn(Outcomes) -> bool) -> (bool, bool) {
+    let mut reached = true;
+    let mut stopped = false;
+    for b in seq {
+        let outcomes = outcomes(b);
+        stopped |= reached && stop(outcomes);
+        reached &= go(outcomes);
+    }
+    (stopped, reached)
+}
+
+fn success(o: Outcomes) -> bool {
+    o.success
+}
+
+fn failure(o: Outcomes) -> bool {
+    o.failure
+}
+
+pub(crate) fn outcomes<A>(behavior: &Behavior<A>) -> Outcomes {
+    match behavior {
+        Behavior::Action(_) => Outcomes::new(true, true),
+        // Could be any behavior until it is resolved.
+        Behavior::Subtree(_) => Outcomes::new(true, true),
+        Behavior::Period(_) => Outcomes::new(true, false),
+        Behavior::Forever => Outcomes::new(false, false),
+        Behavior::Named(_, ev) | Behavior::Tagged(_, ev) | Behavior::Seeded(_, ev) => outcomes(ev),
+        Behavior::Invert(ev) => {
+            let o = outcomes(ev);
+            Outcomes::new(o.failure, o.success)
+        }
+        Behavior::AlwaysSucceed(ev) => Outcomes::new(outcomes(ev).can_terminate(), false),
+        // Fails right away while the cooldown is active, which takes a success.
+        Behavior::SharedCooldown(_, _, ev) => {
+            let o = outcomes(ev);
+            Outcomes::new(o.success, o.failure || o.success)
+        }
+        Behavior::RestartOnFailure(ev) => Outcomes::new(outcomes(ev).success, false),
+        Behavior::Sequence(seq) => {
+            let (failed, succeeded) = one_by_one(seq, failure, success);
+            Outcomes::new(succeeded, failed)
+        }
+        // Fails once the budget is used up.
+        Behavior::TimeBudgetSequence(_, seq) => Outcomes::new(one_by_one(seq, failure, success).1, true),
+        Behavior::Select(sel) | Behavior::CooldownSelect(_, sel) | Behavior::SelectReactive(sel) => {
+            let (succeeded, failed) = one_by_one(sel, success, failure);
+            Outcomes::new(succeeded, failed)
+        }
+        // Any behavior can be tried first.
+        Behavior::AdaptiveSelect(_, _, sel) | Behavior::TieBreakSelect(_, sel) => Outcomes::new(
+            sel.iter().any(|b| outcomes(b).success),
+            sel.iter().all(|b| outcomes(b).failure),
+        ),
+        // Behaviors that never fail still time out.
+        Behavior::ImpatientSelect(_, sel) => Outcomes::new(sel.iter().any(|b| outcomes(b).success), true),
+        Behavior::If(condition, success, failure) => {
+            let (c, s, f) = (outcomes(condition), outcomes(success), outcomes(failure));
+            Outcomes::new(
+                (c.success && s.success) || (c.failure && f.success),
+                (c.success && s.failure) || (c.failure && f.failure),
+            )
+        }
+        Behavior::IfThen(condition, success) => {
+            let (c, s) = (outcomes(condition), outcomes(success));
+            Outcomes::new(c.success && s.success, c.failure || (c.success && s.failure))
+        }
+        // The condition holds unless it fails, even while it is running.
+        Behavior::IntervalGuard(_, condition, ev) => {
+            let o = outcomes(ev);
+            Outcomes::new(o.success, o.failure || outcomes(condition).failure)
+        }
+        // Ends when the condition does, or when the body fails.
+        Behavior::While(condition, body)
+        | Behavior::RepeatSequence(condition, body)
+        | Behavior::BoundedWhile(_, condition, body) => {
+            let c = outcomes(condition);
+            Outcomes::new(c.success, c.failure || one_by_one(body, failure, success).0)
+        }
+        Behavior::WhenAll(all) | Behavior::RoundRobinParallel(_, all) | Behavior::OrderedParallel(all) => {
+            Outcomes::new(
+                all.iter().all(|b| outcomes(b).success),
+                all.iter().any(|b| outcomes(b).failure),
+            )
+        }
+        Behavior::ResourceParallel(_, all) => Outcomes::new(
+            all.iter().all(|(_, b)| outcomes(b).success),
+            all.iter().any(|(_, b)| outcomes(b).failure),
+        ),
+        // Only the required behaviors count.
+        Behavior::RoleParallel(all) => {
+            let required: Vec<_> = all.iter().filter(|(role, _)| *role == Role::Required).collect();
+            Outcomes::new(
+                required.iter().all(|(_, b)| outcomes(b).success),
+                required.iter().any(|(_, b)| outcomes(b).failure),
+            )
+        }
+        Behavior::WhenAny(any) => Outcomes::new(
+            any.iter().any(|b| outcomes(b).success),
+            any.iter().all(|b| outcomes(b).failure),
+        ),
+        // Fails once too many behaviors have failed to reach the threshold.
+        Behavior::EarlySuccessParallel(threshold, all) => {
+            let succeeding = all.iter().filter(|b| outcomes(b).success).count();
+            let failing = all.iter().filter(|b| outcomes(b).failure).count();
+            Outcomes::new(succeeding >= *threshold, failing + threshold > all.len())
+        }
+        // Also fails if the behaviors succeed out of order.
+        Behavior::After(after) => Outcomes::new(
+            after.iter().all(|b| outcomes(b).success),
+            after.iter().any(|b| outcomes(b).failure) || after.iter().filter(|b| outcomes(b).success).count() > 1,
+        ),
+        // The body starts over forever once `init` has succeeded.
+        Behavior::WithInit(init, body) => {
+            let (i, b) = (outcomes(init), outcomes(body));
+            Outcomes::new(false, i.failure || (i.success && b.failure))
+        }
+        Behavior::DoWhile(body, condition) => {
+            let (b, c) = (outcomes(body), outcomes(condition));
+            Outcomes::new(b.success && c.failure, b.failure)
+        }
+        // Ends with the behavior, or with `on_timeout` once the behavior has timed out.
+        Behavior::WithTimeout(_, ev, on_timeout) => {
+            let (b, t) = (outcomes(ev), outcomes(on_timeout));
+            Outcomes::new(b.success || t.success, b.failure || t.failure)
+        }
+        // The finalizer runs however the body ends, and can fail either way.
+        Behavior::Finally(body, finalizer) => {
+            let (b, f) = (outcomes(body), outcomes(finalizer));
+            Outcomes::new(
+                b.success && f.success,
+                (b.failure && f.can_terminate()) || (b.can_terminate() && f.failure),
+            )
+        }
+    }
+}
+
+// Collects the paths of the innermost behaviors that keep `behavior` from terminating.
+pub(crate) fn trapping_subtrees<A>(behavior: &Behavior<A>, path: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+    if outcomes(behavior).can_terminate() {
+        return;
+    }
+    if let Behavior::Named(_, ev) | Behavior::Tagged(_, ev) = behavior {
+        return trapping_subtrees(ev, path, paths);
+    }
+    let before = paths.len();
+    for (i, child) in behavior.children().into_iter().enumerate() {
+        path.push(i);
+        trapping_subtrees(child, path, paths);
+        path.pop();
+    }
+    // None of the children is stuck on its own, so this one is the trap.
+    if paths.len() == before {
+        paths.push(path.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Behavior::{RestartOnFailure, WhenAny, WithInit};
+    use crate::{Action, AlwaysSucceed, Behavior, Forever, Period, Select, Sequence, While};
+
+    #[test]
+    fn test_can_terminate() {
+        let walk = AlwaysSucceed(Box::new(Action("walk")));
+        let patrol: Behavior<&str> = While(Box::new(Forever), vec![walk, Period(1.0)]);
+        assert!(!patrol.can_terminate());
+        assert_eq!(patrol.trapping_subtrees(), vec![vec![0]]);
+
+        // a sibling that can succeed ends the WhenAny
+        let guard = WhenAny(vec![patrol.clone(), Action("see enemy")]);
+        assert!(guard.can_terminate());
+        assert!(guard.trapping_subtrees().is_empty());
+
+        // the sequence gets stuck in the loop
+        let stuck = Sequence(vec![Period(1.0), patrol.named("patrol"), Action("sleep")]);
+        assert!(!stuck.can_terminate());
+        assert_eq!(stuck.trapping_subtrees(), vec![vec![1, 0]]);
+
+        // a select only tries the loop if the first behavior fails
+        let select = Select(vec![Action("rest"), Forever]);
+        assert!(select.can_terminate());
+
+        // the innermost trap is reported
+        let restart: Behavior<&str> = RestartOnFailure(Box::new(Sequence(vec![Period(1.0), Forever])));
+        assert!(!restart.can_terminate());
+        assert_eq!(restart.trapping_subtrees(), vec![vec![0, 1]]);
+
+        // a node can be a trap of its own
+        let init: Behavior<&str> = WithInit(Box::new(Period(1.0)), Box::new(Period(1.0)));
+        assert!(!init.can_terminate());
+        assert_eq!(init.trapping_subtrees(), vec![vec![]]);
+    }
+}
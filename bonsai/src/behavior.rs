@@ -1,7 +1,17 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::analysis::{outcomes, trapping_subtrees};
+use crate::hash::{hash_behavior, Fnv};
+
 /// Describes a behavior.
 ///
 /// This is used for more complex event logic.
 /// Can also be used for game AI.
+///
+/// A behavior can be cloned, and compared with another behavior
+/// whenever the action type `A` is `PartialEq`.
 #[derive(Clone, serde::Deserialize, serde::Serialize, PartialEq, Debug)]
 pub enum Behavior<A> {
     /// Waits an amount of time before continuing
@@ -29,8 +39,24 @@ pub enum Behavior<A> {
     /// If a behavior fails it will try the next one.
     /// Fails if the last behavior fails.
     /// Can be thought of as a short-circuited logical OR gate.
+    ///
+    /// Remembers the behavior that is running, and resumes it on the next
+    /// tick without checking the behaviors before it again, so that a
+    /// running behavior is never preempted. See `SelectReactive` for a
+    /// selector that checks the behaviors from the first one on every tick.
     Select(Vec<Behavior<A>>),
     /// `If(condition, success, failure)`
+    ///
+    /// Runs the condition, then `success` if the condition succeeds or
+    /// `failure` if it fails, and ends with the result of that branch.
+    ///
+    /// While the condition is running, the `If` is running and neither
+    /// branch is ticked. Once the condition has ended, the branch it chose
+    /// is remembered until the branch ends: the condition is not checked
+    /// again while the branch is running, even if it would end differently
+    /// by then. Entering the `If` again, e.g. on the next iteration of a
+    /// loop, checks the condition anew. The time left over by the condition
+    /// is passed on to the branch within the same tick.
     If(Box<Behavior<A>>, Box<Behavior<A>>, Box<Behavior<A>>),
     /// when condition is false, always Failure
     /// `IfThen(condition, success)`
@@ -120,13 +146,984 @@ pub enum Behavior<A> {
     /// Succeeds if all behaviors succeed, but only if succeeding in sequence.
     /// Fails if one behavior fails.
     After(Vec<Behavior<A>>),
+    /// `SharedCooldown(key, duration, behavior)`
+    ///
+    /// Runs the behavior unless the cooldown identified by `key` is active,
+    /// in which case it fails immediately. Every `SharedCooldown` node using
+    /// the same key shares one timer: when any of them succeeds, the cooldown
+    /// restarts for all of them.
+    ///
+    /// A behavior which is already running when the cooldown is started by
+    /// another node is allowed to finish.
+    ///
+    /// The timers are kept in the [SharedState](crate::SharedState) of the
+    /// tree, which the `BT` keeps across ticks. A `State` ticked with
+    /// `State::tick` starts from a fresh one on every tick, so the timers
+    /// only last across ticks with `State::tick_with_shared`.
+    ///
+    /// f64: Cooldown time in seconds
+    SharedCooldown(String, f64, Box<Behavior<A>>),
+    /// `TimeBudgetSequence(budget, behaviors)`
+    ///
+    /// Runs behaviors one by one like a `Sequence`, but with a time budget for
+    /// the sequence as a whole. The time spent while any of the behaviors is
+    /// running counts against the budget, and once it is used up the sequence
+    /// fails, dropping the behavior that was running.
+    ///
+    /// The budget starts over whenever the sequence is entered anew.
+    ///
+    /// f64: Time budget in seconds
+    TimeBudgetSequence(f64, Vec<Behavior<A>>),
+    /// `CooldownSelect(cooldown, behaviors)`
+    ///
+    /// Runs behaviors one by one until a behavior succeeds, like a `Select`,
+    /// but a behavior that fails is put on cooldown and skipped by the
+    /// selection until the cooldown has passed. Avoids retrying a behavior
+    /// that keeps failing over and over.
+    ///
+    /// A new selection starts from the first behavior each time the node is
+    /// ticked after succeeding or failing. Fails if all behaviors that are
+    /// not on cooldown fail.
+    ///
+    /// The cooldowns are kept in the [SharedState](crate::SharedState) of the
+    /// tree by node path, so that they last when the node is entered anew,
+    /// e.g. in the body of a `While`. A `State` ticked with `State::tick`
+    /// starts from a fresh shared state on every tick, so the cooldowns only
+    /// last across ticks with `State::tick_with_shared`.
+    ///
+    /// f64: Cooldown time in seconds
+    CooldownSelect(f64, Vec<Behavior<A>>),
+    /// `Named(name, behavior)`
+    ///
+    /// Gives a behavior a name, which is used as its label when visualizing
+    /// the tree. Otherwise behaves exactly like the named behavior, and does
+    /// not count as a level of its own in node paths.
+    ///
+    /// See also [Behavior::named].
+    Named(String, Box<Behavior<A>>),
+    /// Restarts the behavior when it fails, instead of failing.
+    ///
+    /// The failed behavior is reset and entered anew on the next tick, and
+    /// `Running` is returned in the meantime. Succeeds if the behavior succeeds.
+    ///
+    /// Meant for keeping background behaviors alive in parallel nodes: since
+    /// a restarting behavior never fails, it never makes a `WhenAll` or an
+    /// `After` fail, and never counts as failed for a `WhenAny`.
+    RestartOnFailure(Box<Behavior<A>>),
+    /// `WithInit(init, body)`
+    ///
+    /// Runs `init` until it succeeds, and then runs `body` over and over.
+    /// `init` only runs once, no matter how many times `body` starts over.
+    /// Resetting the tree makes `init` run again.
+    ///
+    /// Fails if `init` fails, or if `body` fails.
+    WithInit(Box<Behavior<A>>, Box<Behavior<A>>),
+    /// `Tagged(tag, behavior)`
+    ///
+    /// Tags a behavior, and with it all the actions it contains, so that they
+    /// can be looked up and disabled together, see `BT::actions_with_tag` and
+    /// `BT::disable_tag`. A disabled behavior is skipped, returning the status
+    /// given when disabling the tag instead of running. Otherwise behaves
+    /// exactly like the tagged behavior, and does not count as a level of its
+    /// own in node paths.
+    ///
+    /// See also [Behavior::tagged].
+    Tagged(String, Box<Behavior<A>>),
+    /// `DoWhile(body, condition)`
+    ///
+    /// Runs `body`, and then `condition` each time `body` succeeds. Runs
+    /// `body` again if `condition` succeeds, and succeeds if `condition` fails.
+    /// Unlike `While`, the body always runs at least once, and the condition
+    /// is only checked in between runs of the body.
+    ///
+    /// Fails if `body` fails. Each run of `body` and `condition` starts over
+    /// from scratch, and so does the whole loop when it is entered anew.
+    DoWhile(Box<Behavior<A>>, Box<Behavior<A>>),
+    /// `ImpatientSelect(timeout, behaviors)`
+    ///
+    /// Runs behaviors one by one until a behavior succeeds, like a `Select`,
+    /// but gives up on a behavior that keeps running for longer than the
+    /// timeout and moves on to the next one, as if it had failed. The running
+    /// behavior is dropped, and the time past the timeout is passed on to the
+    /// next behavior. Each behavior gets the full timeout.
+    ///
+    /// Fails if the last behavior fails or times out.
+    ///
+    /// f64: Timeout in seconds
+    ImpatientSelect(f64, Vec<Behavior<A>>),
+    /// `RoundRobinParallel(per_tick, behaviors)`
+    ///
+    /// Runs behaviors in parallel like a `WhenAll`, but only ticks `per_tick`
+    /// of the running behaviors on each update, taking turns in order. Each
+    /// behavior is given all the time that has passed since it was last
+    /// ticked, so that time-based behaviors keep the right pace.
+    ///
+    /// This spreads the cost of ticking many behaviors over several ticks,
+    /// at the expense of latency: with `n` running behaviors, each of them
+    /// only reacts every `n / per_tick` ticks. Events other than updates are
+    /// passed to all the running behaviors.
+    ///
+    /// Fails if any behavior fails, and succeeds when all behaviors succeed.
+    ///
+    /// usize: Number of behaviors ticked per update, at least one
+    RoundRobinParallel(usize, Vec<Behavior<A>>),
+    /// `EarlySuccessParallel(threshold, behaviors)`
+    ///
+    /// Runs behaviors in parallel, and succeeds as soon as `threshold` of them
+    /// have succeeded, without aborting the others. Unlike `WhenAny`, the
+    /// behaviors that are still running keep running in the background: the
+    /// node hands them over to the tree, which ticks them on its own before
+    /// the rest of the tree on each tick, until they terminate. Their results
+    /// are ignored. This works anywhere in the tree, e.g. in a `Sequence`
+    /// which moves on once the node has succeeded.
+    ///
+    /// The background behaviors are kept in the [SharedState](crate::SharedState)
+    /// of the tree, so they only last across ticks of a `State` ticked with
+    /// `State::tick_with_shared`. Like the finalizers of `Finally`, they run
+    /// outside of the node paths. They are aborted when the tree is reset, and
+    /// when the node runs again, e.g. in a `While`, and succeeds with
+    /// behaviors still running, those it left in the background the last
+    /// time are aborted.
+    ///
+    /// Fails if so many behaviors fail that `threshold` of them can no longer
+    /// succeed.
+    ///
+    /// usize: Number of behaviors that have to succeed
+    EarlySuccessParallel(usize, Vec<Behavior<A>>),
+    /// `IntervalGuard(interval, condition, behavior)`
+    ///
+    /// Runs the behavior as long as the condition holds, but only checks the
+    /// condition every `interval` seconds instead of on every tick, e.g. for
+    /// conditions that are expensive to evaluate. The condition is checked
+    /// when the node is entered, and then again each time `interval` seconds
+    /// have passed while the behavior is running. In between it is assumed
+    /// to still hold, so the behavior may keep running for up to `interval`
+    /// seconds after the condition stopped holding.
+    ///
+    /// Each check ticks the condition once from the start, and the condition
+    /// holds unless it fails. Fails if the condition fails, dropping the
+    /// behavior. Otherwise succeeds or fails with the behavior.
+    ///
+    /// f64: Time in seconds between the checks
+    IntervalGuard(f64, Box<Behavior<A>>, Box<Behavior<A>>),
+    /// Runs all behaviors in parallel, but accepts their results in order,
+    /// like a `Sequence`.
+    ///
+    /// Every behavior that is still running is ticked on each update, and a
+    /// behavior that terminates is not ticked again. Its result is buffered
+    /// until all the behaviors declared before it have been accepted, and is
+    /// then accepted in turn. Accepting a success takes a sequence step, see
+    /// `BT::sequence_steps`, so the steps are reported in declaration order
+    /// however the behaviors finish.
+    ///
+    /// Succeeds once all behaviors have succeeded. Fails once the failure of
+    /// a behavior is accepted, i.e. once all the behaviors before it have
+    /// succeeded, dropping the behaviors that are still running. A behavior
+    /// failing while an earlier one is running therefore does not end the
+    /// node until the earlier one has succeeded, and an earlier failure wins.
+    OrderedParallel(Vec<Behavior<A>>),
+    /// Runs the behavior defined elsewhere under a key, like a function call,
+    /// so that common behaviors such as fleeing are authored once and
+    /// referred to from many trees.
+    ///
+    /// Subtrees are replaced with their definitions when the tree is built
+    /// with `BT::with_subtrees`, see [Behavior::resolve_subtrees].
+    ///
+    /// # Panics
+    ///
+    /// `State::new` and `BT::new` panic if the subtrees of the behavior are
+    /// not resolved. `State::try_new` and `BT::try_new` fail instead, e.g.
+    /// for a deserialized tree.
+    Subtree(String),
+    /// `Finally(body, finalizer)`
+    ///
+    /// Runs `body`, then runs `finalizer` to completion however `body` ends,
+    /// like `try`/`finally`, e.g. to release a lock or stop an effect.
+    ///
+    /// Returns the result of `body` once `finalizer` is done, unless
+    /// `finalizer` fails, in which case it fails. Wrap `finalizer` in
+    /// `AlwaysSucceed` to ignore its failures.
+    ///
+    /// If the behavior is aborted while running, i.e. dropped by the node
+    /// above it, as a `WhenAny` does with the behaviors that are still running
+    /// once one of them succeeds, or by resetting the tree, `finalizer` is
+    /// still run to completion. It then runs on its own, ticked by the `BT`
+    /// after the rest of the tree with the same events, starting with the
+    /// tick that aborted the behavior, or the next tick after a reset. Its
+    /// result is ignored. The finalizers of the `Finally` behaviors within
+    /// `body` run first. A `finalizer` that was already running when aborted
+    /// goes on from where it was.
+    Finally(Box<Behavior<A>>, Box<Behavior<A>>),
+    /// Runs behaviors one by one until a behavior succeeds, like a `Select`,
+    /// but starts over from the first behavior on every tick.
+    ///
+    /// The behaviors before the running one are started anew on each tick.
+    /// When one of them succeeds or is running, it takes over, and the
+    /// behavior that was running is aborted, so that e.g. a behavior behind a
+    /// condition preempts a lower priority behavior as soon as the condition
+    /// holds. A `Select` would instead keep running the later behavior until
+    /// it terminates.
+    ///
+    /// Events other than updates are passed on to the next behavior when a
+    /// behavior fails, rather than consumed.
+    SelectReactive(Vec<Behavior<A>>),
+    /// Runs behaviors in parallel, each with its own [Role].
+    ///
+    /// Succeeds once all the `Required` behaviors have succeeded, dropping
+    /// the `Optional` behaviors that are still running, and fails as soon as
+    /// a `Required` behavior fails. The results of the `Optional` behaviors
+    /// are ignored, e.g. to play an animation along with a move without
+    /// failing the move when the animation is missing.
+    ///
+    /// Without `Required` behaviors, succeeds on the first tick, after
+    /// ticking the `Optional` behaviors once.
+    RoleParallel(Vec<(Role, Behavior<A>)>),
+    /// `WithTimeout(timeout, behavior, on_timeout)`
+    ///
+    /// Runs the behavior, typically an action, and if it is still running
+    /// after `timeout` seconds, aborts it and runs `on_timeout` instead, e.g.
+    /// to clean up after an action that is stuck, or to fall back on another.
+    ///
+    /// Returns the result of the behavior if it terminates in time, and the
+    /// result of `on_timeout` otherwise. The time counts from when the node
+    /// is entered, and the time left over past the timeout is passed on to
+    /// `on_timeout`.
+    ///
+    /// f64: Time in seconds the behavior is given to terminate
+    WithTimeout(f64, Box<Behavior<A>>, Box<Behavior<A>>),
+    /// `AdaptiveSelect(key, learning_rate, behaviors)`
+    ///
+    /// Runs behaviors one by one until a behavior succeeds, like a `Select`,
+    /// but tries them in an order that favors the behaviors that have been
+    /// succeeding, e.g. for an AI that learns which attack works against the
+    /// player.
+    ///
+    /// An estimate of the success rate of each behavior is kept in the tree
+    /// under `key`, so that it is kept when the node is entered again, and
+    /// shared by the nodes with the same key. The estimates start at 0.5, and
+    /// each time a behavior succeeds or fails its estimate moves towards 1 or
+    /// 0 by `learning_rate`. Each time the node is entered, the order is
+    /// drawn at random, with each next behavior drawn with a chance in
+    /// proportion to its estimate, so that behaviors that have been failing
+    /// still get tried now and then. See `BT::set_seed` to reproduce the
+    /// draws, and `BT::success_estimates` to inspect the estimates, which are
+    /// reset along with the tree.
+    ///
+    /// The estimates are kept in the [SharedState](crate::SharedState) of the
+    /// tree, see `SharedState::success_estimates` when ticking a `State`.
+    ///
+    /// f64: Learning rate between 0 and 1, the tree panics when it is built
+    /// with any other, or `BT::try_new` fails
+    AdaptiveSelect(String, f64, Vec<Behavior<A>>),
+    /// `ResourceParallel(pool, [(cost, behavior)])`
+    ///
+    /// Runs behaviors in parallel, each one once the resource pool `pool`
+    /// can afford its cost, e.g. abilities sharing a budget of action points.
+    ///
+    /// The nodes only see the blackboard through the actions, so the pools
+    /// are kept next to it, in the [SharedState](crate::SharedState) of the
+    /// tree, and are shared by key between nodes. They are set with
+    /// `BT::set_resource_pool`, or `SharedState::set_resource_pool` when
+    /// ticking a `State`, and a pool that was never set holds nothing. The
+    /// costs must be finite and not negative, the tree panics when it is
+    /// built with any other, or `BT::try_new` fails. A behavior takes its cost out of the pool
+    /// when it starts. Until the pool holds enough, it is deferred: it is not
+    /// ticked, and is tried again on the next tick. The behaviors claim their
+    /// cost in the order they are declared on each tick, so that an earlier
+    /// behavior gets the resources first when they are short, but a later
+    /// behavior that costs less can still start with what is left.
+    ///
+    /// The cost of a behavior that succeeds is spent, and the cost of one
+    /// that fails or is aborted is given back to the pool. Succeeds once all
+    /// the behaviors have succeeded, and fails as soon as one of them fails,
+    /// aborting those that are still running.
+    ResourceParallel(String, Vec<(f64, Behavior<A>)>),
+    /// `BoundedWhile(max_iterations, condition, body)`
+    ///
+    /// Loops while the conditional behavior is running, like a `While`, but
+    /// goes on with the body within a tick for as long as its behaviors
+    /// complete, instead of moving on by one behavior per tick, e.g. to
+    /// handle all the items of a queue at once.
+    ///
+    /// The body is run at most `max_iterations` times per tick, so that an
+    /// instant body under a condition that keeps running cannot hang the
+    /// tick: once the cap is hit, `Running` is returned, and the loop goes
+    /// on with the next tick. The condition is ticked once per tick. A cap
+    /// of 0 counts as 1.
+    ///
+    /// Succeeds if the conditional behavior succeeds.
+    /// Fails if the conditional behavior fails,
+    /// or if any behavior in the loop body fails.
+    ///
+    /// usize: Maximum number of times the body runs per tick
+    BoundedWhile(usize, Box<Behavior<A>>, Vec<Behavior<A>>),
+    /// `Seeded(salt, behavior)`
+    ///
+    /// Runs a behavior with random choices of its own, drawn from a stream
+    /// forked off the one of the tree for `salt`, e.g. so that agents built
+    /// from the same tree and seed do not make the same random choices, while
+    /// each of them can still be reproduced with `BT::set_seed`.
+    ///
+    /// The stream only depends on the seed of the enclosing stream and on the
+    /// salt, so that draws made elsewhere in the tree do not shift it. It
+    /// goes on from where it was each time the node is entered. A `Seeded`
+    /// behavior inside another one forks off the stream of the enclosing one,
+    /// and nodes with the same salt under the same stream share theirs.
+    ///
+    /// The streams are kept in the [SharedState](crate::SharedState) of the
+    /// tree, so a `State` goes on with them as long as it is ticked with the
+    /// same shared state, which is seeded with `SharedState::set_seed`.
+    ///
+    /// Ends with the result of the behavior.
+    ///
+    /// u64: Salt mixed into the seed of the enclosing stream
+    Seeded(u64, Box<Behavior<A>>),
+    /// `TieBreakSelect(tie_break, behaviors)`
+    ///
+    /// Runs behaviors one by one until a behavior succeeds, like a `Select`,
+    /// but for behaviors that are equally good options, so that the order in
+    /// which they are tried is left to `tie_break`, e.g. to keep an AI from
+    /// always picking the same idle animation. The order is settled each time
+    /// the node is entered, see [TieBreak].
+    ///
+    /// Fails if all the behaviors fail.
+    TieBreakSelect(TieBreak, Vec<Behavior<A>>),
+}
+
+/// The role of a behavior in a `RoleParallel`.
+#[derive(Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq, Eq, Hash, Debug)]
+pub enum Role {
+    /// The behavior must succeed for the parallel to succeed.
+    Required,
+    /// The result of the behavior is ignored.
+    Optional,
+}
+
+/// The order in which a `TieBreakSelect` tries its behaviors.
+///
+/// The random stream and the places to start from of the round robins are
+/// kept in the [SharedState](crate::SharedState) of the tree, so a `State`
+/// goes on with them as long as it is ticked with the same shared state.
+#[derive(Clone, serde::Deserialize, serde::Serialize, PartialEq, Eq, Hash, Debug)]
+pub enum TieBreak {
+    /// In the order they are declared, like a `Select`.
+    DeclarationOrder,
+    /// In a random order, drawn each time the node is entered, see `BT::set_seed`
+    /// and `SharedState::set_seed`.
+    Random,
+    /// In the order they are declared, but starting from the next behavior
+    /// each time the node is entered, wrapping around. The place to start
+    /// from is kept in the tree under the key, and shared by the nodes with
+    /// the same key.
+    RoundRobin(String),
+}
+
+impl<A> Behavior<A> {
+    /// Gives the behavior a name, see [Behavior::Named].
+    ///
+    /// ```
+    /// use bonsai_bt::{Action, Behavior, Sequence};
+    ///
+    /// let flee: Behavior<&str> = Sequence(vec![Action("turn"), Action("run")]).named("flee");
+    /// ```
+    pub fn named<S: Into<String>>(self, name: S) -> Behavior<A> {
+        Behavior::Named(name.into(), Box::new(self))
+    }
+
+    /// Tags the behavior, see [Behavior::Tagged].
+    ///
+    /// ```
+    /// use bonsai_bt::{Action, Behavior, Select};
+    ///
+    /// let attack: Behavior<&str> = Select(vec![Action("shoot"), Action("punch")]).tagged("combat");
+    /// ```
+    pub fn tagged<S: Into<String>>(self, tag: S) -> Behavior<A> {
+        Behavior::Tagged(tag.into(), Box::new(self))
+    }
+
+    /// Builds a [Behavior::Sequence] from any iterator of behaviors, without
+    /// collecting them into a `Vec` first.
+    ///
+    /// ```
+    /// use bonsai_bt::{Action, Behavior, Sequence};
+    ///
+    /// let waypoints = ["gate", "tower", "well"];
+    /// let patrol = Behavior::sequence(waypoints.iter().map(|w| Action(*w)));
+    /// assert_eq!(patrol, Sequence(vec![Action("gate"), Action("tower"), Action("well")]));
+    /// ```
+    pub fn sequence<I: IntoIterator<Item = Behavior<A>>>(children: I) -> Behavior<A> {
+        Behavior::Sequence(children.into_iter().collect())
+    }
+
+    /// Builds a [Behavior::Select] from any iterator of behaviors.
+    pub fn select<I: IntoIterator<Item = Behavior<A>>>(children: I) -> Behavior<A> {
+        Behavior::Select(children.into_iter().collect())
+    }
+
+    /// Builds a [Behavior::WhenAll] from any iterator of behaviors.
+    pub fn when_all<I: IntoIterator<Item = Behavior<A>>>(children: I) -> Behavior<A> {
+        Behavior::WhenAll(children.into_iter().collect())
+    }
+
+    /// Builds a [Behavior::WhenAny] from any iterator of behaviors.
+    pub fn when_any<I: IntoIterator<Item = Behavior<A>>>(children: I) -> Behavior<A> {
+        Behavior::WhenAny(children.into_iter().collect())
+    }
+
+    /// Hash of the shape of the behavior tree: the kinds of the nodes and
+    /// their number of children.
+    ///
+    /// The hash is stable across runs and platforms (64-bit FNV-1a over a
+    /// fixed encoding of the tree), so it can be stored and compared to the
+    /// one of a reloaded tree, e.g. to decide whether the runtime state can
+    /// be kept. Actions and node parameters such as durations are not
+    /// included, see [Behavior::structure_hash_with_payloads] for that.
+    pub fn structure_hash(&self) -> u64 {
+        let mut hasher = Fnv::new();
+        hash_behavior(self, &mut hasher, None);
+        hasher.finish()
+    }
+
+    /// Same as [Behavior::structure_hash], but including the actions and the
+    /// parameters of the nodes, e.g. the duration of a `Period`.
+    ///
+    /// The hash is only as stable as the `Hash` implementation of the action
+    /// type, which is the case for derived implementations over primitive
+    /// types and strings.
+    pub fn structure_hash_with_payloads(&self) -> u64
+        where
+            A: Hash,
+    {
+        let mut hasher = Fnv::new();
+        hash_behavior(self, &mut hasher, Some(&|action: &A, hasher: &mut Fnv| action.hash(hasher)));
+        hasher.finish()
+    }
+
+    /// Whether the behavior can ever return `Success` or `Failure`, assuming
+    /// that every action can return either.
+    ///
+    /// A tree made only of loops and `Forever` branches keeps running forever,
+    /// which is sometimes a bug. See [Behavior::trapping_subtrees] for where
+    /// it gets stuck.
+    pub fn can_terminate(&self) -> bool {
+        outcomes(self).can_terminate()
+    }
+
+    /// List the paths of the innermost behaviors that keep the behavior from
+    /// ever terminating, see [Behavior::can_terminate]. Empty if it can terminate.
+    ///
+    /// The paths are child indices from the root, like the paths of `BT::active_timers`.
+    pub fn trapping_subtrees(&self) -> Vec<Vec<usize>> {
+        let mut paths = vec![];
+        trapping_subtrees(self, &mut vec![], &mut paths);
+        paths
+    }
+
+    /// Checks every action of the behavior with `validate`, e.g. that the
+    /// entities the actions refer to exist and their parameters are in
+    /// range, to catch errors in the data when the tree is loaded rather
+    /// than once it runs into them. See also `BT::with_validation`.
+    ///
+    /// Returns the errors of all the actions that are not valid, in depth
+    /// first order, with the paths of the actions, like the paths of
+    /// `BT::active_timers`. Unresolved [Behavior::Subtree]s are not checked.
+    ///
+    /// ```
+    /// use bonsai_bt::{Action, Behavior, InvalidAction, Period, Sequence};
+    ///
+    /// let behavior: Behavior<f64> = Sequence(vec![Action(0.5), Period(1.0), Action(2.0)]);
+    /// let errors = behavior
+    ///     .validate_actions(|speed| match speed {
+    ///         s if *s <= 1.0 => Ok(()),
+    ///         s => Err(format!("speed {} is above 1", s)),
+    ///     })
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     errors,
+    ///     vec![InvalidAction {
+    ///         path: vec![2],
+    ///         message: "speed 2 is above 1".to_string()
+    ///     }]
+    /// );
+    /// ```
+    pub fn validate_actions<F>(&self, mut validate: F) -> Result<(), Vec<InvalidAction>>
+        where
+            F: FnMut(&A) -> Result<(), String>,
+    {
+        let mut errors = vec![];
+        self.collect_invalid_actions(&mut validate, &mut vec![], &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn collect_invalid_actions<F>(&self, validate: &mut F, path: &mut Vec<usize>, errors: &mut Vec<InvalidAction>)
+        where
+            F: FnMut(&A) -> Result<(), String>,
+    {
+        match self {
+            Behavior::Action(action) => {
+                if let Err(message) = validate(action) {
+                    errors.push(InvalidAction {
+                        path: path.clone(),
+                        message,
+                    })
+                }
+            }
+            Behavior::Named(_, ev) | Behavior::Tagged(_, ev) => ev.collect_invalid_actions(validate, path, errors),
+            _ => {
+                for (i, child) in self.children().into_iter().enumerate() {
+                    path.push(i);
+                    child.collect_invalid_actions(validate, path, errors);
+                    path.pop();
+                }
+            }
+        }
+    }
+
+    /// Returns a copy of the behavior with the subtree at `path` replaced by `subtree`.
+    ///
+    /// The path is made of child indices from the root, like the paths of
+    /// `BT::active_timers`. Names and tags do not add a level to the path,
+    /// and are replaced along with the subtree they wrap.
+    ///
+    /// ```
+    /// use bonsai_bt::{Action, Behavior, Select, Sequence};
+    ///
+    /// let base: Behavior<&str> = Sequence(vec![Action("aim"), Select(vec![Action("shoot"), Action("throw")])]);
+    /// let sniper = base.with_subtree_at(&[1, 0], Action("snipe")).unwrap();
+    /// assert_eq!(sniper, Sequence(vec![Action("aim"), Select(vec![Action("snipe"), Action("throw")])]));
+    /// assert!(base.with_subtree_at(&[2], Action("snipe")).is_err());
+    /// ```
+    pub fn with_subtree_at(&self, path: &[usize], subtree: Behavior<A>) -> Result<Behavior<A>, InvalidPath>
+        where
+            A: Clone,
+    {
+        let mut behavior = self.clone();
+        let mut node = &mut behavior;
+        for &i in path {
+            node = node
+                .children_mut()
+                .into_iter()
+                .nth(i)
+                .ok_or_else(|| InvalidPath(path.to_vec()))?;
+        }
+        *node = subtree;
+        Ok(behavior)
+    }
+
+    /// Returns a copy of the behavior with the behaviors of the composite
+    /// node at `path` replaced by what `f` makes of them, e.g. to try out
+    /// another order of the behaviors of a selector.
+    ///
+    /// The path is the same as for [Behavior::with_subtree_at], and a name
+    /// or a tag around the node is kept. The composite nodes are those with
+    /// a list of behaviors of their own, such as `Sequence`, `Select` or
+    /// `WhenAll`, but not the body of a `While`, nor `RoleParallel` and
+    /// `ResourceParallel`, whose behaviors come with a role or a cost. The
+    /// list `f` returns must not be empty, as no composite node can run
+    /// without behaviors.
+    ///
+    /// ```
+    /// use bonsai_bt::{Action, Behavior, MapChildrenError, Select, Sequence};
+    ///
+    /// let base: Behavior<&str> = Sequence(vec![Action("aim"), Select(vec![Action("shoot"), Action("throw")])]);
+    /// let thrower = base
+    ///     .map_children(&[1], |mut behaviors| {
+    ///         behaviors.reverse();
+    ///         behaviors
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(thrower, Sequence(vec![Action("aim"), Select(vec![Action("throw"), Action("shoot")])]));
+    /// assert_eq!(base.map_children(&[0], |behaviors| behaviors), Err(MapChildrenError::NotComposite(vec![0])));
+    /// ```
+    pub fn map_children<F>(&self, path: &[usize], f: F) -> Result<Behavior<A>, MapChildrenError>
+        where
+            A: Clone,
+            F: FnOnce(Vec<Behavior<A>>) -> Vec<Behavior<A>>,
+    {
+        let mut behavior = self.clone();
+        let mut node = &mut behavior;
+        for &i in path {
+            node = node
+                .children_mut()
+                .into_iter()
+                .nth(i)
+                .ok_or_else(|| MapChildrenError::NotComposite(path.to_vec()))?;
+        }
+        let behaviors = node
+            .composite_behaviors_mut()
+            .ok_or_else(|| MapChildrenError::NotComposite(path.to_vec()))?;
+        *behaviors = f(std::mem::take(behaviors));
+        if behaviors.is_empty() {
+            return Err(MapChildrenError::NoBehaviors(path.to_vec()));
+        }
+        Ok(behavior)
+    }
+
+    // The list of behaviors of a composite node, with `Named` and `Tagged`
+    // seen through, see `map_children`.
+    fn composite_behaviors_mut(&mut self) -> Option<&mut Vec<Behavior<A>>> {
+        match self {
+            Behavior::Named(_, ev) | Behavior::Tagged(_, ev) => ev.composite_behaviors_mut(),
+            Behavior::Select(seq)
+            | Behavior::Sequence(seq)
+            | Behavior::WhenAll(seq)
+            | Behavior::WhenAny(seq)
+            | Behavior::After(seq)
+            | Behavior::TimeBudgetSequence(_, seq)
+            | Behavior::CooldownSelect(_, seq)
+            | Behavior::ImpatientSelect(_, seq)
+            | Behavior::RoundRobinParallel(_, seq)
+            | Behavior::EarlySuccessParallel(_, seq)
+            | Behavior::OrderedParallel(seq)
+            | Behavior::SelectReactive(seq)
+            | Behavior::AdaptiveSelect(_, _, seq)
+            | Behavior::TieBreakSelect(_, seq) => Some(seq),
+            _ => None,
+        }
+    }
+
+    /// Returns a copy of the behavior with every [Behavior::Subtree] replaced
+    /// by its definition in `subtrees`, including the subtrees referred to by
+    /// the definitions.
+    ///
+    /// Fails if a subtree has no definition, or if a definition refers to
+    /// itself, which would make the tree infinitely deep. Also fails once the
+    /// resolved tree has more than [MAX_RESOLVED_NODES] nodes, as subtrees
+    /// referring to others several times expand exponentially, see
+    /// [Behavior::resolve_subtrees_within] for another limit.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use bonsai_bt::{Action, Behavior, Select, Sequence, SubtreeError};
+    ///
+    /// let mut subtrees = HashMap::new();
+    /// subtrees.insert("flee".to_string(), Sequence(vec![Action("turn"), Action("run")]));
+    ///
+    /// let guard: Behavior<&str> = Select(vec![Action("fight"), Behavior::Subtree("flee".to_string())]);
+    /// let resolved = guard.resolve_subtrees(&subtrees).unwrap();
+    /// assert_eq!(resolved, Select(vec![Action("fight"), Sequence(vec![Action("turn"), Action("run")])]));
+    ///
+    /// subtrees.insert("panic".to_string(), Select(vec![Behavior::Subtree("panic".to_string())]));
+    /// let panic: Behavior<&str> = Behavior::Subtree("panic".to_string());
+    /// assert!(matches!(panic.resolve_subtrees(&subtrees), Err(SubtreeError::Recursive(_))));
+    /// ```
+    pub fn resolve_subtrees(&self, subtrees: &HashMap<String, Behavior<A>>) -> Result<Behavior<A>, SubtreeError>
+        where
+            A: Clone,
+    {
+        self.resolve_subtrees_within(subtrees, MAX_RESOLVED_NODES)
+    }
+
+    /// Same as [Behavior::resolve_subtrees], but fails once the resolved tree
+    /// has more than `max_nodes` nodes, including `Named` and `Tagged`.
+    ///
+    /// The nodes are counted while the subtrees are inlined, so resolving
+    /// stops as soon as the limit is exceeded.
+    pub fn resolve_subtrees_within(
+        &self,
+        subtrees: &HashMap<String, Behavior<A>>,
+        max_nodes: usize,
+    ) -> Result<Behavior<A>, SubtreeError>
+        where
+            A: Clone,
+    {
+        let mut behavior = self.clone();
+        let mut resolver = Resolver {
+            subtrees,
+            stack: vec![],
+            nodes: 0,
+            max_nodes,
+        };
+        resolver.resolve(&mut behavior)?;
+        Ok(behavior)
+    }
+
+    // Fails with the key of the first unresolved subtree of the behavior.
+    pub(crate) fn check_resolved(&self) -> Result<(), SubtreeError> {
+        match self {
+            Behavior::Subtree(key) => Err(SubtreeError::Unknown(key.clone())),
+            Behavior::Named(_, ev) | Behavior::Tagged(_, ev) => ev.check_resolved(),
+            _ => self.children().into_iter().try_for_each(|child| child.check_resolved()),
+        }
+    }
+
+    // Fails with the path of the first node with a parameter it cannot run with.
+    pub(crate) fn check_parameters(&self) -> Result<(), InvalidParameter> {
+        check_parameters(self, &mut vec![])
+    }
+
+    // The children of the behavior, in the order they are indexed by in node paths.
+    //
+    // `Named` and `Tagged` have their behavior as the only child, but do not
+    // count as a level in node paths.
+    pub(crate) fn children(&self) -> Vec<&Behavior<A>> {
+        match self {
+            Behavior::Period(_) | Behavior::Forever | Behavior::Action(_) | Behavior::Subtree(_) => vec![],
+            Behavior::Invert(ev)
+            | Behavior::AlwaysSucceed(ev)
+            | Behavior::SharedCooldown(_, _, ev)
+            | Behavior::Named(_, ev)
+            | Behavior::RestartOnFailure(ev)
+            | Behavior::Seeded(_, ev)
+            | Behavior::Tagged(_, ev) => vec![ev],
+            Behavior::If(condition, success, failure) => vec![condition, success, failure],
+            Behavior::IfThen(condition, success) | Behavior::IntervalGuard(_, condition, success) => {
+                vec![condition, success]
+            }
+            Behavior::WithInit(init, body) => vec![init, body],
+            Behavior::DoWhile(body, condition) => vec![body, condition],
+            Behavior::Finally(body, finalizer) => vec![body, finalizer],
+            Behavior::WithTimeout(_, ev, on_timeout) => vec![ev, on_timeout],
+            Behavior::While(condition, seq)
+            | Behavior::RepeatSequence(condition, seq)
+            | Behavior::BoundedWhile(_, condition, seq) => std::iter::once(&**condition).chain(seq).collect(),
+            Behavior::Select(seq)
+            | Behavior::Sequence(seq)
+            | Behavior::WhenAll(seq)
+            | Behavior::WhenAny(seq)
+            | Behavior::After(seq)
+            | Behavior::TimeBudgetSequence(_, seq)
+            | Behavior::CooldownSelect(_, seq)
+            | Behavior::ImpatientSelect(_, seq)
+            | Behavior::RoundRobinParallel(_, seq)
+            | Behavior::EarlySuccessParallel(_, seq)
+            | Behavior::OrderedParallel(seq)
+            | Behavior::SelectReactive(seq)
+            | Behavior::AdaptiveSelect(_, _, seq)
+            | Behavior::TieBreakSelect(_, seq) => seq.iter().collect(),
+            Behavior::RoleParallel(all) => all.iter().map(|(_, b)| b).collect(),
+            Behavior::ResourceParallel(_, all) => all.iter().map(|(_, b)| b).collect(),
+        }
+    }
+
+    // Same as `children`, but mutable, and with `Named` and `Tagged` seen through.
+    fn children_mut(&mut self) -> Vec<&mut Behavior<A>> {
+        match self {
+            Behavior::Period(_) | Behavior::Forever | Behavior::Action(_) | Behavior::Subtree(_) => vec![],
+            Behavior::Named(_, ev) | Behavior::Tagged(_, ev) => ev.children_mut(),
+            Behavior::Invert(ev)
+            | Behavior::AlwaysSucceed(ev)
+            | Behavior::SharedCooldown(_, _, ev)
+            | Behavior::RestartOnFailure(ev)
+            | Behavior::Seeded(_, ev) => vec![ev],
+            Behavior::If(condition, success, failure) => vec![condition, success, failure],
+            Behavior::IfThen(condition, success) | Behavior::IntervalGuard(_, condition, success) => {
+                vec![condition, success]
+            }
+            Behavior::WithInit(init, body) => vec![init, body],
+            Behavior::DoWhile(body, condition) => vec![body, condition],
+            Behavior::Finally(body, finalizer) => vec![body, finalizer],
+            Behavior::WithTimeout(_, ev, on_timeout) => vec![ev, on_timeout],
+            Behavior::While(condition, seq)
+            | Behavior::RepeatSequence(condition, seq)
+            | Behavior::BoundedWhile(_, condition, seq) => std::iter::once(&mut **condition).chain(seq).collect(),
+            Behavior::Select(seq)
+            | Behavior::Sequence(seq)
+            | Behavior::WhenAll(seq)
+            | Behavior::WhenAny(seq)
+            | Behavior::After(seq)
+            | Behavior::TimeBudgetSequence(_, seq)
+            | Behavior::CooldownSelect(_, seq)
+            | Behavior::ImpatientSelect(_, seq)
+            | Behavior::RoundRobinParallel(_, seq)
+            | Behavior::EarlySuccessParallel(_, seq)
+            | Behavior::OrderedParallel(seq)
+            | Behavior::SelectReactive(seq)
+            | Behavior::AdaptiveSelect(_, _, seq)
+            | Behavior::TieBreakSelect(_, seq) => seq.iter_mut().collect(),
+            Behavior::RoleParallel(all) => all.iter_mut().map(|(_, b)| b).collect(),
+            Behavior::ResourceParallel(_, all) => all.iter_mut().map(|(_, b)| b).collect(),
+        }
+    }
+
+    // Collects the paths of the actions tagged with `tag`.
+    pub(crate) fn tagged_actions(&self, tag: &str, tagged: bool, path: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        match self {
+            Behavior::Action(_) => {
+                if tagged {
+                    paths.push(path.clone())
+                }
+            }
+            Behavior::Named(_, ev) => ev.tagged_actions(tag, tagged, path, paths),
+            Behavior::Tagged(t, ev) => ev.tagged_actions(tag, tagged || t == tag, path, paths),
+            _ => {
+                for (i, child) in self.children().into_iter().enumerate() {
+                    path.push(i);
+                    child.tagged_actions(tag, tagged, path, paths);
+                    path.pop();
+                }
+            }
+        }
+    }
+}
+
+/// The error returned by [Behavior::with_subtree_at] when there is no subtree at the path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidPath(pub Vec<usize>);
+
+impl fmt::Display for InvalidPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no subtree at path {:?}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidPath {}
+
+/// The error returned by [Behavior::map_children].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MapChildrenError {
+    /// There is no composite node at this path.
+    NotComposite(Vec<usize>),
+    /// The composite node at this path was left without behaviors.
+    NoBehaviors(Vec<usize>),
+}
+
+impl fmt::Display for MapChildrenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapChildrenError::NotComposite(path) => write!(f, "no composite node at path {:?}", path),
+            MapChildrenError::NoBehaviors(path) => write!(f, "no behaviors left for the node at path {:?}", path),
+        }
+    }
+}
+
+impl std::error::Error for MapChildrenError {}
+
+/// An action found not valid by [Behavior::validate_actions].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidAction {
+    /// Path of the action, see [Behavior::validate_actions].
+    pub path: Vec<usize>,
+    /// Why the action is not valid.
+    pub message: String,
+}
+
+impl fmt::Display for InvalidAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid action at path {:?}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for InvalidAction {}
+
+/// A parameter of a behavior which the tree cannot run with, as found by
+/// `State::try_new`, `BT::try_new` and `BtBuilder::build`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvalidParameter {
+    /// The `AdaptiveSelect` at this path has a learning rate outside of `[0, 1]`.
+    LearningRate(Vec<usize>),
+    /// The `ResourceParallel` at this path has a cost that is infinite, NaN
+    /// or negative.
+    Cost(Vec<usize>),
 }
 
+impl fmt::Display for InvalidParameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvalidParameter::LearningRate(path) => {
+                write!(f, "learning rate not between 0 and 1 at path {:?}", path)
+            }
+            InvalidParameter::Cost(path) => write!(f, "cost infinite, NaN or negative at path {:?}", path),
+        }
+    }
+}
+
+impl std::error::Error for InvalidParameter {}
+
+fn check_parameters<A>(behavior: &Behavior<A>, path: &mut Vec<usize>) -> Result<(), InvalidParameter> {
+    match behavior {
+        // Names and tags do not add a level to the path.
+        Behavior::Named(_, ev) | Behavior::Tagged(_, ev) => return check_parameters(ev, path),
+        Behavior::AdaptiveSelect(_, learning_rate, _) if !(0.0..=1.0).contains(learning_rate) => {
+            return Err(InvalidParameter::LearningRate(path.clone()));
+        }
+        Behavior::ResourceParallel(_, all) if all.iter().any(|(cost, _)| !cost.is_finite() || *cost < 0.0) => {
+            return Err(InvalidParameter::Cost(path.clone()));
+        }
+        _ => {}
+    }
+    for (i, child) in behavior.children().into_iter().enumerate() {
+        path.push(i);
+        check_parameters(child, path)?;
+        path.pop();
+    }
+    Ok(())
+}
+
+/// The number of nodes a tree can have once its subtrees are resolved by
+/// [Behavior::resolve_subtrees].
+pub const MAX_RESOLVED_NODES: usize = 100_000;
+
+// Inlines the definitions of the subtrees of a behavior.
+struct Resolver<'a, A> {
+    subtrees: &'a HashMap<String, Behavior<A>>,
+    // The keys of the definitions being resolved, outermost first.
+    stack: Vec<String>,
+    // The nodes of the resolved tree so far.
+    nodes: usize,
+    max_nodes: usize,
+}
+
+impl<'a, A: Clone> Resolver<'a, A> {
+    fn resolve(&mut self, behavior: &mut Behavior<A>) -> Result<(), SubtreeError> {
+        if let Behavior::Subtree(key) = behavior {
+            let key = key.clone();
+            if self.stack.contains(&key) {
+                self.stack.push(key);
+                return Err(SubtreeError::Recursive(self.stack.clone()));
+            }
+            *behavior = self.subtrees.get(&key).ok_or_else(|| SubtreeError::Unknown(key.clone()))?.clone();
+            self.stack.push(key);
+            self.resolve(behavior)?;
+            self.stack.pop();
+            return Ok(());
+        }
+        self.nodes += 1;
+        if self.nodes > self.max_nodes {
+            return Err(SubtreeError::TooManyNodes);
+        }
+        match behavior {
+            Behavior::Named(_, ev) | Behavior::Tagged(_, ev) => self.resolve(ev),
+            _ => behavior.children_mut().into_iter().try_for_each(|child| self.resolve(child)),
+        }
+    }
+}
+
+/// The error returned by [Behavior::resolve_subtrees] and `BT::with_subtrees`,
+/// and by `State::try_new` and `BT::try_new` for an unresolved subtree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubtreeError {
+    /// There is no definition for the subtree with this key.
+    Unknown(String),
+    /// The definition of a subtree refers to itself. Holds the keys of the
+    /// subtrees leading back to it, starting and ending with its own.
+    Recursive(Vec<String>),
+    /// The tree has more nodes than allowed once its subtrees are resolved.
+    TooManyNodes,
+}
+
+impl fmt::Display for SubtreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SubtreeError::Unknown(key) => write!(f, "no definition for subtree {:?}", key),
+            SubtreeError::Recursive(keys) => write!(f, "recursive subtree: {}", keys.join(" -> ")),
+            SubtreeError::TooManyNodes => write!(f, "too many nodes once the subtrees are resolved"),
+        }
+    }
+}
+
+impl std::error::Error for SubtreeError {}
+
 #[cfg(test)]
 mod tests {
-    use crate::Behavior::{self, Action, Sequence, Period, Forever, WhenAny, While};
+    use super::{InvalidAction, InvalidPath, MapChildrenError};
+    use crate::Behavior::{self, Action, Invert, Select, Sequence, Period, Forever, WhenAny, While};
 
-    #[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+    #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
     pub(crate) enum EnemyAction {
         /// Circles forever around target pos.
         Circling,
@@ -167,6 +1164,160 @@ mod tests {
         let _bt_deserialized: Behavior<EnemyAction> = serde_json::from_str(&bt_serialized).unwrap();
     }
 
+    #[test]
+    fn test_behavior_equality() {
+        let behavior = Sequence(vec![
+            Invert(Box::new(Action(EnemyAction::PlayerWithinDistance(50.0)))),
+            While(Box::new(Period(5.0)), vec![Action(EnemyAction::Circling)]),
+        ]);
+
+        assert_eq!(behavior.clone(), behavior);
+        assert_eq!(
+            behavior,
+            Sequence(vec![
+                Invert(Box::new(Action(EnemyAction::PlayerWithinDistance(50.0)))),
+                While(Box::new(Period(5.0)), vec![Action(EnemyAction::Circling)]),
+            ])
+        );
+
+        // differs in a boxed child
+        assert_ne!(
+            behavior,
+            Sequence(vec![
+                Invert(Box::new(Action(EnemyAction::PlayerWithinDistance(10.0)))),
+                While(Box::new(Period(5.0)), vec![Action(EnemyAction::Circling)]),
+            ])
+        );
+        // differs in the number of children
+        assert_ne!(behavior, Sequence(vec![Action(EnemyAction::Circling)]));
+    }
+
+    #[test]
+    fn test_validate_actions() {
+        let attack = |d| Action(EnemyAction::AttackPlayer(d));
+        let behavior = While(
+            Box::new(Action(EnemyAction::PlayerWithinDistance(-1.0))),
+            vec![attack(0.1), Invert(Box::new(attack(-0.5))).named("miss")],
+        );
+        let errors = behavior.validate_actions(|action| match action {
+            EnemyAction::PlayerWithinDistance(d) | EnemyAction::AttackPlayer(d) if *d < 0.0 => {
+                Err(format!("negative value {}", d))
+            }
+            _ => Ok(()),
+        });
+        let error = |path: Vec<usize>, message: &str| InvalidAction {
+            path,
+            message: message.to_string(),
+        };
+        // all the errors are collected, and the name adds no level
+        assert_eq!(
+            errors,
+            Err(vec![error(vec![0], "negative value -1"), error(vec![2, 0], "negative value -0.5")])
+        );
+        assert_eq!(attack(0.1).validate_actions(|_| Ok(())), Ok(()));
+    }
+
+    #[test]
+    fn test_with_subtree_at() {
+        let patrol = While(
+            Box::new(Forever),
+            vec![
+                Action(EnemyAction::Circling),
+                Action(EnemyAction::FlyTowardPlayer).named("fly"),
+            ],
+        )
+        .named("patrol");
+
+        // the condition is at 0, and the names add no level
+        let wait = patrol.with_subtree_at(&[0], Period(5.0)).unwrap();
+        assert_eq!(
+            wait,
+            While(
+                Box::new(Period(5.0)),
+                vec![
+                    Action(EnemyAction::Circling),
+                    Action(EnemyAction::FlyTowardPlayer).named("fly"),
+                ],
+            )
+            .named("patrol")
+        );
+
+        // the name of the replaced subtree goes with it
+        let attack = patrol
+            .with_subtree_at(&[2], Action(EnemyAction::AttackPlayer(0.1)))
+            .unwrap();
+        assert_eq!(
+            attack,
+            While(
+                Box::new(Forever),
+                vec![Action(EnemyAction::Circling), Action(EnemyAction::AttackPlayer(0.1))],
+            )
+            .named("patrol")
+        );
+
+        // the empty path is the root
+        assert_eq!(patrol.with_subtree_at(&[], Forever), Ok(Forever));
+
+        assert_eq!(patrol.with_subtree_at(&[3], Forever), Err(InvalidPath(vec![3])));
+        assert_eq!(patrol.with_subtree_at(&[1, 0], Forever), Err(InvalidPath(vec![1, 0])));
+    }
+
+    #[test]
+    fn test_map_children() {
+        let attack = Sequence(vec![
+            Action(EnemyAction::FlyTowardPlayer),
+            Select(vec![
+                Action(EnemyAction::AttackPlayer(0.1)),
+                Action(EnemyAction::AttackPlayer(0.5)),
+            ])
+            .named("strike"),
+        ]);
+
+        // the name of the composite node is kept
+        let reversed = attack.map_children(&[1], |mut behaviors| {
+            behaviors.reverse();
+            behaviors
+        });
+        assert_eq!(
+            reversed,
+            Ok(Sequence(vec![
+                Action(EnemyAction::FlyTowardPlayer),
+                Select(vec![
+                    Action(EnemyAction::AttackPlayer(0.5)),
+                    Action(EnemyAction::AttackPlayer(0.1)),
+                ])
+                .named("strike"),
+            ]))
+        );
+
+        // the empty path is the root
+        let shortened = attack.map_children(&[], |behaviors| behaviors.into_iter().take(1).collect());
+        assert_eq!(shortened, Ok(Sequence(vec![Action(EnemyAction::FlyTowardPlayer)])));
+
+        let keep = |behaviors| behaviors;
+        assert_eq!(attack.map_children(&[0], keep), Err(MapChildrenError::NotComposite(vec![0])));
+        assert_eq!(attack.map_children(&[2], keep), Err(MapChildrenError::NotComposite(vec![2])));
+        let loop_body = While(Box::new(Forever), vec![Action(EnemyAction::Circling)]);
+        assert_eq!(loop_body.map_children(&[], keep), Err(MapChildrenError::NotComposite(vec![])));
+
+        // a composite node can not be left without behaviors
+        let removed = attack.map_children(&[1], |_| vec![]);
+        assert_eq!(removed, Err(MapChildrenError::NoBehaviors(vec![1])));
+    }
+
+    #[test]
+    fn test_serialize_named_behavior() {
+        let behavior = Sequence(vec![
+            Action(EnemyAction::Circling).named("circle"),
+            Action(EnemyAction::FlyTowardPlayer),
+        ])
+        .named("patrol");
+
+        let bt_serialized = serde_json::to_string(&behavior).unwrap();
+        let bt_deserialized: Behavior<EnemyAction> = serde_json::from_str(&bt_serialized).unwrap();
+        assert_eq!(bt_deserialized, behavior);
+    }
+
     #[test]
     fn test_deserialize_behavior() {
         let bt_json = r#"
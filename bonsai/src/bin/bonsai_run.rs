@@ -0,0 +1,121 @@
+//! Runs a behavior tree loaded from a JSON file, headless, logging the
+//! actions as they run.
+//!
+//! ```text
+//! cargo run --features cli --bin bonsai-run -- tree.json [--dt SECONDS] [--max-ticks N]
+//! ```
+//!
+//! The tree is made of the demo actions below, e.g.
+//!
+//! ```json
+//! {"Sequence": [{"Action": {"Say": "hello"}}, {"Period": 0.5}, {"Action": {"Busy": 3}}, {"Action": "Succeed"}]}
+//! ```
+
+use std::fs::File;
+use std::io::BufReader;
+
+use bonsai_bt::{ActionArgs, ActionData, Behavior, Event, Failure, Running, Status, Success, UpdateArgs, BT};
+
+/// Demo actions for trying out trees.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+enum DemoAction {
+    /// Prints the message and succeeds.
+    Say(String),
+    /// Keeps running for a number of ticks, then succeeds.
+    Busy(u32),
+    /// Succeeds.
+    Succeed,
+    /// Fails.
+    Fail,
+}
+
+struct Options {
+    path: String,
+    dt: f64,
+    max_ticks: usize,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let mut path = None;
+    let mut dt = 0.1;
+    let mut max_ticks = 1000;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dt" => {
+                let value = args.next().ok_or("missing value for --dt")?;
+                dt = value.parse().map_err(|_| format!("invalid --dt: {}", value))?;
+            }
+            "--max-ticks" => {
+                let value = args.next().ok_or("missing value for --max-ticks")?;
+                max_ticks = value.parse().map_err(|_| format!("invalid --max-ticks: {}", value))?;
+            }
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+    let path = path.ok_or("usage: bonsai-run <tree.json> [--dt SECONDS] [--max-ticks N]")?;
+    Ok(Options { path, dt, max_ticks })
+}
+
+fn load(path: &str) -> Result<Behavior<DemoAction>, String> {
+    let file = File::open(path).map_err(|e| format!("could not open {}: {}", path, e))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| format!("could not parse {}: {}", path, e))
+}
+
+// Runs the action, and logs it along with the time of the tick.
+fn dispatch(time: f64, args: ActionArgs<Event, DemoAction>, data: &mut ActionData) -> (Status, f64) {
+    let (status, dt) = match args.action {
+        DemoAction::Say(message) => {
+            println!("[{:8.3}] {}", time, message);
+            (Success, args.dt)
+        }
+        DemoAction::Busy(ticks) => {
+            let ticked = data.get_or_insert_with(|| 0u32);
+            *ticked += 1;
+            if *ticked >= *ticks {
+                (Success, args.dt)
+            } else {
+                (Running, 0.0)
+            }
+        }
+        DemoAction::Succeed => (Success, args.dt),
+        DemoAction::Fail => (Failure, args.dt),
+    };
+    println!("[{:8.3}] {:?}: {:?}", time, args.action, status);
+    (status, dt)
+}
+
+fn main() {
+    let loaded = parse_options().and_then(|options| load(&options.path).map(|behavior| (options, behavior)));
+    let (options, behavior) = match loaded {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+
+    let mut bt = match BT::try_new(behavior, ()) {
+        Ok(bt) => bt,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+    let e: Event = UpdateArgs { dt: options.dt }.into();
+    let mut status = Running;
+    let mut ticks = 0;
+    while status == Running && ticks < options.max_ticks {
+        let time = ticks as f64 * options.dt;
+        status = bt.tick_with_data(&e, &mut |args, data, _| dispatch(time, args, data)).0;
+        ticks += 1;
+    }
+
+    println!("{:?} after {} ticks", status, ticks);
+    std::process::exit(match status {
+        Success => 0,
+        Failure => 1,
+        Running => 3,
+    })
+}
@@ -1,10 +1,29 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use petgraph::dot::{Config, Dot};
 use petgraph::Graph;
 
-use crate::visualizer::{Mermaid, NodeType};
-use crate::{ActionArgs, Behavior, State, Status, UpdateEvent};
+use crate::builder::{BtBuilder, BuildError};
+use crate::cancel::CancelToken;
+use crate::debug_tree::{node_statuses, render_tree};
+use crate::limits::{check_limits, LimitError, Limits};
+use crate::reload::{compatible, reload_state};
+use crate::replay::EventLog;
+use crate::save::{BtState, StateMismatch};
+use crate::shared::{SequenceStep, SharedState};
+#[cfg(feature = "node-stats")]
+use crate::stats::{Histogram, NodeStats, SelectionInfo, SelectorDepth};
+use crate::template::BtTemplate;
+use crate::timers::{active_timers, TimerInfo};
+use crate::visualizer::{graph_paths, leaves_graph, Mermaid, NodeType};
+use crate::{
+    ActionArgs, ActionData, Behavior, Event, InvalidAction, Signal, State, Status, SubtreeError, UpdateArgs,
+    UpdateEvent,
+};
 
 /// A "blackboard" is a simple key/value storage shared by all the nodes of the Tree.
 ///
@@ -14,43 +33,272 @@ use crate::{ActionArgs, Behavior, State, Status, UpdateEvent};
 /// a blackboard, a messaging capability that allows nodes to share state in the behavior tree.
 ///
 /// An "entry" of the Blackboard is a key/value pair.
-#[derive(Clone, Debug)]
-pub struct BlackBoard<K>(K);
+///
+/// A blackboard can be forked cheaply, see [BlackBoard::fork]. Its data is
+/// shared through an `Arc`, so a blackboard is `Send` when the data is both
+/// `Send` and `Sync`. So is a `BT`, whose definition is shared the same way
+/// with its templates, when its actions are as well.
+///
+/// Entries of a blackboard can be given a time to live, see
+/// [BlackBoard::insert_with_ttl].
+pub struct BlackBoard<K>(Arc<K>, Option<fn(&mut Arc<K>, f64)>);
 
 impl<K> BlackBoard<K> {
+    pub(crate) fn new(data: K) -> Self {
+        BlackBoard(Arc::new(data), None)
+    }
+
+    /// Moves the expiring entries of the blackboard, if any, `dt` seconds
+    /// forward in time.
+    pub(crate) fn advance(&mut self, dt: f64) {
+        if let Some(expire) = self.1 {
+            expire(&mut self.0, dt);
+        }
+    }
+
     pub fn get_db(&self) -> &K {
         &self.0
     }
 
+    /// # Panics
+    ///
+    /// Panics if the data is shared with a fork, write to a forked
+    /// blackboard with [BlackBoard::make_mut] instead.
     pub fn get_db_mut(&mut self) -> &mut K {
-        &mut self.0
+        Arc::get_mut(&mut self.0).expect("blackboard is shared with a fork, write to it with make_mut")
+    }
+}
+
+impl<K: Clone> BlackBoard<K> {
+    /// Like `get_db_mut`, but copies the data first if it is shared with a
+    /// fork.
+    pub fn make_mut(&mut self) -> &mut K {
+        Arc::make_mut(&mut self.0)
+    }
+
+    /// Creates a copy-on-write snapshot of the blackboard.
+    ///
+    /// The fork shares the data with this blackboard until either of them
+    /// writes to it through `make_mut`, which makes the writer copy the
+    /// data first. This makes it cheap to fork many speculative futures,
+    /// e.g. when searching through the outcomes of a behavior tree.
+    ///
+    /// Cloning a blackboard (or a `BT`) forks it.
+    pub fn fork(&self) -> BlackBoard<K> {
+        BlackBoard(Arc::clone(&self.0), self.1)
+    }
+}
+
+/// The value of a blackboard entry which may expire, see
+/// [BlackBoard::insert_with_ttl].
+///
+/// The time to live is stored with the value, so writing a plain value over
+/// an entry, e.g. with `make_mut().insert(key, value.into())`, also stops it
+/// from expiring.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expiring<V> {
+    pub value: V,
+    /// Remaining time to live in seconds, or `None` if the value does not
+    /// expire.
+    pub ttl: Option<f64>,
+}
+
+impl<V> From<V> for Expiring<V> {
+    fn from(value: V) -> Self {
+        Expiring {
+            value,
+            ttl: None,
+        }
+    }
+}
+
+impl<V> BlackBoard<HashMap<String, Expiring<V>>> {
+    /// Value of the entry for `key`, or `None` if there is none or it has
+    /// expired.
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.get_db().get(key).map(|entry| &entry.value)
+    }
+
+    /// Remaining time to live in seconds of the entry for `key`, or `None`
+    /// if the entry does not expire or has expired.
+    pub fn ttl(&self, key: &str) -> Option<f64> {
+        self.get_db().get(key)?.ttl
+    }
+}
+
+impl<V: Clone> BlackBoard<HashMap<String, Expiring<V>>> {
+    /// Inserts an entry which expires after `ttl` seconds, e.g. the last seen
+    /// position of an enemy which is only worth chasing for a while.
+    ///
+    /// The time to live counts down with the delta time of the ticks of the
+    /// tree, and once it is used up the entry is removed. Inserting the key
+    /// again starts the countdown over, and [BlackBoard::keep] stops it.
+    pub fn insert_with_ttl<S: Into<String>>(&mut self, key: S, value: V, ttl: f64) {
+        self.1 = Some(expire::<V>);
+        let ttl = Some(ttl);
+        self.make_mut().insert(key.into(), Expiring { value, ttl });
+    }
+
+    /// Keeps the entry for `key` from expiring.
+    pub fn keep(&mut self, key: &str) {
+        if let Some(entry) = self.make_mut().get_mut(key) {
+            entry.ttl = None;
+        }
+    }
+}
+
+// Counts down the time to live of the expiring entries by `dt` seconds, and
+// removes the entries which have expired.
+fn expire<V: Clone>(data: &mut Arc<HashMap<String, Expiring<V>>>, dt: f64) {
+    if data.values().any(|entry| entry.ttl.is_some()) {
+        let data = Arc::make_mut(data);
+        for ttl in data.values_mut().filter_map(|entry| entry.ttl.as_mut()) {
+            *ttl -= dt;
+        }
+        data.retain(|_, entry| entry.ttl.map_or(true, |ttl| ttl > 0.0));
+    }
+}
+
+impl<K: Clone> Clone for BlackBoard<K> {
+    fn clone(&self) -> Self {
+        self.fork()
+    }
+}
+
+impl<K: Debug> Debug for BlackBoard<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("BlackBoard").field(self.get_db()).finish()
     }
 }
 
 /// The BT struct contains a compiled (immutable) version
 /// of the behavior and a blackboard key/value storage
+///
+/// Cloning a BT is cheap, as the clone shares the blackboard
+/// until either of the trees writes to it with [BlackBoard::make_mut].
+///
+/// The actions can emit events of type `O` to the application, [Signal]s
+/// unless the tree is built for another type with [BT::with_output].
 #[derive(Clone, Debug)]
-pub struct BT<A, K> {
+pub struct BT<A, K, O = Signal> {
     /// constructed behavior tree
     pub state: State<A>,
-    /// keep the initial state
-    initial_behavior: Behavior<A>,
+    /// keep the initial state, shared with the templates taken from the tree
+    initial_behavior: Arc<Behavior<A>>,
     /// blackboard
     bb: BlackBoard<K>,
+    /// state shared between nodes, e.g. cooldown timers
+    shared: SharedState<A>,
+    /// events the tree is ticked with, while recording
+    event_log: Option<EventLog>,
+    /// status of the root after the last tick, `None` until ticked
+    last_status: Option<Status>,
+    /// events emitted by the actions, until drained
+    output: Vec<O>,
 }
 
 impl<A: Clone + Debug, K: Debug> BT<A, K> {
+    /// Creates a tree running the behavior, with a blackboard holding `blackboard`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the behavior has an unresolved [Behavior::Subtree] or a
+    /// parameter the tree cannot run with, see [BT::try_new].
     pub fn new(behavior: Behavior<A>, blackboard: K) -> Self {
-        let backup_behavior = behavior.clone();
-        let bt = State::new(behavior);
+        Self::from_shared(Arc::new(behavior), blackboard)
+    }
+
+    /// Same as `new`, but with the behavior shared with other trees, see
+    /// [BtTemplate].
+    pub(crate) fn from_shared(behavior: Arc<Behavior<A>>, blackboard: K) -> Self {
+        let bt = State::new((*behavior).clone());
 
         Self {
             state: bt,
-            initial_behavior: backup_behavior,
-            bb: BlackBoard(blackboard),
+            initial_behavior: behavior,
+            bb: BlackBoard::new(blackboard),
+            shared: SharedState::new(),
+            event_log: None,
+            last_status: None,
+            output: vec![],
+        }
+    }
+
+    /// Starts building a tree with any of the checks of `with_subtrees`,
+    /// `with_limits` and `with_validation` together, see [BtBuilder].
+    pub fn builder<'a>(behavior: Behavior<A>, blackboard: K) -> BtBuilder<'a, A, K> {
+        BtBuilder::new(behavior, blackboard)
+    }
+
+    /// Same as `new`, but fails instead of panicking if the behavior has an
+    /// unresolved [Behavior::Subtree] or a parameter the tree cannot run
+    /// with, e.g. a deserialized tree.
+    pub fn try_new(behavior: Behavior<A>, blackboard: K) -> Result<Self, BuildError> {
+        behavior.check_resolved()?;
+        behavior.check_parameters()?;
+        Ok(Self::new(behavior, blackboard))
+    }
+
+    /// Same as `new`, but with the [Behavior::Subtree]s of the behavior
+    /// replaced by their definitions in `subtrees` first.
+    ///
+    /// Fails if a subtree has no definition or refers to itself, or if the
+    /// resolved tree is too large, see [Behavior::resolve_subtrees]. See
+    /// [BT::builder] to check the resolved tree against limits as well.
+    pub fn with_subtrees(
+        behavior: Behavior<A>,
+        blackboard: K,
+        subtrees: &HashMap<String, Behavior<A>>,
+    ) -> Result<Self, SubtreeError> {
+        Ok(Self::new(behavior.resolve_subtrees(subtrees)?, blackboard))
+    }
+
+    /// Same as `new`, but fails without building the tree if the behavior
+    /// exceeds `limits`, e.g. a deserialized tree with a huge `Sequence`.
+    /// See [BT::builder] to resolve subtrees or validate actions as well.
+    ///
+    /// ```
+    /// use bonsai_bt::{Action, LimitError, Limits, Sequence, BT};
+    ///
+    /// let limits = Limits { max_children: 2, ..Limits::default() };
+    /// let behavior = Sequence(vec![Action("aim"), Action("shoot"), Action("reload")]);
+    /// let error = BT::with_limits(behavior, (), limits).unwrap_err();
+    /// assert_eq!(error, LimitError::TooManyChildren { path: vec![], count: 3 });
+    /// ```
+    pub fn with_limits(behavior: Behavior<A>, blackboard: K, limits: Limits) -> Result<Self, LimitError> {
+        check_limits(&behavior, &limits)?;
+        Ok(Self::new(behavior, blackboard))
+    }
+
+    /// Same as `new`, but fails without building the tree if any action of
+    /// the behavior is not valid according to `validate`, with the errors
+    /// of all of them, see [Behavior::validate_actions]. See [BT::builder] to
+    /// resolve subtrees or check limits as well.
+    pub fn with_validation<F>(behavior: Behavior<A>, blackboard: K, validate: F) -> Result<Self, Vec<InvalidAction>>
+        where
+            F: FnMut(&A) -> Result<(), String>,
+    {
+        behavior.validate_actions(validate)?;
+        Ok(Self::new(behavior, blackboard))
+    }
+
+    /// Turns the tree into one whose actions emit events of type `O` to the
+    /// application instead of [Signal]s, see [BT::tick_with_output]. The
+    /// signals that were not drained yet are dropped.
+    pub fn with_output<O>(self) -> BT<A, K, O> {
+        BT {
+            state: self.state,
+            initial_behavior: self.initial_behavior,
+            bb: self.bb,
+            shared: self.shared,
+            event_log: self.event_log,
+            last_status: self.last_status,
+            output: vec![],
         }
     }
+}
 
+impl<A: Clone + Debug, K: Debug, O> BT<A, K, O> {
     /// Updates the cursor that tracks an event.
     ///
     /// The action need to return status and remaining delta time.
@@ -70,11 +318,224 @@ impl<A: Clone + Debug, K: Debug> BT<A, K> {
             F: FnMut(ActionArgs<E, A>, &mut BlackBoard<K>) -> (Status, f64),
             A: Debug,
     {
-        self.state.tick(e, &mut self.bb, f)
+        self.tick_with_data(e, &mut |args, _, blackboard| f(args, blackboard))
+    }
+
+    /// Same as `tick`, but also passes the closure the data the action node
+    /// keeps between ticks, see [ActionData].
+    pub fn tick_with_data<E, F>(&mut self, e: &E, f: &mut F) -> (Status, f64)
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut ActionData, &mut BlackBoard<K>) -> (Status, f64),
+            A: Debug,
+    {
+        if let Some(log) = &mut self.event_log {
+            log.record(e);
+        }
+        if let Some(dt) = e.update(|args| args.dt) {
+            self.bb.advance(dt);
+        }
+        let result = self.state.tick_with_data(e, &mut self.shared, &mut self.bb, f);
+        #[cfg(feature = "node-stats")]
+        self.shared.counters.record(result.0);
+        self.last_status = Some(result.0);
+        result
+    }
+
+    /// Same as `tick`, but evaluates each distinct action at most once per tick.
+    ///
+    /// When an action is ticked again during the same tick, e.g. a condition
+    /// shared by several branches of the tree, the result of its first
+    /// evaluation is reused instead of calling the closure again. The reused
+    /// result has the same status, and uses up the same amount of time from
+    /// the delta time it is given, or all of it if less is left. Actions are told apart by equality, so
+    /// e.g. `InRange(10.0)` and `InRange(20.0)` are evaluated separately.
+    ///
+    /// Only meant for actions that do not change anything when evaluated
+    /// more than once in a tick, such as conditions.
+    pub fn tick_memoized<E, F>(&mut self, e: &E, f: &mut F) -> (Status, f64)
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut BlackBoard<K>) -> (Status, f64),
+            A: Eq + Hash,
+    {
+        let mut memo: HashMap<A, (Status, f64)> = HashMap::new();
+        self.tick(e, &mut |args, blackboard| {
+            let (action, dt) = (args.action, args.dt);
+            if let Some(&(status, used)) = memo.get(action) {
+                return (status, (dt - used).max(0.0));
+            }
+            let (status, remaining_dt) = f(args, blackboard);
+            memo.insert(action.clone(), (status, dt - remaining_dt));
+            (status, remaining_dt)
+        })
+    }
+
+    /// Same as `tick`, but stops as soon as `token` is cancelled.
+    ///
+    /// Once the token is cancelled, no more actions are called: the actions
+    /// that remain to be ticked fail right away, and the rest of the tick
+    /// unwinds quickly. The tree is then reset with [BT::reset_bt], aborting
+    /// whatever was running, so actions holding on to resources should check
+    /// the token themselves.
+    /// The tick returns `Failure`, and the token tells it apart from a tree
+    /// that failed on its own. If the token is already cancelled, the tree is
+    /// reset without being ticked.
+    ///
+    /// The token stays cancelled until reset with [CancelToken::reset].
+    pub fn tick_cancellable<E, F>(&mut self, e: &E, token: &CancelToken, f: &mut F) -> (Status, f64)
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut BlackBoard<K>) -> (Status, f64),
+            A: Debug,
+    {
+        let dt = if token.is_cancelled() {
+            e.update(|args| args.dt).unwrap_or(0.0)
+        } else {
+            let (status, dt) = self.tick(e, &mut |args, blackboard| {
+                if token.is_cancelled() {
+                    return (Status::Failure, args.dt);
+                }
+                f(args, blackboard)
+            });
+            if !token.is_cancelled() {
+                return (status, dt);
+            }
+            dt
+        };
+        self.reset_bt();
+        self.last_status = Some(Status::Failure);
+        (Status::Failure, dt)
+    }
+
+    /// Same as `tick`, but stops the tick once `max_micros` microseconds of
+    /// wall-clock time have passed, e.g. to spread a heavy tree over several
+    /// frames.
+    ///
+    /// Once the budget is used up, no other node is ticked: the nodes that
+    /// have not been reached yet are left as they are, and count as `Running`
+    /// for the nodes above them, which have been ticked already. The next
+    /// tick goes on from there, as the nodes that were done before the budget
+    /// ran out have moved on. The root and the node ticked when the budget
+    /// runs out are never interrupted, so a tick can take longer than the
+    /// budget.
+    ///
+    /// Only the nodes that are reached see the delta time of a tick: a
+    /// time-based node such as `Period` that is not reached before the budget
+    /// runs out does not count the delta time of that tick.
+    pub fn tick_budgeted<E, F>(&mut self, e: &E, max_micros: u64, f: &mut F) -> (Status, f64)
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut BlackBoard<K>) -> (Status, f64),
+            A: Debug,
+    {
+        let deadline = Instant::now() + Duration::from_micros(max_micros);
+        self.shared.set_deadline(Some(deadline));
+        let result = self.tick(e, f);
+        self.shared.set_deadline(None);
+        result
+    }
+
+    /// Ticks the tree `n` times with a constant delta time `dt` in seconds,
+    /// and returns the status after each tick.
+    ///
+    /// Stops early if the tree succeeds or fails before `n` ticks, so the
+    /// last status returned is then the terminal one and there are fewer
+    /// than `n` statuses. The tree is not reset between the ticks, nor
+    /// after the last one.
+    pub fn tick_n<F>(&mut self, n: usize, dt: f64, f: &mut F) -> Vec<Status>
+        where
+            F: FnMut(ActionArgs<Event, A>, &mut BlackBoard<K>) -> (Status, f64),
+    {
+        let e: Event = UpdateArgs { dt }.into();
+        let mut statuses = Vec::with_capacity(n);
+        for _ in 0..n {
+            let (status, _) = self.tick(&e, f);
+            statuses.push(status);
+            if status != Status::Running {
+                break;
+            }
+        }
+        statuses
+    }
+
+    /// Same as `tick`, but with an external context passed on to the closure.
+    ///
+    /// Useful when the actions need access to e.g. the game world. The context
+    /// is borrowed for the duration of the tick instead of being captured by
+    /// the closure, so the same closure can be reused with different contexts.
+    #[inline]
+    pub fn tick_with_ctx<E, C, F>(&mut self, e: &E, ctx: &mut C, f: &mut F) -> (Status, f64)
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut BlackBoard<K>, &mut C) -> (Status, f64),
+            A: Debug,
+    {
+        self.tick(e, &mut |args, blackboard| f(args, blackboard, ctx))
+    }
+
+    /// Whether the tree is still running, i.e. its root returned `Running`
+    /// on the last tick, e.g. to drive it with `while bt.is_running() { ... }`.
+    ///
+    /// A tree that has not been ticked since it was created, reset or
+    /// restored counts as running, so that such a loop ticks it at least once.
+    pub fn is_running(&self) -> bool {
+        !self.is_complete()
+    }
+
+    /// Whether the root of the tree succeeded or failed on the last tick.
+    ///
+    /// `false` until the tree is ticked, and again once it is reset.
+    pub fn is_complete(&self) -> bool {
+        matches!(self.last_status, Some(Status::Success | Status::Failure))
+    }
+
+    /// Same as `tick`, but with a queue passed on to the closure for the
+    /// actions to emit events to the application, e.g. a sound to play,
+    /// instead of causing the effects themselves. The application applies
+    /// them after the tick, once drained with [BT::drain_events].
+    ///
+    /// The events are kept across ticks until they are drained, so none
+    /// are lost when the tree is ticked several times in between. Resetting
+    /// the tree does not drop them.
+    ///
+    /// ```
+    /// use bonsai_bt::{Action, Event, Success, UpdateArgs, BT};
+    ///
+    /// #[derive(Debug, PartialEq)]
+    /// enum Effect {
+    ///     PlaySound(&'static str),
+    /// }
+    ///
+    /// let mut bt = BT::new(Action("explosion"), ()).with_output::<Effect>();
+    /// let e: Event = UpdateArgs { dt: 0.1 }.into();
+    /// bt.tick_with_output(&e, &mut |args, _, output| {
+    ///     output.push(Effect::PlaySound(*args.action));
+    ///     (Success, args.dt)
+    /// });
+    /// assert_eq!(bt.drain_events(), vec![Effect::PlaySound("explosion")]);
+    /// assert!(bt.drain_events().is_empty());
+    /// ```
+    pub fn tick_with_output<E, F>(&mut self, e: &E, f: &mut F) -> (Status, f64)
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut BlackBoard<K>, &mut Vec<O>) -> (Status, f64),
+            A: Debug,
+    {
+        let mut output = std::mem::take(&mut self.output);
+        let result = self.tick_with_ctx(e, &mut output, f);
+        self.output = output;
+        result
+    }
+
+    /// Takes the events emitted by the actions so far, in the order they
+    /// were emitted, see [BT::tick_with_output].
+    pub fn drain_events(&mut self) -> Vec<O> {
+        std::mem::take(&mut self.output)
     }
 
     pub fn get_graph_instance(&self, root_node_type: NodeType<A>) -> Graph<NodeType<A>, u32> {
-        let behavior = self.initial_behavior.to_owned();
+        let behavior = (*self.initial_behavior).clone();
 
         let mut graph = Graph::<NodeType<A>, u32, petgraph::Directed>::new();
         let root_id = graph.add_node(root_node_type);
@@ -84,6 +545,24 @@ impl<A: Clone + Debug, K: Debug> BT<A, K> {
         graph
     }
 
+    /// Build a graph of only the actions of the behavior tree, with an edge
+    /// from each action to the actions that can follow it.
+    ///
+    /// The composite and decorator nodes are contracted:
+    ///
+    /// - a `Sequence` is linear, its last actions lead to the first actions of the next behavior
+    /// - a `Select` branches, whatever comes before it leads to each of its
+    ///   behaviors and each of them leads to whatever comes after it
+    /// - `If` branches after the condition, and parallel nodes such as `WhenAll`
+    ///   start all of their behaviors at once
+    /// - loops, e.g. the body of a `While`, lead from their last actions back to their first ones
+    ///
+    /// Only the flow on success is drawn, apart from the alternatives of a `Select`.
+    /// The root node leads to the actions the tree starts with.
+    pub fn get_leaves_graph_instance(&self) -> Graph<NodeType<A>, u32> {
+        leaves_graph((*self.initial_behavior).clone())
+    }
+
     /// Compile the behavior tree into a [graphviz](https://graphviz.org/) compatible [DiGraph](https://docs.rs/petgraph/latest/petgraph/graph/type.DiGraph.html).
     ///
     /// ```rust
@@ -117,6 +596,48 @@ impl<A: Clone + Debug, K: Debug> BT<A, K> {
         self.get_graphviz_with_graph_instance().0
     }
 
+    /// Render the behavior tree as a [graphviz](https://graphviz.org/) DOT string with default configuration.
+    ///
+    /// For custom configuration or attributes, build the graph with `get_graph_instance`
+    /// and wrap it in [Dot](https://docs.rs/petgraph/latest/petgraph/dot/struct.Dot.html) yourself.
+    pub fn to_dot(&self) -> String {
+        let graph = self.get_graph_instance(NodeType::Root);
+        format!("{:?}", Dot::with_config(&graph, &[Config::EdgeNoLabel]))
+    }
+
+    /// Render the behavior tree as a [mermaid](https://mermaid.js.org/) flowchart with default configuration.
+    ///
+    /// For custom configuration or attributes, build the graph with `get_graph_instance`
+    /// and wrap it in [Mermaid] yourself.
+    pub fn to_mermaid(&self) -> String {
+        let graph = self.get_graph_instance(NodeType::Root);
+        format!("{:?}", Mermaid::new(&graph))
+    }
+
+    /// Render the behavior tree as a [mermaid](https://mermaid.js.org/) flowchart with the nodes
+    /// colored by how often they have been activated, see [Mermaid::with_heatmap].
+    ///
+    /// The counts are those of [BT::node_stats], so that the nodes which have never run stand
+    /// out. A `Tagged` node is colored like its behavior, and the sequence drawn for the body of
+    /// a `While` is left uncolored, as neither is counted on its own.
+    ///
+    /// Only available with the `node-stats` feature.
+    #[cfg(feature = "node-stats")]
+    pub fn to_mermaid_heatmap(&self) -> String {
+        let graph = self.get_graph_instance(NodeType::Root);
+        format!("{:?}", Mermaid::new(&graph).with_heatmap(&*self.initial_behavior, &self.node_stats()))
+    }
+
+    /// Render only the actions of the behavior tree as a [mermaid](https://mermaid.js.org/)
+    /// flowchart, for a high-level overview of complex trees.
+    ///
+    /// Each action leads to the actions that can follow it, see
+    /// [BT::get_leaves_graph_instance] for how the other nodes are contracted.
+    pub fn to_mermaid_leaves_only(&self) -> String {
+        let graph = self.get_leaves_graph_instance();
+        format!("{:?}", Mermaid::new(&graph))
+    }
+
     pub(crate) fn get_graphviz_with_graph_instance(&mut self) -> (String, Graph<NodeType<A>, u32>) {
         let graph = self.get_graph_instance(NodeType::Root);
 
@@ -124,6 +645,35 @@ impl<A: Clone + Debug, K: Debug> BT<A, K> {
         (format!("{:?}", digraph), graph)
     }
 
+    /// Render the behavior tree as a [mermaid](https://mermaid.js.org/) flowchart with custom node labels.
+    ///
+    /// The closure is called for every node, and the label it returns replaces the
+    /// default one. Return `None` to keep the default label, e.g. for actions.
+    ///
+    /// ```rust
+    /// use bonsai_bt::{Action, NodeType, Select, Sequence, BT};
+    ///
+    /// let behavior = Select(vec![Sequence(vec![Action("aim"), Action("shoot")]), Action("flee")]);
+    /// let bt = BT::new(behavior, ());
+    ///
+    /// let mermaid = bt.to_mermaid_with_labels(|node| match node {
+    ///     NodeType::Sequence => Some("Do all in order".to_string()),
+    ///     NodeType::Select => Some("Try each until one works".to_string()),
+    ///     _ => None,
+    /// });
+    /// assert!(mermaid.contains("Do all in order"));
+    /// ```
+    pub fn to_mermaid_with_labels<F>(&self, describe: F) -> String
+        where
+            F: Fn(&NodeType<A>) -> Option<String>,
+    {
+        let graph = self.get_graph_instance(NodeType::Root).map(
+            |_, node| describe(node).unwrap_or_else(|| format!("{:?}", node)),
+            |_, edge| *edge,
+        );
+        format!("{}", Mermaid::new(&graph))
+    }
+
     pub fn get_mermaid(&mut self) -> String {
         self.get_mermaid_with_graph_instance().0
     }
@@ -145,6 +695,244 @@ impl<A: Clone + Debug, K: Debug> BT<A, K> {
         (format!("{:?}", digraph), graph)
     }
 
+    /// List the time-based nodes that are currently active, e.g. a `Period`
+    /// that is being waited on, with their elapsed and total durations.
+    ///
+    /// Only nodes on the currently running path of the tree are included, and
+    /// only while they have time left.
+    pub fn active_timers(&self) -> Vec<TimerInfo> {
+        let mut timers = vec![];
+        active_timers(&self.state, &self.shared, &mut vec![], &mut timers);
+        timers
+    }
+
+    /// The time in seconds until the soonest of the active timers runs out,
+    /// see [BT::active_timers], e.g. to sleep until the tree has to be ticked
+    /// again instead of ticking it at a fixed rate.
+    ///
+    /// `None` when no timer is active, in which case the tree only changes
+    /// on events. Running actions are not timers: a tree waiting on one still
+    /// has to be ticked for the action to be polled.
+    pub fn next_wakeup(&self) -> Option<f64> {
+        self.active_timers()
+            .iter()
+            .map(|timer| (timer.total - timer.elapsed).max(0.0))
+            .reduce(f64::min)
+    }
+
+    /// Print the behavior tree as an indented text tree, with each node
+    /// annotated by its runtime status, e.g. to log the state of the tree.
+    ///
+    /// The nodes on the active path are marked with `>` and are `Running`,
+    /// with the elapsed and total time of their timer if they have one, see
+    /// [BT::active_timers]. The behaviors that have terminated while their
+    /// parent is still running show their result, e.g. `Success` for those
+    /// before the running one in a `Sequence`, and the other nodes are
+    /// `Idle`. Once the tree has terminated, the root shows its result.
+    ///
+    /// ```
+    /// use bonsai_bt::{Action, Event, Period, Sequence, Success, UpdateArgs, BT};
+    ///
+    /// let mut bt = BT::new(Sequence(vec![Action("aim"), Period(1.0), Action("shoot")]), ());
+    /// let e: Event = UpdateArgs { dt: 0.3 }.into();
+    /// bt.tick(&e, &mut |args, _| (Success, args.dt));
+    ///
+    /// let expected = "\
+    /// > Sequence [Running]
+    ///     Action(\"aim\") [Success]
+    /// >   Period(1.0) [Running 0.3/1.0]
+    ///     Action(\"shoot\") [Idle]
+    /// ";
+    /// assert_eq!(bt.debug_tree(), expected);
+    /// ```
+    pub fn debug_tree(&self) -> String {
+        let mut statuses = HashMap::new();
+        node_statuses(&self.state, &mut vec![], &mut statuses);
+        if let Some(status @ (Status::Success | Status::Failure)) = self.last_status {
+            // The cursors are left where the tree terminated.
+            statuses.retain(|_, s| *s != Status::Running);
+            statuses.insert(vec![], status);
+        }
+        let graph = self.get_graph_instance(NodeType::Root);
+        render_tree(&graph, &graph_paths(&*self.initial_behavior), &statuses, &self.active_timers())
+    }
+
+    /// List the steps taken by sequences during the last tick, in the order
+    /// they were taken.
+    ///
+    /// A step is taken each time a `Sequence`, a `TimeBudgetSequence`, or the
+    /// loop body of a `While` or `RepeatSequence` completes one of its
+    /// behaviors, including the last one before a loop starts over, and each
+    /// time an `OrderedParallel` accepts the success of one. Useful for
+    /// showing progress, e.g. "Step 2 of 5". Sequences are told apart by
+    /// wrapping them in `Named`.
+    ///
+    /// The steps are only recorded once enabled with
+    /// [BT::record_sequence_steps], and the list is empty until then.
+    pub fn sequence_steps(&self) -> &[SequenceStep] {
+        self.shared.steps()
+    }
+
+    /// Starts or stops recording the steps listed by [BT::sequence_steps].
+    ///
+    /// Recording is off by default, as it costs an allocation per step.
+    pub fn record_sequence_steps(&mut self, enabled: bool) {
+        self.shared.record_steps(enabled);
+    }
+
+    /// Seeds the random number generator the random choices of the nodes
+    /// are drawn from, e.g. the order in which an `AdaptiveSelect` tries its
+    /// behaviors, so that a run can be reproduced.
+    ///
+    /// Trees that are not seeded all start from the same seed. Resetting the
+    /// tree does not reseed it.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.shared.set_seed(seed);
+    }
+
+    /// The estimated success rates of the behaviors of the
+    /// [Behavior::AdaptiveSelect] nodes with `key`, in the order the
+    /// behaviors are declared. `None` until one of the behaviors has been
+    /// tried, and again after the tree is reset.
+    pub fn success_estimates(&self, key: &str) -> Option<&[f64]> {
+        self.shared.success_estimates(key)
+    }
+
+    /// Sets the amount held by the resource pool `key` of the
+    /// [Behavior::ResourceParallel] nodes, e.g. to refill a budget of action
+    /// points at the start of each turn.
+    ///
+    /// The costs of the behaviors that are running have already been taken
+    /// out, and are given back to the new amount if they fail. The pools
+    /// are kept when the tree is reset.
+    pub fn set_resource_pool<S: Into<String>>(&mut self, key: S, amount: f64) {
+        self.shared.set_resource_pool(key, amount);
+    }
+
+    /// Amount left in the resource pool `key`, `0.0` if it was never set.
+    pub fn resource_pool(&self, key: &str) -> f64 {
+        self.shared.resource_pool(key)
+    }
+
+    /// List the paths of the actions tagged with `tag`, see [Behavior::Tagged].
+    ///
+    /// The paths are child indices from the root, like the paths of `BT::active_timers`.
+    pub fn actions_with_tag(&self, tag: &str) -> Vec<Vec<usize>> {
+        let mut paths = vec![];
+        self.initial_behavior.tagged_actions(tag, false, &mut vec![], &mut paths);
+        paths
+    }
+
+    /// How often each node of the tree has run since the tree was created,
+    /// sorted by path, e.g. to find branches that never run or run a lot.
+    ///
+    /// A node is counted as activated when it is ticked while not already
+    /// running. The counts are kept when the tree is reset. Nodes that have
+    /// never been ticked are left out.
+    ///
+    /// Only available with the `node-stats` feature, so that the counting
+    /// costs nothing otherwise.
+    #[cfg(feature = "node-stats")]
+    pub fn node_stats(&self) -> Vec<NodeStats> {
+        self.shared.counters.stats()
+    }
+
+    /// Why the selector at `path` chose the behavior it did during its last
+    /// activation, e.g. to find out why a fallback keeps being picked.
+    ///
+    /// The path is made of child indices from the root, like the paths of
+    /// `BT::active_timers`. A `Select`, `SelectReactive`, `CooldownSelect` or
+    /// `ImpatientSelect` records what happened to each of its behaviors as it tries them, and
+    /// the record is kept after the selector is done, until it is entered
+    /// again. `None` if there is no selector at `path` that has been ticked.
+    ///
+    /// Only available with the `node-stats` feature, like [BT::node_stats].
+    #[cfg(feature = "node-stats")]
+    pub fn last_selection(&self, path: &[usize]) -> Option<SelectionInfo> {
+        self.shared.counters.selection(path)
+    }
+
+    /// How many behaviors each selector tried before one succeeded, over the
+    /// session, sorted by path, e.g. to find selectors that would do better
+    /// trying their behaviors in another order.
+    ///
+    /// Selectors that have never succeeded are left out. The counts are kept
+    /// when the tree is reset.
+    ///
+    /// Only available with the `node-stats` feature, like [BT::node_stats].
+    #[cfg(feature = "node-stats")]
+    pub fn selector_depths(&self) -> Vec<SelectorDepth> {
+        self.shared.counters.depths()
+    }
+
+    /// How long each action has been running each time it completed,
+    /// bucketed into a [Histogram] per action, e.g. to find actions which
+    /// now and then take far longer than usual.
+    ///
+    /// The actions are identified by their paths, like the paths of
+    /// `BT::active_timers`. Actions that have never completed are left out,
+    /// and so are the activations of an action that was aborted. The
+    /// timings are kept when the tree is reset.
+    ///
+    /// Only available with the `node-stats` feature, like [BT::node_stats].
+    #[cfg(feature = "node-stats")]
+    pub fn action_timings(&self) -> HashMap<Vec<usize>, Histogram> {
+        self.shared.counters.timings()
+    }
+
+    /// Sets the upper bounds of the buckets of [BT::action_timings], in
+    /// increasing order, and clears the timings recorded so far.
+    ///
+    /// The default bounds are 0, 0.01, 0.1, 1, 10 and 60 seconds.
+    #[cfg(feature = "node-stats")]
+    pub fn set_timing_buckets(&mut self, bounds: Vec<f64>) {
+        self.shared.counters.set_timing_buckets(bounds);
+    }
+
+    /// Start recording the update events and the signals the tree is ticked
+    /// with into a new [EventLog], to replay them later with [crate::replay].
+    ///
+    /// Signals are recorded for event types which return them from
+    /// `UpdateEvent::signal`, as [Event] does.
+    ///
+    /// Every call to `tick`, and to the methods built on it, is recorded.
+    /// Resets and reloads of the tree are not, so a recorded session only
+    /// replays the same if the tree is not reset in between.
+    pub fn record_events(&mut self) {
+        self.event_log = Some(EventLog::new());
+    }
+
+    /// The events recorded since `record_events` was called, if recording.
+    pub fn event_log(&self) -> Option<&EventLog> {
+        self.event_log.as_ref()
+    }
+
+    /// Stop recording, and give back the recorded events.
+    pub fn take_event_log(&mut self) -> Option<EventLog> {
+        self.event_log.take()
+    }
+
+    /// The definition of the tree, without its runtime state and its
+    /// blackboard, to build fresh trees of the same kind from, see [BtTemplate].
+    pub fn template(&self) -> BtTemplate<A> {
+        BtTemplate::new(Arc::clone(&self.initial_behavior), self.shared.disabled_tags().clone())
+    }
+
+    /// Disable the behaviors tagged with `tag`, making them return `status`
+    /// right away instead of running, e.g. to turn off all combat behaviors
+    /// during a cutscene. Behaviors that are running when disabled are
+    /// stopped, and start over once enabled again.
+    ///
+    /// Disabled tags are kept when the tree is reset.
+    pub fn disable_tag(&mut self, tag: &str, status: Status) {
+        self.shared.disable_tag(tag, status);
+    }
+
+    /// Enable the behaviors tagged with `tag` again, see [BT::disable_tag].
+    pub fn enable_tag(&mut self, tag: &str) {
+        self.shared.enable_tag(tag);
+    }
+
     /// Retrieve a mutable reference to the blackboard for
     /// this Behavior Tree
     pub fn get_blackboard(&self) -> &BlackBoard<K> {
@@ -157,7 +945,7 @@ impl<A: Clone + Debug, K: Debug> BT<A, K> {
 
     /// Retrieve a mutable reference to the internal state
     /// of the Behavior Tree
-    pub fn get_state(bt: &mut BT<A, K>) -> &mut State<A> {
+    pub fn get_state(bt: &mut BT<A, K, O>) -> &mut State<A> {
         &mut bt.state
     }
 
@@ -171,22 +959,107 @@ impl<A: Clone + Debug, K: Debug> BT<A, K> {
     /// that the behavior has concluded and ticking the BT won't progress any further - then it could
     /// be desirable to return the BT to it's initial state at t=0.0 before it was ever ticked.
     ///
+    /// The behaviors left running in the background by `EarlySuccessParallel`
+    /// nodes are aborted along with the tree, so their finalizers still run.
+    ///
     /// PS! invoking reset_bt does not reset the Blackboard.
     pub fn reset_bt(&mut self) {
-        let initial_behavior = self.initial_behavior.to_owned();
-        self.state = State::new(initial_behavior)
+        let initial_behavior = (*self.initial_behavior).clone();
+        self.state.abort(&mut self.shared);
+        self.shared.abort_background();
+        self.state = State::new(initial_behavior);
+        self.shared.reset();
+        self.last_status = None;
     }
 
     pub fn reset_with_new_behavior(&mut self, behavior: Behavior<A>) {
-        self.state = State::new(behavior)
+        self.state.abort(&mut self.shared);
+        self.shared.abort_background();
+        self.state = State::new(behavior);
+        self.shared.reset();
+        self.last_status = None;
+    }
+
+    /// Replaces the behavior of the tree, keeping the runtime state of the
+    /// parts of the tree that did not change, e.g. when a designer edits the
+    /// tree file while the game is running.
+    ///
+    /// The new behavior is compared with the one given to `BT::new` or to the
+    /// last reload, subtree by subtree at the same paths, using
+    /// [Behavior::structure_hash_with_payloads]:
+    ///
+    /// - An unchanged subtree keeps its state, e.g. how long a `Period` has
+    ///   been waiting for.
+    /// - A node that only changed in its children keeps its own state, e.g.
+    ///   which behavior of a `Sequence` is running, and its running children
+    ///   are compared in turn.
+    /// - Any other subtree starts over.
+    ///
+    /// If the root node itself changed, the whole tree is reset, like with
+    /// [BT::reset_bt]. The blackboard is kept either way.
+    pub fn reload(&mut self, behavior: Behavior<A>)
+        where
+            A: Hash,
+    {
+        if compatible(&*self.initial_behavior, &behavior) {
+            reload_state(&mut self.state, &*self.initial_behavior, &behavior, &mut self.shared);
+        } else {
+            self.state.abort(&mut self.shared);
+            self.shared.abort_background();
+            self.state = State::new(behavior.clone());
+            self.shared.reset();
+            self.last_status = None;
+        }
+        self.initial_behavior = Arc::new(behavior);
+    }
+
+    /// The runtime state of the tree, e.g. which nodes are running and how
+    /// long they have been waiting for, to serialize e.g. into a save game
+    /// separately from the behavior and the blackboard.
+    ///
+    /// Restore it with [BT::restore_state].
+    pub fn save_state(&self) -> BtState<A>
+        where
+            A: Hash,
+    {
+        let structure_hash = self.initial_behavior.structure_hash_with_payloads();
+        BtState::new(structure_hash, &self.state, &self.shared)
+    }
+
+    /// Replaces the runtime state of the tree with one saved by
+    /// [BT::save_state], so that the tree goes on from where the saved one
+    /// was. The blackboard is kept.
+    ///
+    /// Fails without changing the tree if the state was saved from another
+    /// behavior, according to [Behavior::structure_hash_with_payloads]. The
+    /// current state is dropped without being aborted, so the finalizers of
+    /// the `Finally` behaviors that are running do not run.
+    pub fn restore_state(&mut self, saved: BtState<A>) -> Result<(), StateMismatch>
+        where
+            A: Hash,
+    {
+        let expected = self.initial_behavior.structure_hash_with_payloads();
+        if saved.structure_hash() != expected {
+            return Err(StateMismatch {
+                expected,
+                found: saved.structure_hash(),
+            });
+        }
+        let (state, shared) = saved.restore(&*self.initial_behavior);
+        self.state = state;
+        self.shared.restore(shared);
+        self.last_status = None;
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
+    use std::sync::Arc;
 
-    use super::BlackBoard;
+    use super::{BlackBoard, BT};
+    use crate::{Action, NodeType, Sequence, Period};
 
     #[test]
     fn test_bb() {
@@ -195,8 +1068,84 @@ mod tests {
         db.insert("win_width".to_string(), 10.0);
         db.insert("win_height".to_string(), 12.0);
 
-        let mut blackboard = BlackBoard(db);
+        let mut blackboard = BlackBoard::new(db);
         let win_width = blackboard.get_db().get("win_width").unwrap().to_owned();
         assert_eq!(win_width, 10.0);
     }
+
+    #[test]
+    fn test_bb_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<BlackBoard<HashMap<String, f32>>>();
+    }
+
+    #[test]
+    fn test_fork_bb() {
+        let mut db: HashMap<String, f32> = HashMap::new();
+        db.insert("win_width".to_string(), 10.0);
+
+        let mut blackboard = BlackBoard::new(db);
+        let mut fork = blackboard.fork();
+        assert!(Arc::ptr_eq(&blackboard.0, &fork.0));
+
+        // writing to the fork copies the data, leaving the original as is
+        fork.make_mut().insert("win_width".to_string(), 20.0);
+        assert!(!Arc::ptr_eq(&blackboard.0, &fork.0));
+        assert_eq!(blackboard.get_db()["win_width"], 10.0);
+        assert_eq!(fork.get_db()["win_width"], 20.0);
+
+        // writing to the original copies the data as well
+        let other = blackboard.fork();
+        blackboard.make_mut().insert("win_height".to_string(), 12.0);
+        assert!(!other.get_db().contains_key("win_height"));
+        assert_eq!(blackboard.get_db()["win_height"], 12.0);
+
+        // writing to data that is no longer shared needs no copy
+        let before = Arc::as_ptr(&blackboard.0);
+        blackboard.make_mut().insert("win_height".to_string(), 14.0);
+        assert_eq!(before, Arc::as_ptr(&blackboard.0));
+    }
+
+    #[test]
+    fn test_get_db_mut_without_clone() {
+        #[derive(Debug)]
+        struct Db {
+            count: u32,
+        }
+
+        let mut blackboard = BlackBoard::new(Db { count: 0 });
+        blackboard.get_db_mut().count += 1;
+        assert_eq!(blackboard.get_db().count, 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_get_db_mut_of_fork() {
+        let mut blackboard = BlackBoard::new(0);
+        let _fork = blackboard.fork();
+        *blackboard.get_db_mut() += 1;
+    }
+
+    #[test]
+    fn test_to_mermaid_and_dot() {
+        let behavior = Sequence(vec![Period(1.0), Action("inc")]);
+        let mut bt = BT::new(behavior, ());
+
+        let mermaid = bt.to_mermaid();
+        assert!(mermaid.starts_with("flowchart TD"));
+        assert!(mermaid.contains("Period(1.0)"));
+        assert_eq!(mermaid, bt.get_mermaid());
+
+        let labeled = bt.to_mermaid_with_labels(|node| match node {
+            NodeType::Sequence => Some("Do all in order".to_string()),
+            _ => None,
+        });
+        assert!(labeled.contains("[\"Do all in order\"]"));
+        assert!(labeled.contains("[\"Period(1.0)\"]"));
+        assert!(!labeled.contains("Sequence"));
+
+        let dot = bt.to_dot();
+        assert!(dot.starts_with("digraph"));
+        assert_eq!(dot, bt.get_graphviz());
+    }
 }
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+
+use crate::limits::{check_limits, LimitError, Limits};
+use crate::{Behavior, InvalidAction, InvalidParameter, SubtreeError, BT};
+
+type Validate<'a, A> = Box<dyn FnMut(&A) -> Result<(), String> + 'a>;
+
+/// Builds a `BT` with any of the checks of `BT::with_subtrees`,
+/// `BT::with_limits` and `BT::with_validation`, as returned by `BT::builder`.
+///
+/// The subtrees are resolved first, with the node limit applied while they
+/// are inlined, so that a tree which only grows too large once resolved is
+/// rejected before it is built. The resolved tree is then checked against
+/// the other limits, and its actions are validated.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use bonsai_bt::{Action, Behavior, BuildError, LimitError, Limits, Sequence, BT};
+///
+/// let mut subtrees = HashMap::new();
+/// subtrees.insert("attack".to_string(), Sequence(vec![Action("aim"), Action("shoot")]));
+/// let behavior = Sequence(vec![Behavior::Subtree("attack".to_string()), Action("reload")]);
+///
+/// let limits = Limits { max_nodes: 3, ..Limits::default() };
+/// let error = BT::builder(behavior.clone(), ()).subtrees(&subtrees).limits(limits).build().unwrap_err();
+/// assert_eq!(error, BuildError::Limit(LimitError::TooManyNodes));
+///
+/// let bt = BT::builder(behavior, ())
+///     .subtrees(&subtrees)
+///     .validation(|action: &&str| if action.is_empty() { Err("empty".to_string()) } else { Ok(()) })
+///     .build();
+/// assert!(bt.is_ok());
+/// ```
+pub struct BtBuilder<'a, A, K> {
+    behavior: Behavior<A>,
+    blackboard: K,
+    subtrees: Option<&'a HashMap<String, Behavior<A>>>,
+    limits: Limits,
+    validate: Option<Validate<'a, A>>,
+}
+
+impl<'a, A: Clone + Debug, K: Debug> BtBuilder<'a, A, K> {
+    pub(crate) fn new(behavior: Behavior<A>, blackboard: K) -> Self {
+        BtBuilder {
+            behavior,
+            blackboard,
+            subtrees: None,
+            limits: Limits::default(),
+            validate: None,
+        }
+    }
+
+    /// Replaces the [Behavior::Subtree]s of the behavior by their
+    /// definitions in `subtrees`, see [Behavior::resolve_subtrees].
+    pub fn subtrees(mut self, subtrees: &'a HashMap<String, Behavior<A>>) -> Self {
+        self.subtrees = Some(subtrees);
+        self
+    }
+
+    /// Rejects the tree if it exceeds `limits` once its subtrees are resolved.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Rejects the tree if any of its actions is not valid according to
+    /// `validate`, see [Behavior::validate_actions].
+    pub fn validation<F>(mut self, validate: F) -> Self
+        where
+            F: FnMut(&A) -> Result<(), String> + 'a,
+    {
+        self.validate = Some(Box::new(validate));
+        self
+    }
+
+    /// Builds the tree, or fails with the first check it does not pass.
+    ///
+    /// A behavior with unresolved subtrees fails with
+    /// [SubtreeError::Unknown], and one with a parameter the tree cannot run
+    /// with fails with [BuildError::Parameter], rather than panicking, as
+    /// with `BT::try_new`.
+    pub fn build(self) -> Result<BT<A, K>, BuildError> {
+        let behavior = match self.subtrees {
+            Some(subtrees) => {
+                let max_nodes = self.limits.max_nodes.min(crate::MAX_RESOLVED_NODES);
+                match self.behavior.resolve_subtrees_within(subtrees, max_nodes) {
+                    Err(SubtreeError::TooManyNodes) if self.limits.max_nodes == max_nodes => {
+                        return Err(BuildError::Limit(LimitError::TooManyNodes))
+                    }
+                    resolved => resolved?,
+                }
+            }
+            None => {
+                self.behavior.check_resolved()?;
+                self.behavior
+            }
+        };
+        check_limits(&behavior, &self.limits)?;
+        behavior.check_parameters()?;
+        if let Some(validate) = self.validate {
+            behavior.validate_actions(validate)?;
+        }
+        Ok(BT::new(behavior, self.blackboard))
+    }
+}
+
+/// The error returned by [BtBuilder::build], and by `State::try_new` and
+/// `BT::try_new`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// A subtree could not be resolved.
+    Subtree(SubtreeError),
+    /// The tree exceeds the limits.
+    Limit(LimitError),
+    /// A behavior has a parameter the tree cannot run with.
+    Parameter(InvalidParameter),
+    /// Some of the actions are not valid.
+    InvalidActions(Vec<InvalidAction>),
+}
+
+impl From<SubtreeError> for BuildError {
+    fn from(error: SubtreeError) -> Self {
+        BuildError::Subtree(error)
+    }
+}
+
+impl From<InvalidParameter> for BuildError {
+    fn from(error: InvalidParameter) -> Self {
+        BuildError::Parameter(error)
+    }
+}
+
+impl From<LimitError> for BuildError {
+    fn from(error: LimitError) -> Self {
+        BuildError::Limit(error)
+    }
+}
+
+impl From<Vec<InvalidAction>> for BuildError {
+    fn from(errors: Vec<InvalidAction>) -> Self {
+        BuildError::InvalidActions(errors)
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::Subtree(error) => write!(f, "{}", error),
+            BuildError::Limit(error) => write!(f, "{}", error),
+            BuildError::Parameter(error) => write!(f, "{}", error),
+            BuildError::InvalidActions(errors) => {
+                let messages: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                write!(f, "{}", messages.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
@@ -0,0 +1,94 @@
+use crate::Behavior;
+
+/// Starts a chain of conditions which reads like if-else-if, see [CondChain].
+pub fn cond_chain<A>() -> CondChain<A> {
+    CondChain {
+        branches: vec![],
+        otherwise: None,
+    }
+}
+
+/// Builds "if `A` then `X`, else if `B` then `Y`, else `Z`" out of nested
+/// [Behavior::If] nodes, instead of writing them out by hand.
+///
+/// The chain expands to one `If` per `when`, each one running the next one
+/// when its condition fails:
+///
+/// `If(A, X, If(B, Y, Z))`
+///
+/// Like with `If`, only the behavior of the first condition that succeeds
+/// is run, and the chain ends with the result of that behavior, even if it
+/// fails. Without `otherwise`, the last `when` becomes an `IfThen`, so the
+/// chain fails when none of the conditions succeed.
+///
+/// The nodes are named after the branches they stand for, "if", "else if"
+/// and "else", to keep the tree readable once visualized. Names do not
+/// count in node paths, so the paths are those of the nested `If` nodes.
+///
+/// ```
+/// use bonsai_bt::{cond_chain, Action, Behavior, If};
+///
+/// let behavior: Behavior<&str> = cond_chain()
+///     .when(Action("see enemy"), Action("attack"))
+///     .when(Action("hear noise"), Action("search"))
+///     .otherwise(Action("patrol"))
+///     .build();
+/// assert_eq!(
+///     behavior,
+///     If(
+///         Box::new(Action("see enemy")),
+///         Box::new(Action("attack")),
+///         Box::new(
+///             If(
+///                 Box::new(Action("hear noise")),
+///                 Box::new(Action("search")),
+///                 Box::new(Action("patrol").named("else")),
+///             )
+///             .named("else if")
+///         ),
+///     )
+///     .named("if")
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct CondChain<A> {
+    branches: Vec<(Behavior<A>, Behavior<A>)>,
+    otherwise: Option<Behavior<A>>,
+}
+
+impl<A> CondChain<A> {
+    /// Runs `then` if `condition` succeeds and none of the conditions
+    /// before it did.
+    pub fn when(mut self, condition: Behavior<A>, then: Behavior<A>) -> Self {
+        self.branches.push((condition, then));
+        self
+    }
+
+    /// Runs `behavior` if none of the conditions succeed.
+    pub fn otherwise(mut self, behavior: Behavior<A>) -> Self {
+        self.otherwise = Some(behavior);
+        self
+    }
+
+    /// Expands the chain into a behavior.
+    ///
+    /// A chain without any `when` is only its `otherwise` behavior, and an
+    /// empty chain is an empty `WhenAny`, which fails right away on any event.
+    pub fn build(self) -> Behavior<A> {
+        let mut branches = self.branches;
+        let mut behavior = match (self.otherwise, branches.pop()) {
+            (Some(otherwise), None) => return otherwise,
+            (None, None) => return Behavior::WhenAny(vec![]),
+            (Some(otherwise), Some((condition, then))) => Behavior::If(
+                Box::new(condition),
+                Box::new(then),
+                Box::new(otherwise.named("else")),
+            ),
+            (None, Some((condition, then))) => Behavior::IfThen(Box::new(condition), Box::new(then)),
+        };
+        while let Some((condition, then)) = branches.pop() {
+            behavior = Behavior::If(Box::new(condition), Box::new(then), Box::new(behavior.named("else if")));
+        }
+        behavior.named("if")
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Write};
+
+use petgraph::{graph::Graph, stable_graph::NodeIndex, Direction::Incoming};
+
+use crate::state::State::{self, *};
+use crate::status::Status;
+use crate::timers::TimerInfo;
+use crate::visualizer::NodeType;
+
+// Walks the currently active cursors of the state like `active_timers`, and
+// collects the status of the nodes it can tell: `Running` for the active
+// nodes, and the result of the behaviors that have terminated while their
+// parent is still running, e.g. those before the running one in a `Sequence`.
+pub(crate) fn node_statuses<A>(state: &State<A>, path: &mut Vec<usize>, statuses: &mut HashMap<Vec<usize>, Status>) {
+    statuses.insert(path.clone(), Status::Running);
+    match state {
+        ActionState(_, _) | WaitState(_, _) | WaitForeverState => {}
+        // Names do not add a level to the path.
+        NamedState(_, cur) => node_statuses(cur, path, statuses),
+        TaggedState(_, _, running, cur) => {
+            if *running {
+                node_statuses(cur, path, statuses)
+            }
+        }
+        InvertState(cur) | AlwaysSucceedState(cur) | RestartOnFailureState(_, cur) | SeededState(_, cur) => {
+            child(0, cur, path, statuses)
+        }
+        IfState(_, _, status, cur) => {
+            let index = match status {
+                Status::Running => 0,
+                Status::Success => 1,
+                Status::Failure => 2,
+            };
+            if index > 0 {
+                settled(0, *status, path, statuses);
+            }
+            child(index, cur, path, statuses)
+        }
+        IfThenState(_, status, cur) => match status {
+            Status::Running => child(0, cur, path, statuses),
+            Status::Success => {
+                settled(0, Status::Success, path, statuses);
+                child(1, cur, path, statuses)
+            }
+            Status::Failure => settled(0, Status::Failure, path, statuses),
+        },
+        SequenceState(seq, i, cur) | TimeBudgetSequenceState(_, _, seq, i, cur) => {
+            for j in 0..*i {
+                settled(j, Status::Success, path, statuses);
+            }
+            if *i < seq.len() {
+                child(*i, cur, path, statuses)
+            }
+        }
+        SelectState(sel, i, cur) | SelectReactiveState(sel, i, cur) | ImpatientSelectState(_, sel, i, _, cur) => {
+            for j in 0..*i {
+                settled(j, Status::Failure, path, statuses);
+            }
+            if *i < sel.len() {
+                child(*i, cur, path, statuses)
+            }
+        }
+        AdaptiveSelectState(_, _, _, order, i, cur) | TieBreakSelectState(_, _, order, i, cur) => {
+            for &j in order.iter().take(*i) {
+                settled(j, Status::Failure, path, statuses);
+            }
+            if *i < order.len() {
+                child(order[*i], cur, path, statuses)
+            }
+        }
+        WhileState(ev_cursor, _, i, cur)
+        | RepeatSequenceState(ev_cursor, _, i, _, cur)
+        | BoundedWhileState(_, ev_cursor, _, i, cur) => {
+            // The body follows the condition, from 1 on.
+            child(0, ev_cursor, path, statuses);
+            for j in 0..*i {
+                settled(j + 1, Status::Success, path, statuses);
+            }
+            child(*i + 1, cur, path, statuses)
+        }
+        // The behaviors that have terminated are those without a cursor, and
+        // they could only have succeeded in a `WhenAll`, or failed in a `WhenAny`.
+        WhenAllState(cursors) => parallel(cursors, Some(Status::Success), path, statuses),
+        WhenAnyState(cursors) => parallel(cursors, Some(Status::Failure), path, statuses),
+        RoundRobinParallelState(_, _, cursors, _)
+        | EarlySuccessParallelState(_, _, cursors)
+        | RoleParallelState(_, cursors)
+        | ResourceParallelState(_, _, _, cursors) => parallel(cursors, None, path, statuses),
+        OrderedParallelState(_, cursors, results) => {
+            for (j, cur) in cursors.iter().enumerate() {
+                match cur {
+                    Some(cur) => child(j, cur, path, statuses),
+                    None if results[j] != Status::Running => settled(j, results[j], path, statuses),
+                    None => {}
+                }
+            }
+        }
+        AfterState(i, cursors) => {
+            for (j, cur) in cursors.iter().enumerate() {
+                if j < *i {
+                    settled(j, Status::Success, path, statuses)
+                } else {
+                    child(j, cur, path, statuses)
+                }
+            }
+        }
+        SharedCooldownState(_, _, _, running, cur) => {
+            if *running {
+                child(0, cur, path, statuses)
+            }
+        }
+        CooldownSelectState(_, _, current, cur) => {
+            if let Some(j) = current {
+                child(*j, cur, path, statuses)
+            }
+        }
+        FinallyState(_, _, status, running, cur) => {
+            // `Finally(body, finalizer)` has `body` at 0 and `finalizer` at 1.
+            if *running {
+                if *status == Status::Running {
+                    child(0, cur, path, statuses)
+                } else {
+                    settled(0, *status, path, statuses);
+                    child(1, cur, path, statuses)
+                }
+            }
+        }
+        DoWhileState(_, _, checking, cur) => {
+            // `DoWhile(body, condition)` has `body` at 0 and `condition` at 1.
+            if *checking {
+                settled(0, Status::Success, path, statuses);
+                child(1, cur, path, statuses)
+            } else {
+                child(0, cur, path, statuses)
+            }
+        }
+        WithInitState(_, initialized, cur) => {
+            // `WithInit(init, body)` has `init` at 0 and `body` at 1.
+            if *initialized {
+                settled(0, Status::Success, path, statuses);
+                child(1, cur, path, statuses)
+            } else {
+                child(0, cur, path, statuses)
+            }
+        }
+        WithTimeoutState(_, _, _, timed_out, cur) => {
+            // `WithTimeout(timeout, behavior, on_timeout)` has `behavior` at 0 and `on_timeout` at 1.
+            let index = if *timed_out { 1 } else { 0 };
+            child(index, cur, path, statuses)
+        }
+        IntervalGuardState(_, _, _, checked, cur) => {
+            // `IntervalGuard(interval, condition, behavior)` has the behavior at 1.
+            if *checked {
+                settled(0, Status::Success, path, statuses);
+            }
+            child(1, cur, path, statuses)
+        }
+    }
+}
+
+fn child<A>(index: usize, state: &State<A>, path: &mut Vec<usize>, statuses: &mut HashMap<Vec<usize>, Status>) {
+    path.push(index);
+    node_statuses(state, path, statuses);
+    path.pop();
+}
+
+fn settled(index: usize, status: Status, path: &mut Vec<usize>, statuses: &mut HashMap<Vec<usize>, Status>) {
+    path.push(index);
+    statuses.insert(path.clone(), status);
+    path.pop();
+}
+
+fn parallel<A>(
+    cursors: &[Option<State<A>>],
+    terminated: Option<Status>,
+    path: &mut Vec<usize>,
+    statuses: &mut HashMap<Vec<usize>, Status>,
+) {
+    for (j, cur) in cursors.iter().enumerate() {
+        match (cur, terminated) {
+            (Some(cur), _) => child(j, cur, path, statuses),
+            (None, Some(status)) => settled(j, status, path, statuses),
+            (None, None) => {}
+        }
+    }
+}
+
+// Prints the graph built by `BT::dfs_recursive` as an indented tree, one node
+// per line, with the status of each node in brackets, and the elapsed and
+// total time of its timer if it has one. The nodes on the active path are
+// marked with `>`, and the nodes without a known status are `Idle`.
+//
+// `paths` are the node paths of the nodes of the graph, see `graph_paths`.
+// The nodes without a path are left out, and their children are printed in
+// their place.
+pub(crate) fn render_tree<A: Debug>(
+    graph: &Graph<NodeType<A>, u32>,
+    paths: &[Option<Vec<usize>>],
+    statuses: &HashMap<Vec<usize>, Status>,
+    timers: &[TimerInfo],
+) -> String {
+    let mut out = String::new();
+    let mut depths = vec![0; graph.node_count()];
+    // The nodes are added depth first, so a parent comes before its children.
+    for (n, path) in paths.iter().enumerate().skip(1) {
+        let parent = graph.neighbors_directed(NodeIndex::new(n), Incoming).next().unwrap().index();
+        depths[n] = depths[parent] + if paths[parent].is_some() { 1 } else { 0 };
+        let path = match path {
+            Some(path) => path,
+            None => continue,
+        };
+        let status = statuses.get(path);
+        let marker = if status == Some(&Status::Running) { '>' } else { ' ' };
+        let status = match status {
+            Some(status) => format!("{:?}", status),
+            None => "Idle".to_string(),
+        };
+        let _ = write!(out, "{} {}{:?} [{}", marker, "  ".repeat(depths[n]), graph[NodeIndex::new(n)], status);
+        if let Some(timer) = timers.iter().find(|timer| timer.path == *path) {
+            let _ = write!(out, " {:.1}/{:.1}", timer.elapsed, timer.total);
+        }
+        out.push_str("]\n");
+    }
+    out
+}
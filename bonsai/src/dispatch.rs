@@ -0,0 +1,116 @@
+use crate::{ActionArgs, Status};
+
+// A sub-dispatcher, see `CompositeDispatcher::register`.
+type SubDispatcher<'a, E, A, B> = Box<dyn FnMut(ActionArgs<E, A>, &mut B) -> Option<(Status, f64)> + 'a>;
+
+// The handler of the actions no sub-dispatcher handles.
+type Fallback<'a, E, A, B> = Box<dyn FnMut(ActionArgs<E, A>, &mut B) -> (Status, f64) + 'a>;
+
+/// Runs the actions of a tree with several sub-dispatchers, e.g. one per
+/// subsystem or crate, instead of a single closure matching every action.
+///
+/// The sub-dispatchers are tried in the order they are registered, and the
+/// first one that handles the action runs it. A sub-dispatcher handles an
+/// action by returning `Some` status and remaining delta time, and passes
+/// it on to the next one by returning `None`. The actions none of them
+/// handle go to the fallback, which fails them by default.
+///
+/// A composite dispatcher can itself be registered into another one with
+/// `try_dispatch`, to compose them hierarchically.
+///
+/// ```
+/// use bonsai_bt::{Action, ActionArgs, CompositeDispatcher, Event, Failure, Sequence, State, Success, UpdateArgs};
+///
+/// #[derive(Clone, Debug)]
+/// enum Act {
+///     Move,
+///     Attack,
+///     Dance,
+/// }
+///
+/// let mut moves = 0;
+/// let mut dispatcher = CompositeDispatcher::new()
+///     // the movement subsystem only knows how to move
+///     .register(|args: ActionArgs<Event, Act>, _: &mut ()| match args.action {
+///         Act::Move => {
+///             moves += 1;
+///             Some((Success, args.dt))
+///         }
+///         _ => None,
+///     })
+///     .register(|args, _| match args.action {
+///         Act::Attack => Some((Success, args.dt)),
+///         _ => None,
+///     });
+///
+/// // nobody knows how to dance, so the fallback fails it
+/// let mut state = State::new(Sequence(vec![Action(Act::Move), Action(Act::Attack), Action(Act::Dance)]));
+/// let e: Event = UpdateArgs { dt: 0.1 }.into();
+/// let (status, _) = state.tick(&e, &mut (), &mut |args, blackboard| dispatcher.dispatch(args, blackboard));
+/// assert_eq!(status, Failure);
+/// drop(dispatcher);
+/// assert_eq!(moves, 1);
+/// ```
+pub struct CompositeDispatcher<'a, E, A, B> {
+    dispatchers: Vec<SubDispatcher<'a, E, A, B>>,
+    fallback: Fallback<'a, E, A, B>,
+}
+
+impl<'a, E, A, B> CompositeDispatcher<'a, E, A, B> {
+    /// Creates a dispatcher without sub-dispatchers, which fails every action.
+    pub fn new() -> Self {
+        CompositeDispatcher {
+            dispatchers: vec![],
+            fallback: Box::new(|args, _| (Status::Failure, args.dt)),
+        }
+    }
+
+    /// Tries `dispatcher` after the sub-dispatchers registered so far.
+    pub fn register<F>(mut self, dispatcher: F) -> Self
+        where
+            F: FnMut(ActionArgs<E, A>, &mut B) -> Option<(Status, f64)> + 'a,
+    {
+        self.dispatchers.push(Box::new(dispatcher));
+        self
+    }
+
+    /// Runs the actions no sub-dispatcher handles with `fallback`, instead
+    /// of failing them.
+    pub fn fallback<F>(mut self, fallback: F) -> Self
+        where
+            F: FnMut(ActionArgs<E, A>, &mut B) -> (Status, f64) + 'a,
+    {
+        self.fallback = Box::new(fallback);
+        self
+    }
+
+    /// Runs the action with the first sub-dispatcher that handles it, or
+    /// with the fallback, meant to be called from the closure a tree is
+    /// ticked with.
+    pub fn dispatch(&mut self, args: ActionArgs<E, A>, blackboard: &mut B) -> (Status, f64) {
+        let (event, dt, action) = (args.event, args.dt, args.action);
+        match self.try_dispatch(ActionArgs { event, dt, action }, blackboard) {
+            Some(result) => result,
+            None => (self.fallback)(ActionArgs { event, dt, action }, blackboard),
+        }
+    }
+
+    /// Runs the action with the first sub-dispatcher that handles it, or
+    /// returns `None` without running it, e.g. to register this dispatcher
+    /// into another one.
+    pub fn try_dispatch(&mut self, args: ActionArgs<E, A>, blackboard: &mut B) -> Option<(Status, f64)> {
+        let (event, dt, action) = (args.event, args.dt, args.action);
+        for dispatcher in &mut self.dispatchers {
+            if let Some(result) = dispatcher(ActionArgs { event, dt, action }, &mut *blackboard) {
+                return Some(result);
+            }
+        }
+        None
+    }
+}
+
+impl<'a, E, A, B> Default for CompositeDispatcher<'a, E, A, B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
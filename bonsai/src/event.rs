@@ -3,6 +3,18 @@
 /// game/application loop.
 ///
 /// dt: states how much forward in time we should move the behavior tree
+///
+/// Seconds are only a convention, any unit of time works as long as the
+/// durations in the behaviors, e.g. of a `Period`, are given in the same
+/// unit. For deterministic lockstep simulations time can be counted in whole
+/// milliseconds or ticks: every integer up to 2^53 is exact as an `f64`, and
+/// the tree only adds, subtracts and compares times, so the results are exact
+/// and the same on every platform.
+///
+/// The time-based nodes only count the time of their current interval, e.g.
+/// a `Period` counts from zero each time it starts, and cooldowns count down
+/// to zero. Nothing adds up the total time the tree has run, so the precision
+/// of the timers does not degrade however long the tree keeps running.
 #[derive(Copy, Clone, PartialEq, PartialOrd, Debug, serde::Deserialize, serde::Serialize)]
 pub struct UpdateArgs {
     /// Delta time in seconds.
@@ -29,19 +41,95 @@ impl From<UpdateArgs> for Event {
     }
 }
 
+/// A named signal pushed into the tree by the application, e.g. when a door
+/// opens, for conditions to react to within the tick, see [Event::Custom].
+///
+/// Actions also emit signals to the application, e.g. to play a sound, see
+/// `BT::tick_with_output`.
+///
+/// ```
+/// use bonsai_bt::{Action, ActionArgs, Event, Running, Sequence, Signal, State, Success, UpdateArgs};
+///
+/// // waits for the door to open, then walks through
+/// let mut state = State::new(Sequence(vec![Action("door opened"), Action("walk through")]));
+/// let mut handler = |args: ActionArgs<Event, &str>, walked: &mut bool| match *args.action {
+///     "door opened" => match args.event.signal() {
+///         Some(signal) if signal.name == "door_opened" => (Success, args.dt),
+///         _ => (Running, 0.0),
+///     },
+///     _ => {
+///         *walked = true;
+///         (Success, args.dt)
+///     }
+/// };
+/// let mut walked = false;
+/// let update: Event = UpdateArgs { dt: 0.1 }.into();
+/// assert_eq!(state.tick(&update, &mut walked, &mut handler).0, Running);
+///
+/// // the signal is consumed by the condition, and the walk starts on the next update
+/// let signal: Event = Signal::new("door_opened").into();
+/// assert_eq!(state.tick(&signal, &mut walked, &mut handler).0, Running);
+/// assert_eq!(state.tick(&update, &mut walked, &mut handler).0, Success);
+/// assert!(walked);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Signal {
+    /// Name of the signal, e.g. "door_opened".
+    pub name: String,
+    /// Data that comes with the signal, if any.
+    pub payload: Option<String>,
+}
+
+impl Signal {
+    /// Creates a signal without a payload.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Signal {
+            name: name.into(),
+            payload: None,
+        }
+    }
+
+    /// Creates a signal with a payload.
+    pub fn with_payload<S: Into<String>, P: Into<String>>(name: S, payload: P) -> Self {
+        Signal {
+            name: name.into(),
+            payload: Some(payload.into()),
+        }
+    }
+}
+
+impl From<Signal> for Event {
+    fn from(signal: Signal) -> Self {
+        Event::Custom(signal)
+    }
+}
+
 /// Models all events.
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum Event {
     /// Input events.
     ///
     /// Events that commonly used by event loops.
     Loop(Loop),
+    /// A signal from the application, passed on to the actions like any
+    /// event other than an update: it carries no delta time, and a node
+    /// moving on from a behavior that terminated on it waits for the next
+    /// tick to run the next behavior.
+    Custom(Signal),
 }
 impl Event {
     /// Creates [Event] from [UpdateArgs] with `0.0` delta time.
     pub fn zero_dt_args() -> Self {
         UpdateArgs::zero_dt().into()
     }
+
+    /// The signal, if this is a [Event::Custom] event. Update events carry no signal.
+    pub fn signal(&self) -> Option<&Signal> {
+        match self {
+            Event::Custom(signal) => Some(signal),
+            Event::Loop(_) => None,
+        }
+    }
 }
 
 /// When the application state should be updated.
@@ -60,6 +148,10 @@ pub trait UpdateEvent: Sized {
     fn update_args(&self) -> Option<UpdateArgs> {
         self.update(|args| *args)
     }
+    /// Returns the signal, if this event is one.
+    fn signal(&self) -> Option<&Signal> {
+        None
+    }
 }
 
 impl UpdateEvent for Event {
@@ -73,8 +165,13 @@ impl UpdateEvent for Event {
     {
         match *self {
             Event::Loop(Loop::Update(ref args)) => Some(f(args)),
+            Event::Custom(_) => None,
         }
     }
+
+    fn signal(&self) -> Option<&Signal> {
+        Event::signal(self)
+    }
 }
 
 use std::time::Instant;
@@ -132,6 +229,16 @@ mod tests {
         let _: Option<Event> = UpdateEvent::from_update_args(&UpdateArgs { dt: 1.0 }, &e);
     }
 
+    #[test]
+    fn test_signal_is_not_an_update() {
+        let e: Event = Signal::with_payload("door_opened", "north").into();
+        assert_eq!(e.update_args(), None);
+        assert_eq!(e.signal().unwrap().payload.as_deref(), Some("north"));
+
+        let e: Event = UpdateArgs { dt: 1.0 }.into();
+        assert_eq!(e.signal(), None);
+    }
+
     #[test]
     fn test_timer() {
         let mut timer = Timer::init_time();
@@ -0,0 +1,199 @@
+use std::hash::{Hash, Hasher};
+
+use crate::{Behavior, TieBreak};
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a hasher.
+///
+/// Integers are hashed as little endian, and `usize`/`isize` as 64 bits,
+/// so that the hash is the same on every platform.
+pub(crate) struct Fnv(u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Self {
+        Fnv(FNV_OFFSET_BASIS)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64)
+    }
+}
+
+pub(crate) type HashAction<'a, A> = &'a dyn Fn(&A, &mut Fnv);
+
+// Hashes the behavior into `hasher`, with the actions hashed by `action`
+// and the parameters of the nodes included, if given.
+pub(crate) fn hash_behavior<A>(behavior: &Behavior<A>, hasher: &mut Fnv, action: Option<HashAction<A>>) {
+    hash_node(behavior, hasher, action);
+    for child in behavior.children() {
+        hash_behavior(child, hasher, action);
+    }
+}
+
+// Hashes the node itself, without its children, other than how many there are.
+pub(crate) fn hash_node<A>(behavior: &Behavior<A>, hasher: &mut Fnv, action: Option<HashAction<A>>) {
+    // The kinds are numbered explicitly, so that the hash does not depend
+    // on the order of the variants.
+    let kind: u8 = match behavior {
+        Behavior::Period(_) => 0,
+        Behavior::Forever => 1,
+        Behavior::Action(_) => 2,
+        Behavior::Invert(_) => 3,
+        Behavior::AlwaysSucceed(_) => 4,
+        Behavior::Select(_) => 5,
+        Behavior::If(_, _, _) => 6,
+        Behavior::IfThen(_, _) => 7,
+        Behavior::Sequence(_) => 8,
+        Behavior::While(_, _) => 9,
+        Behavior::RepeatSequence(_, _) => 10,
+        Behavior::WhenAll(_) => 11,
+        Behavior::WhenAny(_) => 12,
+        Behavior::After(_) => 13,
+        Behavior::SharedCooldown(_, _, _) => 14,
+        Behavior::TimeBudgetSequence(_, _) => 15,
+        Behavior::CooldownSelect(_, _) => 16,
+        Behavior::Named(_, _) => 17,
+        Behavior::RestartOnFailure(_) => 18,
+        Behavior::WithInit(_, _) => 19,
+        Behavior::Tagged(_, _) => 20,
+        Behavior::DoWhile(_, _) => 21,
+        Behavior::ImpatientSelect(_, _) => 22,
+        Behavior::RoundRobinParallel(_, _) => 23,
+        Behavior::EarlySuccessParallel(_, _) => 24,
+        Behavior::IntervalGuard(_, _, _) => 25,
+        Behavior::OrderedParallel(_) => 26,
+        Behavior::Subtree(_) => 27,
+        Behavior::Finally(_, _) => 28,
+        Behavior::SelectReactive(_) => 29,
+        Behavior::RoleParallel(_) => 30,
+        Behavior::WithTimeout(_, _, _) => 31,
+        Behavior::AdaptiveSelect(_, _, _) => 32,
+        Behavior::ResourceParallel(_, _) => 33,
+        Behavior::BoundedWhile(_, _, _) => 34,
+        Behavior::Seeded(_, _) => 35,
+        Behavior::TieBreakSelect(_, _) => 36,
+    };
+    hasher.write_u8(kind);
+
+    if let Some(action) = action {
+        match behavior {
+            Behavior::Action(a) => action(a, hasher),
+            Behavior::Period(t)
+            | Behavior::TimeBudgetSequence(t, _)
+            | Behavior::CooldownSelect(t, _)
+            | Behavior::ImpatientSelect(t, _)
+            | Behavior::IntervalGuard(t, _, _)
+            | Behavior::WithTimeout(t, _, _) => hasher.write_u64(t.to_bits()),
+            Behavior::RoundRobinParallel(n, _)
+            | Behavior::EarlySuccessParallel(n, _)
+            | Behavior::BoundedWhile(n, _, _) => hasher.write_usize(*n),
+            Behavior::Seeded(salt, _) => hasher.write_u64(*salt),
+            Behavior::SharedCooldown(key, t, _) | Behavior::AdaptiveSelect(key, t, _) => {
+                key.hash(hasher);
+                hasher.write_u64(t.to_bits())
+            }
+            Behavior::Named(s, _) | Behavior::Tagged(s, _) | Behavior::Subtree(s) => s.hash(hasher),
+            Behavior::RoleParallel(all) => {
+                for (role, _) in all {
+                    hasher.write_u8(*role as u8)
+                }
+            }
+            Behavior::ResourceParallel(pool, all) => {
+                pool.hash(hasher);
+                for (cost, _) in all {
+                    hasher.write_u64(cost.to_bits())
+                }
+            }
+            Behavior::TieBreakSelect(tie_break, _) => match tie_break {
+                TieBreak::DeclarationOrder => hasher.write_u8(0),
+                TieBreak::Random => hasher.write_u8(1),
+                TieBreak::RoundRobin(key) => {
+                    hasher.write_u8(2);
+                    key.hash(hasher)
+                }
+            },
+            _ => {}
+        }
+    }
+
+    hasher.write_usize(behavior.children().len());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+
+    use super::Fnv;
+    use crate::{Action, Period, Select, Sequence};
+
+    #[test]
+    fn test_fnv_test_vectors() {
+        let hash = |bytes: &[u8]| {
+            let mut hasher = Fnv::new();
+            hasher.write(bytes);
+            hasher.finish()
+        };
+        assert_eq!(hash(b""), 0xcbf29ce484222325);
+        assert_eq!(hash(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash(b"foobar"), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn test_structure_hash() {
+        let behavior = Sequence(vec![Period(1.0), Action("aim"), Action("shoot")]);
+        // the hash must not change between runs, platforms or versions
+        assert_eq!(behavior.structure_hash(), 0x26b4621d769c5928);
+
+        // the shape is the same, with other actions and durations
+        let other = Sequence(vec![Period(2.0), Action("aim"), Action("throw")]);
+        assert_eq!(behavior.structure_hash(), other.structure_hash());
+        assert_ne!(behavior.structure_hash_with_payloads(), other.structure_hash_with_payloads());
+        assert_eq!(
+            behavior.structure_hash_with_payloads(),
+            behavior.clone().structure_hash_with_payloads()
+        );
+
+        // other kinds of nodes, or other arities, give other shapes
+        let select = Select(vec![Period(1.0), Action("aim"), Action("shoot")]);
+        assert_ne!(behavior.structure_hash(), select.structure_hash());
+        let shorter = Sequence(vec![Period(1.0), Action("aim")]);
+        assert_ne!(behavior.structure_hash(), shorter.structure_hash());
+        let nested = Sequence(vec![Period(1.0), Sequence(vec![Action("aim"), Action("shoot")])]);
+        assert_ne!(behavior.structure_hash(), nested.structure_hash());
+    }
+}
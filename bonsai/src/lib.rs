@@ -25,6 +25,30 @@
 //! - Run `A` and `B` in parallell and wait for both to succeed: `WhenAll([A, B])`
 //! - Run `A` and `B` in parallell and wait for any to succeed: `WhenAny([A, B])`
 //! - Run `A` and `B` in parallell, but `A` has to succeed before `B`: `After([A, B])`
+//! - Run `A` unless a cooldown shared by key with other nodes is active: `SharedCooldown(key, t, A)`
+//! - Move from state `A` to state `B`, but fail if it takes more than `t` seconds: `TimeBudgetSequence(t, [A, B])`
+//! - Try `A` first and then try `B`, skipping each for `t` seconds after it fails: `CooldownSelect(t, [A, B])`
+//! - Start `A` over whenever it fails: `RestartOnFailure(A)`
+//! - Do `A` once, then do `B` forever: `WithInit(A, B)`
+//! - Tag `A` so that it can be disabled along with other behaviors: `Tagged(tag, A)`
+//! - Do `A`, and do it again for as long as `B` succeeds afterwards: `DoWhile(A, B)`
+//! - Try `A` first and then try `B`, giving up on each after running for `t` seconds: `ImpatientSelect(t, [A, B])`
+//! - Run `A`, `B` and `C` in parallel, but only tick one of them per update: `RoundRobinParallel(1, [A, B, C])`
+//! - Succeed once `A` or `B` succeeds, but keep running the other one: `EarlySuccessParallel(1, [A, B])`
+//! - Run `B` while `A` holds, checking `A` every `t` seconds: `IntervalGuard(t, A, B)`
+//! - Run `A` and `B` in parallel, but accept their results in order: `OrderedParallel([A, B])`
+//! - Run the behavior defined once under `key`, like a function call: `Subtree(key)`
+//! - Do `A`, then do `B` however `A` ends, even if it is aborted: `Finally(A, B)`
+//! - Try `A` first and then try `B`, but switch back to `A` as soon as it succeeds or runs: `SelectReactive([A, B])`
+//! - Run `A` and `B` in parallel, but only wait for `A` to succeed: `RoleParallel([(Required, A), (Optional, B)])`
+//! - Try `A` and `B` in the order of how well they have been doing lately: `AdaptiveSelect(key, rate, [A, B])`
+//! - Do `A`, but give up on it and do `B` instead if it runs for more than `t` seconds: `WithTimeout(t, A, B)`
+//! - Do `X` if `A` succeeds, else `Y` if `B` does, else `Z`: `cond_chain().when(A, X).when(B, Y).otherwise(Z).build()`
+//! - Run `A` and `B` in parallel, each once the pool `p` can afford it: `ResourceParallel(p, [(2.0, A), (1.0, B)])`
+//! - Do `B` repeatedly while `A` runs, up to `n` times per tick: `BoundedWhile(n, A, [B])`
+//! - Do `A`, then `B` only if `C` succeeds, then `D`: `steps().step(A).step_if(C, B).step(D).build()`
+//! - Do `A` with random choices of its own, forked off those of the tree for `salt`: `Seeded(salt, A)`
+//! - Try `A` and `B` in a random order, as either is as good as the other: `TieBreakSelect(Random, [A, B])`
 //!
 //! See the `Behavior` enum for more information.
 
@@ -119,20 +143,61 @@
 //! ```
 
 pub use behavior::Behavior::{
-    self, Action, After, AlwaysSucceed, If, Invert, Select, Sequence, Period, Forever, WhenAll, WhenAny, While,
+    self, Action, AdaptiveSelect, After, AlwaysSucceed, BoundedWhile, CooldownSelect, DoWhile, EarlySuccessParallel,
+    Finally, If, ImpatientSelect, IntervalGuard, Invert, Named, OrderedParallel, RoleParallel, ResourceParallel, Select,
+    SelectReactive, Sequence, Period, Forever, RestartOnFailure, RoundRobinParallel, Seeded, SharedCooldown, Subtree,
+    Tagged, TieBreakSelect, TimeBudgetSequence, WhenAll, WhenAny, While, WithInit, WithTimeout,
 };
 
-pub use bt::BT;
-pub use event::{Event, Timer, UpdateArgs, UpdateEvent};
-pub use state::{ActionArgs, State, RUNNING};
+pub use behavior::{
+    InvalidAction, InvalidParameter, InvalidPath, MapChildrenError, Role, SubtreeError, TieBreak, MAX_RESOLVED_NODES,
+};
+pub use bt::{Expiring, BT};
+pub use builder::{BtBuilder, BuildError};
+pub use cancel::CancelToken;
+pub use cond_chain::{cond_chain, CondChain};
+pub use dispatch::CompositeDispatcher;
+pub use event::{Event, Signal, Timer, UpdateArgs, UpdateEvent};
+pub use limits::{LimitError, Limits};
+pub use replay::{replay, EventLog};
+pub use save::{BtState, StateMismatch};
+pub use shared::{SequenceStep, SharedState};
+pub use state::{ActionArgs, ActionData, Chunk, State, RUNNING};
+#[cfg(feature = "node-stats")]
+pub use stats::{Candidate, Histogram, NodeStats, SelectionInfo, SelectorDepth};
 pub use status::Status::{self, Failure, Running, Success};
+pub use steps::{steps, Steps};
+pub use template::BtTemplate;
+pub use timers::TimerInfo;
 pub use visualizer::*;
 
+/// Derives `Display` and a `short_name` for an action enum, see the `bonsai-derive` crate.
+#[cfg(feature = "derive")]
+pub use bonsai_derive::Action;
+
+mod analysis;
 mod behavior;
 mod bt;
+mod builder;
+mod cancel;
+mod cond_chain;
+mod debug_tree;
+mod dispatch;
 mod event;
+mod hash;
+mod limits;
+mod reload;
+mod replay;
+mod rng;
+mod save;
 mod sequence;
+mod shared;
 mod state;
+#[cfg(feature = "node-stats")]
+mod stats;
 mod status;
+mod steps;
+mod template;
+mod timers;
 mod visualizer;
 mod when_all;
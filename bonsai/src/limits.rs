@@ -0,0 +1,84 @@
+use std::fmt;
+
+use crate::Behavior;
+
+/// Limits on the size of a behavior tree, checked by `BT::with_limits` and
+/// `BT::builder` before the tree is built, e.g. on a server running trees
+/// submitted by users.
+///
+/// Both limits are disabled by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of behaviors of a single node, e.g. of a `Sequence`.
+    pub max_children: usize,
+    /// Maximum number of nodes in the whole tree, including `Named` and `Tagged`.
+    pub max_nodes: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_children: usize::MAX,
+            max_nodes: usize::MAX,
+        }
+    }
+}
+
+/// The error returned by `BT::with_limits` when a tree exceeds its [Limits].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LimitError {
+    /// The node at `path` has `count` behaviors, more than `max_children`.
+    TooManyChildren { path: Vec<usize>, count: usize },
+    /// The tree has more than `max_nodes` nodes.
+    TooManyNodes,
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LimitError::TooManyChildren { path, count } => {
+                write!(f, "too many children at path {:?}: {}", path, count)
+            }
+            LimitError::TooManyNodes => write!(f, "too many nodes"),
+        }
+    }
+}
+
+impl std::error::Error for LimitError {}
+
+// Checks `behavior` against `limits`, stopping at the first node that
+// exceeds them.
+pub(crate) fn check_limits<A>(behavior: &Behavior<A>, limits: &Limits) -> Result<(), LimitError> {
+    check(behavior, limits, &mut vec![], &mut 0)
+}
+
+fn check<A>(
+    behavior: &Behavior<A>,
+    limits: &Limits,
+    path: &mut Vec<usize>,
+    nodes: &mut usize,
+) -> Result<(), LimitError> {
+    *nodes += 1;
+    if *nodes > limits.max_nodes {
+        return Err(LimitError::TooManyNodes);
+    }
+    match behavior {
+        // Names and tags do not add a level to the path.
+        Behavior::Named(_, ev) | Behavior::Tagged(_, ev) => check(ev, limits, path, nodes),
+        _ => {
+            let children = behavior.children();
+            if children.len() > limits.max_children {
+                return Err(LimitError::TooManyChildren {
+                    path: path.clone(),
+                    count: children.len(),
+                });
+            }
+            for (i, child) in children.into_iter().enumerate() {
+                path.push(i);
+                check(child, limits, path, nodes)?;
+                path.pop();
+            }
+            Ok(())
+        }
+    }
+}
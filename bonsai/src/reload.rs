@@ -0,0 +1,210 @@
+use std::hash::{Hash, Hasher};
+
+use crate::hash::{hash_node, Fnv};
+use crate::shared::SharedState;
+use crate::state::State::{self, *};
+use crate::status::Status;
+use crate::Behavior;
+
+// Hash of the node itself, including the action and the parameters of the
+// node, and its number of children.
+fn node_hash<A: Hash>(behavior: &Behavior<A>) -> u64 {
+    let mut hasher = Fnv::new();
+    hash_node(behavior, &mut hasher, Some(&|action: &A, hasher: &mut Fnv| action.hash(hasher)));
+    hasher.finish()
+}
+
+// Whether the state of `old` can be kept as it is for `new`, or adapted to it.
+pub(crate) fn compatible<A: Hash>(old: &Behavior<A>, new: &Behavior<A>) -> bool {
+    node_hash(old) == node_hash(new)
+}
+
+// Adapts `state`, the runtime state of `old`, to the behavior `new`. The
+// path of the node being ticked in the shared state is the one of the state.
+//
+// Subtrees are matched by their path: a subtree of `new` which hashes the
+// same as the subtree of `old` at the same path, including the actions and
+// parameters, keeps its state. A node which only differs from the old one in
+// its children keeps its own state, such as which child is running, and the
+// running children are adapted in turn. Any other subtree starts over, and
+// is aborted first.
+pub(crate) fn reload_state<A: Clone + Hash>(
+    state: &mut State<A>,
+    old: &Behavior<A>,
+    new: &Behavior<A>,
+    shared: &mut SharedState<A>,
+) {
+    if old.structure_hash_with_payloads() == new.structure_hash_with_payloads() {
+        return;
+    }
+    if !compatible(old, new) {
+        state.abort(shared);
+        *state = State::new(new.clone());
+        return;
+    }
+
+    let (olds, news) = (old.children(), new.children());
+    // Names and tags do not add a level to the path.
+    match (&mut *state, new) {
+        (NamedState(_, cur), _) => return reload_state(cur, olds[0], news[0], shared),
+        (TaggedState(_, ev, _, cur), Behavior::Tagged(_, new_ev)) => {
+            *ev = new_ev.clone();
+            return reload_state(cur, olds[0], news[0], shared);
+        }
+        _ => {}
+    }
+    let mut child = |i: usize, cur: &mut State<A>| {
+        if i < news.len() {
+            shared.push_path(i);
+            reload_state(cur, olds[i], news[i], shared);
+            shared.pop_path();
+        }
+    };
+    match (state, new) {
+        (InvertState(cur), _) | (AlwaysSucceedState(cur), _) => child(0, cur),
+        (SeededState(salt, cur), Behavior::Seeded(new_salt, _)) => {
+            *salt = *new_salt;
+            child(0, cur)
+        }
+        (RestartOnFailureState(ev, cur), Behavior::RestartOnFailure(new_ev))
+        | (SharedCooldownState(_, _, ev, _, cur), Behavior::SharedCooldown(_, _, new_ev)) => {
+            *ev = new_ev.clone();
+            child(0, cur)
+        }
+        (IfState(success, failure, status, cur), Behavior::If(_, new_success, new_failure)) => {
+            *success = new_success.clone();
+            *failure = new_failure.clone();
+            let index = match status {
+                Status::Running => 0,
+                Status::Success => 1,
+                Status::Failure => 2,
+            };
+            child(index, cur)
+        }
+        (IntervalGuardState(_, condition, _, _, cur), Behavior::IntervalGuard(_, new_condition, _)) => {
+            *condition = new_condition.clone();
+            child(1, cur)
+        }
+        (WithTimeoutState(_, on_timeout, _, timed_out, cur), Behavior::WithTimeout(_, _, new_on_timeout)) => {
+            *on_timeout = new_on_timeout.clone();
+            child(if *timed_out { 1 } else { 0 }, cur)
+        }
+        (IfThenState(success, status, cur), Behavior::IfThen(_, new_success)) => {
+            *success = new_success.clone();
+            match status {
+                Status::Running => child(0, cur),
+                Status::Success => child(1, cur),
+                Status::Failure => {}
+            }
+        }
+        (SelectState(seq, i, cur), Behavior::Select(new_seq))
+        | (SelectReactiveState(seq, i, cur), Behavior::SelectReactive(new_seq))
+        | (SequenceState(seq, i, cur), Behavior::Sequence(new_seq))
+        | (TimeBudgetSequenceState(_, _, seq, i, cur), Behavior::TimeBudgetSequence(_, new_seq))
+        | (ImpatientSelectState(_, seq, i, _, cur), Behavior::ImpatientSelect(_, new_seq)) => {
+            seq.clone_from(new_seq);
+            if *i < seq.len() {
+                child(*i, cur)
+            }
+        }
+        (AdaptiveSelectState(_, _, sel, order, i, cur), Behavior::AdaptiveSelect(_, _, new_sel))
+        | (TieBreakSelectState(_, sel, order, i, cur), Behavior::TieBreakSelect(_, new_sel)) => {
+            sel.clone_from(new_sel);
+            if *i < order.len() {
+                child(order[*i], cur)
+            }
+        }
+        (WhileState(ev_cursor, rep, i, cur), Behavior::While(_, new_rep))
+        | (RepeatSequenceState(ev_cursor, rep, i, _, cur), Behavior::RepeatSequence(_, new_rep))
+        | (BoundedWhileState(_, ev_cursor, rep, i, cur), Behavior::BoundedWhile(_, _, new_rep)) => {
+            rep.clone_from(new_rep);
+            child(0, ev_cursor);
+            child(*i + 1, cur)
+        }
+        (WhenAllState(cursors), _)
+        | (WhenAnyState(cursors), _)
+        | (RoundRobinParallelState(_, _, cursors, _), _)
+        | (EarlySuccessParallelState(_, _, cursors), _)
+        | (OrderedParallelState(_, cursors, _), _)
+        | (RoleParallelState(_, cursors), _)
+        | (ResourceParallelState(_, _, _, cursors), _) => {
+            for (j, cur) in cursors.iter_mut().enumerate() {
+                if let Some(cur) = cur {
+                    child(j, cur)
+                }
+            }
+        }
+        (AfterState(_, cursors), _) => {
+            for (j, cur) in cursors.iter_mut().enumerate() {
+                child(j, cur)
+            }
+        }
+        (CooldownSelectState(_, sel, current, cur), Behavior::CooldownSelect(_, new_sel)) => {
+            sel.clone_from(new_sel);
+            if let Some(j) = current {
+                child(*j, cur)
+            }
+        }
+        (WithInitState(body, initialized, cur), Behavior::WithInit(_, new_body)) => {
+            *body = new_body.clone();
+            child(if *initialized { 1 } else { 0 }, cur)
+        }
+        (FinallyState(body, finalizer, status, running, cur), Behavior::Finally(new_body, new_finalizer)) => {
+            *body = new_body.clone();
+            *finalizer = new_finalizer.clone();
+            if *running {
+                child(if *status == Status::Running { 0 } else { 1 }, cur)
+            } else {
+                **cur = State::new((**body).clone());
+            }
+        }
+        (DoWhileState(body, condition, checking, cur), Behavior::DoWhile(new_body, new_condition)) => {
+            *body = new_body.clone();
+            *condition = new_condition.clone();
+            child(if *checking { 1 } else { 0 }, cur)
+        }
+        // Nodes without children are never only changed in their children.
+        (state, _) => {
+            state.abort(shared);
+            *state = State::new(new.clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Action, ActionArgs, Event, Period, Select, Sequence, Success, UpdateArgs, While, BT};
+
+    #[test]
+    fn test_reload_keeps_unchanged_subtrees() {
+        let behavior = While(
+            Box::new(Period(10.0)),
+            vec![Sequence(vec![Period(1.0), Action("aim"), Action("shoot")])],
+        );
+        let mut bt = BT::new(behavior, ());
+        let mut actions = vec![];
+        let mut handler = |args: ActionArgs<Event, &'static str>, _: &mut _| {
+            actions.push(*args.action);
+            (Success, args.dt)
+        };
+        let e: Event = UpdateArgs { dt: 0.5 }.into();
+        bt.tick(&e, &mut handler);
+
+        // the action after the period changes, the period keeps its time
+        let behavior = While(
+            Box::new(Period(10.0)),
+            vec![Sequence(vec![Period(1.0), Action("aim"), Action("throw")])],
+        );
+        bt.reload(behavior);
+        let timers = bt.active_timers();
+        assert_eq!(timers.len(), 2);
+        assert_eq!(timers[0].elapsed, 0.5);
+        assert_eq!(timers[1].elapsed, 0.5);
+        bt.tick(&e, &mut handler);
+        assert_eq!(actions, vec!["aim", "throw"]);
+
+        // the root changes, so the tree starts over
+        bt.reload(Select(vec![Period(1.0)]));
+        assert_eq!(bt.active_timers()[0].elapsed, 0.0);
+    }
+}
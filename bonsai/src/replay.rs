@@ -0,0 +1,59 @@
+use std::fmt::Debug;
+
+use crate::bt::BlackBoard;
+use crate::{ActionArgs, Event, Status, UpdateEvent, BT};
+
+/// The events a tree has been ticked with, in order, see
+/// `BT::record_events`.
+///
+/// A log can be serialized, e.g. to attach it to a bug report, and fed back
+/// into a fresh tree with [replay].
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct EventLog(Vec<Event>);
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `e`, if it is an update event or a signal.
+    pub fn record<E: UpdateEvent>(&mut self, e: &E) {
+        if let Some(args) = e.update_args() {
+            self.0.push(args.into());
+        } else if let Some(signal) = e.signal() {
+            self.0.push(signal.clone().into());
+        }
+    }
+
+    /// The recorded events, oldest first.
+    pub fn events(&self) -> &[Event] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+}
+
+/// Ticks `bt` with every event of `log` in order, and returns the result of
+/// each tick.
+///
+/// Given a freshly constructed tree, and a handler that behaves the same as
+/// during the recording, e.g. with its random number generators seeded the
+/// same way, this reproduces the recorded session tick by tick.
+pub fn replay<A, K, O, F>(log: &EventLog, bt: &mut BT<A, K, O>, f: &mut F) -> Vec<(Status, f64)>
+    where
+        A: Clone + Debug,
+        K: Debug,
+        F: FnMut(ActionArgs<Event, A>, &mut BlackBoard<K>) -> (Status, f64),
+{
+    log.events().iter().map(|e| bt.tick(e, f)).collect()
+}
@@ -0,0 +1,63 @@
+/// The seed of the random number generator of a tree that is not seeded
+/// with `BT::set_seed`.
+pub(crate) const DEFAULT_SEED: u64 = 0x853c_49e6_748f_ea9b;
+
+/// A small random number generator (SplitMix64), for the random choices of
+/// the nodes of a tree, e.g. the order in which an `AdaptiveSelect` tries its
+/// behaviors.
+///
+/// The sequence only depends on the seed, so that runs can be reproduced,
+/// e.g. with [replay](crate::replay). It is not meant for cryptography.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// The seed of the stream forked off the stream seeded with `seed` for
+    /// `salt`, so that the streams forked for different salts do not
+    /// correlate with each other or with the stream they are forked off.
+    pub(crate) fn fork_seed(seed: u64, salt: u64) -> u64 {
+        Rng::new(seed ^ Rng::new(salt).next_u64()).next_u64()
+    }
+
+    /// A number in `[0, 1)`.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(DEFAULT_SEED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Rng;
+
+    #[test]
+    fn test_splitmix64_reference_values() {
+        // The first outputs of the reference implementation seeded with 1234567.
+        let mut rng = Rng::new(1234567);
+        assert_eq!(rng.next_u64(), 6457827717110365317);
+        assert_eq!(rng.next_u64(), 3203168211198807973);
+    }
+
+    #[test]
+    fn test_next_f64_is_in_unit_interval() {
+        let mut rng = Rng::default();
+        assert!((0..1000).map(|_| rng.next_f64()).all(|x| (0.0..1.0).contains(&x)));
+    }
+}
@@ -0,0 +1,504 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::slice;
+
+use crate::shared::SharedState;
+use crate::state::State::{self, *};
+use crate::status::Status::{self, *};
+use crate::Behavior;
+
+/// The runtime state of a `BT`, without its behavior and its blackboard, as
+/// returned by `BT::save_state`.
+///
+/// The state can be serialized on its own, e.g. into a save game, and
+/// restored with `BT::restore_state` into a tree built from the same
+/// behavior, e.g. the one shipped with the game. It records the
+/// [structure hash](crate::Behavior::structure_hash_with_payloads) of the
+/// behavior it was saved from, so that a save from another version of the
+/// tree is rejected rather than restored into the wrong nodes.
+///
+/// Only the runtime state of the nodes is kept, by node path: which
+/// behaviors are running, how long the nodes have waited, and the results
+/// they keep. The nodes are rebuilt from the behavior of the tree on
+/// restore, so the actions do not need to be serializable, and are not in
+/// the saved state. The data the actions keep while they run is not saved
+/// either, see [ActionData](crate::ActionData).
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(bound = "")]
+pub struct BtState<A> {
+    structure_hash: u64,
+    nodes: Vec<NodeRecord>,
+    /// The finalizers of the aborted `Finally` behaviors, and the behaviors
+    /// running in the background, each from the path of its behavior.
+    finalizers: Vec<Vec<NodeRecord>>,
+    background: Vec<Vec<NodeRecord>>,
+    shared: SharedState<A>,
+}
+
+// The runtime state of a single node, without the behaviors it refers to.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+struct NodeRecord {
+    path: Vec<usize>,
+    /// Indices kept by the node, e.g. of the behavior running.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    indices: Vec<usize>,
+    /// Times in seconds kept by the node, e.g. how long it has waited.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    times: Vec<f64>,
+    /// Results kept by the node, e.g. of its condition.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    statuses: Vec<Status>,
+    /// Flags kept by the node, e.g. which of its behaviors are running.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    flags: Vec<bool>,
+}
+
+impl NodeRecord {
+    fn index(&self, n: usize) -> Option<usize> {
+        self.indices.get(n).copied()
+    }
+
+    fn time(&self, n: usize) -> Option<f64> {
+        self.times.get(n).copied()
+    }
+
+    fn status(&self, n: usize) -> Option<Status> {
+        self.statuses.get(n).copied()
+    }
+
+    fn flag(&self, n: usize) -> Option<bool> {
+        self.flags.get(n).copied()
+    }
+}
+
+type Records<'a> = Peekable<slice::Iter<'a, NodeRecord>>;
+
+impl<A: Clone> BtState<A> {
+    pub(crate) fn new(structure_hash: u64, state: &State<A>, shared: &SharedState<A>) -> Self {
+        let detached = |states: &[(Vec<usize>, State<A>)]| {
+            states
+                .iter()
+                .map(|(path, state)| {
+                    let mut nodes = vec![];
+                    record(state, &mut path.clone(), &mut nodes);
+                    nodes
+                })
+                .collect()
+        };
+        let mut nodes = vec![];
+        record(state, &mut vec![], &mut nodes);
+        BtState {
+            structure_hash,
+            nodes,
+            finalizers: detached(shared.finalizers()),
+            background: detached(shared.background()),
+            shared: shared.clone(),
+        }
+    }
+
+    /// The structure hash of the behavior the state was saved from.
+    pub fn structure_hash(&self) -> u64 {
+        self.structure_hash
+    }
+
+    // Rebuilds the state of the tree from its behavior.
+    //
+    // A node whose record does not fit it, e.g. from a save edited by hand,
+    // starts over. A finalizer or a background behavior which is not found
+    // in the behavior is dropped.
+    pub(crate) fn restore(self, behavior: &Behavior<A>) -> (State<A>, SharedState<A>) {
+        let detached = |saved: Vec<Vec<NodeRecord>>| {
+            saved
+                .iter()
+                .filter_map(|nodes| {
+                    let path = nodes.first()?.path.clone();
+                    let behavior = behavior_at(behavior, &path)?;
+                    let state = restore(behavior, &mut path.clone(), &mut nodes.iter().peekable());
+                    Some((path, state))
+                })
+                .collect()
+        };
+        let state = restore(behavior, &mut vec![], &mut self.nodes.iter().peekable());
+        let mut shared = self.shared;
+        shared.set_detached(detached(self.finalizers), detached(self.background));
+        (state, shared)
+    }
+}
+
+// The behavior at `path`, the outermost one if it is named or tagged.
+fn behavior_at<'a, A>(behavior: &'a Behavior<A>, path: &[usize]) -> Option<&'a Behavior<A>> {
+    let mut behavior = behavior;
+    for &index in path {
+        // Names and tags do not add a level to the path.
+        while let Behavior::Named(_, ev) | Behavior::Tagged(_, ev) = behavior {
+            behavior = ev;
+        }
+        behavior = *behavior.children().get(index)?;
+    }
+    Some(behavior)
+}
+
+// The index in node paths of the behavior an `If` runs, by the result of its condition.
+fn if_index(status: Status) -> usize {
+    match status {
+        Running => 0,
+        Success => 1,
+        Failure => 2,
+    }
+}
+
+fn flag_index(flag: bool) -> usize {
+    if flag {
+        1
+    } else {
+        0
+    }
+}
+
+// Records the runtime state of `state`, the state of the node at `path`, and
+// of the children it keeps track of, in depth-first order.
+fn record<A>(state: &State<A>, path: &mut Vec<usize>, records: &mut Vec<NodeRecord>) {
+    let mut node = NodeRecord {
+        path: path.clone(),
+        ..NodeRecord::default()
+    };
+    // The states of the children, by index in node paths. Names and tags do
+    // not add a level to the path.
+    let mut children: Vec<(Option<usize>, &State<A>)> = vec![];
+    match state {
+        ActionState(_, _) | WaitForeverState => {}
+        WaitState(_, elapsed) => node.times.push(*elapsed),
+        InvertState(cur) | AlwaysSucceedState(cur) | RestartOnFailureState(_, cur) | SeededState(_, cur) => {
+            children.push((Some(0), &**cur))
+        }
+        NamedState(_, cur) => children.push((None, &**cur)),
+        TaggedState(_, _, running, cur) => {
+            node.flags.push(*running);
+            children.push((None, &**cur));
+        }
+        SharedCooldownState(_, _, _, running, cur) => {
+            node.flags.push(*running);
+            children.push((Some(0), &**cur));
+        }
+        IfState(_, _, status, cur) => {
+            node.statuses.push(*status);
+            children.push((Some(if_index(*status)), &**cur));
+        }
+        IfThenState(_, status, cur) => {
+            node.statuses.push(*status);
+            children.push((Some(flag_index(*status == Success)), &**cur));
+        }
+        SequenceState(seq, i, cur) | SelectState(seq, i, cur) | SelectReactiveState(seq, i, cur) => {
+            node.indices.push(*i);
+            if *i < seq.len() {
+                children.push((Some(*i), &**cur));
+            }
+        }
+        TimeBudgetSequenceState(_, spent, seq, i, cur) | ImpatientSelectState(_, seq, i, spent, cur) => {
+            node.indices.push(*i);
+            node.times.push(*spent);
+            if *i < seq.len() {
+                children.push((Some(*i), &**cur));
+            }
+        }
+        CooldownSelectState(_, _, current, cur) => {
+            if let Some(j) = current {
+                node.indices.push(*j);
+                children.push((Some(*j), &**cur));
+            }
+        }
+        WhileState(ev_cursor, _, i, cur) | BoundedWhileState(_, ev_cursor, _, i, cur) => {
+            node.indices.push(*i);
+            // The body follows the condition, from 1 on.
+            children.push((Some(0), &**ev_cursor));
+            children.push((Some(*i + 1), &**cur));
+        }
+        RepeatSequenceState(ev_cursor, _, i, first, cur) => {
+            node.indices.push(*i);
+            node.flags.push(*first);
+            children.push((Some(0), &**ev_cursor));
+            children.push((Some(*i + 1), &**cur));
+        }
+        WhenAllState(cursors) | WhenAnyState(cursors) | RoleParallelState(_, cursors) => {
+            parallel(cursors, &mut node, &mut children)
+        }
+        EarlySuccessParallelState(_, successes, cursors) => {
+            node.indices.push(*successes);
+            parallel(cursors, &mut node, &mut children);
+        }
+        RoundRobinParallelState(_, next, cursors, pending) => {
+            node.indices.push(*next);
+            node.times.extend(pending);
+            parallel(cursors, &mut node, &mut children);
+        }
+        OrderedParallelState(next, cursors, results) => {
+            node.indices.push(*next);
+            node.statuses.extend(results);
+            parallel(cursors, &mut node, &mut children);
+        }
+        ResourceParallelState(_, _, reserved, cursors) => {
+            parallel(cursors, &mut node, &mut children);
+            node.flags.extend(reserved);
+        }
+        AfterState(i, cursors) => {
+            node.indices.push(*i);
+            children.extend(cursors.iter().enumerate().map(|(j, cur)| (Some(j), cur)));
+        }
+        WithInitState(_, initialized, cur) => {
+            node.flags.push(*initialized);
+            children.push((Some(flag_index(*initialized)), &**cur));
+        }
+        DoWhileState(_, _, checking, cur) => {
+            node.flags.push(*checking);
+            children.push((Some(flag_index(*checking)), &**cur));
+        }
+        IntervalGuardState(_, _, since, checked, cur) => {
+            node.times.push(*since);
+            node.flags.push(*checked);
+            // `IntervalGuard(interval, condition, behavior)` has the behavior at 1.
+            children.push((Some(1), &**cur));
+        }
+        FinallyState(_, _, status, running, cur) => {
+            node.statuses.push(*status);
+            node.flags.push(*running);
+            children.push((Some(flag_index(*status != Running)), &**cur));
+        }
+        WithTimeoutState(_, _, elapsed, timed_out, cur) => {
+            node.times.push(*elapsed);
+            node.flags.push(*timed_out);
+            children.push((Some(flag_index(*timed_out)), &**cur));
+        }
+        AdaptiveSelectState(_, _, _, order, i, cur) | TieBreakSelectState(_, _, order, i, cur) => {
+            node.indices.push(*i);
+            node.indices.extend(order);
+            if *i < order.len() {
+                children.push((Some(order[*i]), &**cur));
+            }
+        }
+    }
+    records.push(node);
+    for (index, cur) in children {
+        match index {
+            Some(index) => {
+                path.push(index);
+                record(cur, path, records);
+                path.pop();
+            }
+            None => record(cur, path, records),
+        }
+    }
+}
+
+// Records which behaviors of a parallel node are running, and their states.
+fn parallel<'a, A>(
+    cursors: &'a [Option<State<A>>],
+    node: &mut NodeRecord,
+    children: &mut Vec<(Option<usize>, &'a State<A>)>,
+) {
+    for (j, cur) in cursors.iter().enumerate() {
+        node.flags.push(cur.is_some());
+        if let Some(cur) = cur {
+            children.push((Some(j), cur));
+        }
+    }
+}
+
+// Rebuilds the state of `behavior`, the behavior at `path`, from the records.
+fn restore<A: Clone>(behavior: &Behavior<A>, path: &mut Vec<usize>, records: &mut Records) -> State<A> {
+    let mut state = State::new(behavior.clone());
+    if let Some(node) = records.next_if(|node| node.path == *path) {
+        if restore_node(&mut state, behavior, node, path, records).is_none() {
+            // Skip the records of the children, and start over.
+            while records.next_if(|child| child.path.starts_with(path)).is_some() {}
+            state = State::new(behavior.clone());
+        }
+    }
+    state
+}
+
+// Rebuilds the state of the child at `index` of the node at `path`.
+fn child<A: Clone>(
+    children: &[&Behavior<A>],
+    index: usize,
+    path: &mut Vec<usize>,
+    records: &mut Records,
+) -> Option<Box<State<A>>> {
+    let behavior = children.get(index)?;
+    path.push(index);
+    let state = restore(behavior, path, records);
+    path.pop();
+    Some(Box::new(state))
+}
+
+fn restore_node<A: Clone>(
+    state: &mut State<A>,
+    behavior: &Behavior<A>,
+    node: &NodeRecord,
+    path: &mut Vec<usize>,
+    records: &mut Records,
+) -> Option<()> {
+    let children = behavior.children();
+    match state {
+        ActionState(_, _) | WaitForeverState => {}
+        WaitState(_, elapsed) => *elapsed = node.time(0)?,
+        InvertState(cur) | AlwaysSucceedState(cur) | RestartOnFailureState(_, cur) | SeededState(_, cur) => {
+            *cur = child(&children, 0, path, records)?
+        }
+        NamedState(_, cur) => **cur = restore(children[0], path, records),
+        TaggedState(_, _, running, cur) => {
+            *running = node.flag(0)?;
+            **cur = restore(children[0], path, records);
+        }
+        SharedCooldownState(_, _, _, running, cur) => {
+            *running = node.flag(0)?;
+            *cur = child(&children, 0, path, records)?;
+        }
+        IfState(_, _, status, cur) => {
+            *status = node.status(0)?;
+            *cur = child(&children, if_index(*status), path, records)?;
+        }
+        IfThenState(_, status, cur) => {
+            *status = node.status(0)?;
+            *cur = child(&children, flag_index(*status == Success), path, records)?;
+        }
+        SequenceState(seq, i, cur) | SelectState(seq, i, cur) | SelectReactiveState(seq, i, cur) => {
+            *i = node.index(0)?;
+            if *i < seq.len() {
+                *cur = child(&children, *i, path, records)?;
+            }
+        }
+        TimeBudgetSequenceState(_, spent, seq, i, cur) | ImpatientSelectState(_, seq, i, spent, cur) => {
+            *i = node.index(0)?;
+            *spent = node.time(0)?;
+            if *i < seq.len() {
+                *cur = child(&children, *i, path, records)?;
+            }
+        }
+        CooldownSelectState(_, _, current, cur) => {
+            *current = node.index(0);
+            if let Some(j) = current {
+                *cur = child(&children, *j, path, records)?;
+            }
+        }
+        WhileState(ev_cursor, _, i, cur) | BoundedWhileState(_, ev_cursor, _, i, cur) => {
+            *i = node.index(0)?;
+            *ev_cursor = child(&children, 0, path, records)?;
+            *cur = child(&children, *i + 1, path, records)?;
+        }
+        RepeatSequenceState(ev_cursor, _, i, first, cur) => {
+            *i = node.index(0)?;
+            *first = node.flag(0)?;
+            *ev_cursor = child(&children, 0, path, records)?;
+            *cur = child(&children, *i + 1, path, records)?;
+        }
+        WhenAllState(cursors) | WhenAnyState(cursors) | RoleParallelState(_, cursors) => {
+            restore_parallel(cursors, &children, node, path, records)?
+        }
+        EarlySuccessParallelState(_, successes, cursors) => {
+            *successes = node.index(0)?;
+            restore_parallel(cursors, &children, node, path, records)?;
+        }
+        RoundRobinParallelState(_, next, cursors, pending) => {
+            *next = node.index(0)?;
+            if node.times.len() != pending.len() {
+                return None;
+            }
+            pending.clone_from(&node.times);
+            restore_parallel(cursors, &children, node, path, records)?;
+        }
+        OrderedParallelState(next, cursors, results) => {
+            *next = node.index(0)?;
+            if node.statuses.len() != results.len() {
+                return None;
+            }
+            results.clone_from(&node.statuses);
+            restore_parallel(cursors, &children, node, path, records)?;
+        }
+        ResourceParallelState(_, _, reserved, cursors) => {
+            let n = cursors.len();
+            if node.flags.len() != 2 * n {
+                return None;
+            }
+            reserved.copy_from_slice(&node.flags[n..]);
+            restore_parallel(cursors, &children, node, path, records)?;
+        }
+        AfterState(i, cursors) => {
+            *i = node.index(0)?;
+            for (j, cur) in cursors.iter_mut().enumerate() {
+                *cur = *child(&children, j, path, records)?;
+            }
+        }
+        WithInitState(_, initialized, cur) => {
+            *initialized = node.flag(0)?;
+            *cur = child(&children, flag_index(*initialized), path, records)?;
+        }
+        DoWhileState(_, _, checking, cur) => {
+            *checking = node.flag(0)?;
+            *cur = child(&children, flag_index(*checking), path, records)?;
+        }
+        IntervalGuardState(_, _, since, checked, cur) => {
+            *since = node.time(0)?;
+            *checked = node.flag(0)?;
+            *cur = child(&children, 1, path, records)?;
+        }
+        FinallyState(_, _, status, running, cur) => {
+            *status = node.status(0)?;
+            *running = node.flag(0)?;
+            *cur = child(&children, flag_index(*status != Running), path, records)?;
+        }
+        WithTimeoutState(_, _, elapsed, timed_out, cur) => {
+            *elapsed = node.time(0)?;
+            *timed_out = node.flag(0)?;
+            *cur = child(&children, flag_index(*timed_out), path, records)?;
+        }
+        AdaptiveSelectState(_, _, sel, order, i, cur) | TieBreakSelectState(_, sel, order, i, cur) => {
+            *i = node.index(0)?;
+            *order = node.indices[1..].to_vec();
+            if *i > order.len() || order.iter().any(|&j| j >= sel.len()) {
+                return None;
+            }
+            if *i < order.len() {
+                *cur = child(&children, order[*i], path, records)?;
+            }
+        }
+    }
+    Some(())
+}
+
+fn restore_parallel<A: Clone>(
+    cursors: &mut [Option<State<A>>],
+    children: &[&Behavior<A>],
+    node: &NodeRecord,
+    path: &mut Vec<usize>,
+    records: &mut Records,
+) -> Option<()> {
+    for (j, cur) in cursors.iter_mut().enumerate() {
+        *cur = match node.flag(j)? {
+            true => Some(*child(children, j, path, records)?),
+            false => None,
+        };
+    }
+    Some(())
+}
+
+/// The error returned by `BT::restore_state` when the state was saved from
+/// another behavior than the one of the tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateMismatch {
+    /// The structure hash of the behavior of the tree.
+    pub expected: u64,
+    /// The structure hash of the behavior the state was saved from.
+    pub found: u64,
+}
+
+impl fmt::Display for StateMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "state saved from a behavior with structure hash {:#018x}, but the tree has {:#018x}",
+            self.found, self.expected
+        )
+    }
+}
+
+impl std::error::Error for StateMismatch {}
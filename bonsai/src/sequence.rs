@@ -1,5 +1,8 @@
+use crate::shared::SharedState;
+#[cfg(feature = "node-stats")]
+use crate::stats::Candidate;
 use crate::status::Status::*;
-use crate::{event::UpdateEvent, ActionArgs, Behavior, State, Status, RUNNING};
+use crate::{event::UpdateEvent, ActionArgs, ActionData, Behavior, State, Status, RUNNING};
 use std::fmt::Debug;
 
 pub struct SequenceArgs<'a, A, E, F, B> {
@@ -9,6 +12,7 @@ pub struct SequenceArgs<'a, A, E, F, B> {
     pub i: &'a mut usize,
     pub cursor: &'a mut Box<State<A>>,
     pub e: &'a E,
+    pub shared: &'a mut SharedState<A>,
     pub blackboard: &'a mut B,
     pub f: &'a mut F,
 }
@@ -21,7 +25,7 @@ pub fn sequence<A, E, F, B>(args: SequenceArgs<A, E, F, B>) -> (Status, f64)
 where
     A: Clone,
     E: UpdateEvent,
-    F: FnMut(ActionArgs<E, A>, &mut B) -> (Status, f64),
+    F: FnMut(ActionArgs<E, A>, &mut ActionData, &mut B) -> (Status, f64),
     A: Debug,
 {
     let SequenceArgs {
@@ -31,6 +35,7 @@ where
         i,
         cursor,
         e,
+        shared,
         blackboard,
         f,
     } = args;
@@ -45,7 +50,8 @@ where
     let mut remaining_dt = upd.unwrap_or(0.0);
     let mut remaining_e;
     while *i < seq.len() {
-        match cursor.tick(
+        match cursor.tick_child(
+            *i,
             match upd {
                 Some(_) => {
                     remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
@@ -53,16 +59,31 @@ where
                 }
                 _ => e,
             },
+            shared,
             blackboard,
             f,
         ) {
             (Running, _) => {
+                #[cfg(feature = "node-stats")]
+                if select {
+                    shared.counters.candidate(*i, seq.len(), Candidate::Chosen);
+                }
                 break;
             }
             (s, new_dt) if s == inv_status => {
+                #[cfg(feature = "node-stats")]
+                if select {
+                    shared.counters.candidate(*i, seq.len(), Candidate::Chosen);
+                }
                 return (inv_status, new_dt);
             }
             (s, new_dt) if s == status => {
+                if select {
+                    #[cfg(feature = "node-stats")]
+                    shared.counters.candidate(*i, seq.len(), Candidate::Failed);
+                } else {
+                    shared.step(*i, seq.len());
+                }
                 remaining_dt = match upd {
                     // Change update event with remaining delta time.
                     Some(_) => new_dt,
@@ -95,3 +116,99 @@ where
     }
     RUNNING
 }
+
+pub struct OrderedSelectArgs<'a, A, E, F, B> {
+    pub upd: Option<f64>,
+    pub sel: &'a [Behavior<A>],
+    pub order: &'a mut Vec<usize>,
+    pub i: &'a mut usize,
+    pub cursor: &'a mut Box<State<A>>,
+    pub learning: Option<(&'a str, f64)>,
+    pub e: &'a E,
+    pub shared: &'a mut SharedState<A>,
+    pub blackboard: &'a mut B,
+    pub f: &'a mut F,
+}
+
+// `TieBreakSelect` and `AdaptiveSelect` share same algorithm.
+//
+// Both are a `Select` trying the behaviors in `order`, the indices of the
+// behaviors settled when the node is entered, which is cleared once the node
+// ends so that a new order is settled the next time. `AdaptiveSelect` also
+// learns from the result of each behavior it tries, given the key of its
+// estimates and its learning rate as `learning`.
+pub fn ordered_select<A, E, F, B>(args: OrderedSelectArgs<A, E, F, B>) -> (Status, f64)
+where
+    A: Clone,
+    E: UpdateEvent,
+    F: FnMut(ActionArgs<E, A>, &mut ActionData, &mut B) -> (Status, f64),
+    A: Debug,
+{
+    let OrderedSelectArgs {
+        upd,
+        sel,
+        order,
+        i,
+        cursor,
+        learning,
+        e,
+        shared,
+        blackboard,
+        f,
+    } = args;
+
+    let mut remaining_dt = upd.unwrap_or(0.0);
+    let mut remaining_e;
+    loop {
+        let j = order[*i];
+        match cursor.tick_child(
+            j,
+            match upd {
+                Some(_) => {
+                    remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
+                    &remaining_e
+                }
+                _ => e,
+            },
+            shared,
+            blackboard,
+            f,
+        ) {
+            (Running, _) => {
+                #[cfg(feature = "node-stats")]
+                shared.counters.candidate(j, sel.len(), Candidate::Chosen);
+                return RUNNING;
+            }
+            (Success, new_dt) => {
+                #[cfg(feature = "node-stats")]
+                shared.counters.candidate(j, sel.len(), Candidate::Chosen);
+                if let Some((key, learning_rate)) = learning {
+                    shared.update_estimate(key, sel.len(), j, true, learning_rate);
+                }
+                // Settle a new order the next time.
+                order.clear();
+                return (Success, new_dt);
+            }
+            (Failure, new_dt) => {
+                #[cfg(feature = "node-stats")]
+                shared.counters.candidate(j, sel.len(), Candidate::Failed);
+                if let Some((key, learning_rate)) = learning {
+                    shared.update_estimate(key, sel.len(), j, false, learning_rate);
+                }
+                remaining_dt = new_dt;
+            }
+        };
+        *i += 1;
+        if *i >= order.len() {
+            order.clear();
+            return (Failure, remaining_dt);
+        }
+        // Create a new cursor for next event.
+        // Use the same pointer to avoid allocation.
+        **cursor = State::new(sel[order[*i]].clone());
+        if upd.is_none() {
+            // Other events are 'consumed' and not passed to next.
+            return RUNNING;
+        }
+    }
+}
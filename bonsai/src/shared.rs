@@ -0,0 +1,540 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::rng::{Rng, DEFAULT_SEED};
+#[cfg(feature = "node-stats")]
+use crate::stats::NodeCounters;
+use crate::{State, Status, TieBreak};
+
+/// Runtime state shared by all the nodes of a tree.
+///
+/// Where [State](crate::State) keeps track of a single node, this keeps track
+/// of the things several nodes can refer to by key, e.g. the timers of
+/// `SharedCooldown` nodes. It lives next to the blackboard in the `BT` and
+/// survives across ticks. When ticking a `State` directly, keep one next to
+/// it and tick it with [State::tick_with_shared].
+///
+/// Only the state that outlives a tick is serialized, see `BT::save_state`.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(bound = "")]
+pub struct SharedState<A> {
+    /// Remaining cooldown time in seconds per key.
+    cooldowns: HashMap<String, f64>,
+    /// Remaining cooldown time in seconds of the behaviors of the
+    /// `CooldownSelect` nodes, by node path.
+    select_cooldowns: HashMap<Vec<usize>, f64>,
+    /// Path of the node being ticked.
+    #[serde(skip)]
+    path: Vec<usize>,
+    /// Names of the `Named` behaviors around the node being ticked, innermost last.
+    #[serde(skip)]
+    names: Vec<String>,
+    /// Sequence steps taken during the current tick, while recorded.
+    #[serde(skip)]
+    steps: Vec<SequenceStep>,
+    /// Whether the sequence steps are recorded, see `BT::record_sequence_steps`.
+    #[serde(skip)]
+    record_steps: bool,
+    /// Disabled tags, with the status returned by the behaviors they tag.
+    disabled_tags: HashMap<String, Status>,
+    /// How often each node has run.
+    #[cfg(feature = "node-stats")]
+    #[serde(skip)]
+    pub(crate) counters: NodeCounters,
+    /// Finalizers of aborted `Finally` behaviors, run until they terminate,
+    /// with the path of the finalizer behavior.
+    ///
+    /// Saved as node records by `BT::save_state`.
+    #[serde(skip)]
+    finalizers: Vec<(Vec<usize>, State<A>)>,
+    /// Behaviors left running in the background by the `EarlySuccessParallel`
+    /// nodes which have succeeded, run until they terminate, with their path.
+    ///
+    /// Saved as node records by `BT::save_state`.
+    #[serde(skip)]
+    background: Vec<(Vec<usize>, State<A>)>,
+    /// Whether a node that has to know when it is aborted has run, e.g. a
+    /// `Finally` behavior, so that aborts need handling.
+    aborts_handled: bool,
+    /// Estimated success rate of each behavior of the `AdaptiveSelect` nodes, per key.
+    estimates: HashMap<String, Vec<f64>>,
+    /// Amount left in each resource pool of the `ResourceParallel` nodes, per key.
+    pools: HashMap<String, f64>,
+    /// Behavior each round robin `TieBreakSelect` starts from next, per key.
+    rotations: HashMap<String, usize>,
+    /// Seed of the stream `rng` draws from.
+    seed: u64,
+    /// Source of the random choices of the nodes.
+    rng: Rng,
+    /// Streams forked off by the `Seeded` nodes, by seed, while they are not
+    /// the one drawn from.
+    streams: HashMap<u64, Rng>,
+    /// When the tick being run stops, see `BT::tick_budgeted`.
+    #[serde(skip)]
+    deadline: Option<Instant>,
+}
+
+impl<A> Default for SharedState<A> {
+    fn default() -> Self {
+        SharedState {
+            cooldowns: HashMap::new(),
+            select_cooldowns: HashMap::new(),
+            path: vec![],
+            names: vec![],
+            steps: vec![],
+            record_steps: false,
+            disabled_tags: HashMap::new(),
+            #[cfg(feature = "node-stats")]
+            counters: NodeCounters::default(),
+            finalizers: vec![],
+            background: vec![],
+            aborts_handled: false,
+            estimates: HashMap::new(),
+            pools: HashMap::new(),
+            rotations: HashMap::new(),
+            seed: DEFAULT_SEED,
+            rng: Rng::default(),
+            streams: HashMap::new(),
+            deadline: None,
+        }
+    }
+}
+
+/// A sequence moving on from one of its behaviors, as returned by `BT::sequence_steps`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SequenceStep {
+    /// Name of the closest `Named` behavior around the sequence, if any.
+    pub name: Option<String>,
+    /// Index of the behavior that completed.
+    pub completed: usize,
+    /// Number of behaviors in the sequence.
+    pub total: usize,
+}
+
+impl<A> SharedState<A> {
+    /// Creates the shared state of a tree that has not run yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resets the state of the nodes. What survives a reset:
+    ///
+    /// - the disabled tags and whether the sequence steps are recorded,
+    ///   which are configuration rather than state
+    /// - the node counts, which are kept for the session
+    /// - the finalizers, which still have to run, and whether aborts need
+    ///   handling
+    /// - the resource pools, which belong to the application
+    /// - the random number generators, which go on with their sequences
+    ///
+    /// The behaviors running in the background are dropped, abort them first
+    /// with `abort_background`.
+    pub(crate) fn reset(&mut self) {
+        let disabled_tags = std::mem::take(&mut self.disabled_tags);
+        let record_steps = self.record_steps;
+        let finalizers = std::mem::take(&mut self.finalizers);
+        let aborts_handled = self.aborts_handled;
+        let pools = std::mem::take(&mut self.pools);
+        let seed = self.seed;
+        let rng = self.rng.clone();
+        let streams = std::mem::take(&mut self.streams);
+        #[cfg(feature = "node-stats")]
+        let counters = {
+            let mut counters = std::mem::take(&mut self.counters);
+            counters.reset();
+            counters
+        };
+        *self = SharedState {
+            disabled_tags,
+            record_steps,
+            #[cfg(feature = "node-stats")]
+            counters,
+            finalizers,
+            aborts_handled,
+            pools,
+            seed,
+            rng,
+            streams,
+            ..SharedState::new()
+        };
+    }
+
+    /// Takes over the state of `saved`, but keeps the node counts, which are
+    /// kept for the session, and whether the sequence steps are recorded.
+    pub(crate) fn restore(&mut self, saved: SharedState<A>) {
+        #[cfg(feature = "node-stats")]
+        let counters = std::mem::take(&mut self.counters);
+        let record_steps = self.record_steps;
+        *self = saved;
+        self.record_steps = record_steps;
+        #[cfg(feature = "node-stats")]
+        self.counters = counters;
+    }
+
+    /// Moves all shared timers `dt` seconds forward in time.
+    pub(crate) fn advance(&mut self, dt: f64) {
+        for remaining in self.cooldowns.values_mut() {
+            *remaining -= dt;
+        }
+        self.cooldowns.retain(|_, remaining| *remaining > 0.0);
+        for remaining in self.select_cooldowns.values_mut() {
+            *remaining -= dt;
+        }
+        self.select_cooldowns.retain(|_, remaining| *remaining > 0.0);
+    }
+
+    /// Remaining cooldown time in seconds for `key`, or `0.0` if it is ready.
+    pub(crate) fn cooldown_remaining(&self, key: &str) -> f64 {
+        self.cooldowns.get(key).copied().unwrap_or(0.0)
+    }
+
+    /// (Re)starts the cooldown for `key`.
+    pub(crate) fn start_cooldown(&mut self, key: &str, duration: f64) {
+        if duration > 0.0 {
+            self.cooldowns.insert(key.to_string(), duration);
+        }
+    }
+
+    /// Enters the behavior at `index` of the node being ticked.
+    pub(crate) fn push_path(&mut self, index: usize) {
+        self.path.push(index);
+    }
+
+    /// Moves over to the node at `path`, e.g. to tick a finalizer, and
+    /// returns the path of the node that was being ticked.
+    pub(crate) fn replace_path(&mut self, path: Vec<usize>) -> Vec<usize> {
+        std::mem::replace(&mut self.path, path)
+    }
+
+    /// Leaves the node being ticked, see `push_path`.
+    pub(crate) fn pop_path(&mut self) {
+        self.path.pop();
+    }
+
+    /// Remaining cooldown time in seconds of the behavior of a
+    /// `CooldownSelect` at `path`, or `0.0` if it is ready.
+    pub(crate) fn select_cooldown(&self, path: &[usize]) -> f64 {
+        self.select_cooldowns.get(path).copied().unwrap_or(0.0)
+    }
+
+    /// Remaining cooldown time in seconds of the behavior at `index` of the
+    /// `CooldownSelect` being ticked.
+    pub(crate) fn child_cooldown(&mut self, index: usize) -> f64 {
+        self.path.push(index);
+        let remaining = self.select_cooldown(&self.path);
+        self.path.pop();
+        remaining
+    }
+
+    /// Puts the behavior at `index` of the `CooldownSelect` being ticked on
+    /// cooldown.
+    pub(crate) fn start_child_cooldown(&mut self, index: usize, duration: f64) {
+        if duration > 0.0 {
+            let mut path = self.path.clone();
+            path.push(index);
+            self.select_cooldowns.insert(path, duration);
+        }
+    }
+
+    /// Enters a `Named` behavior while ticking it.
+    pub(crate) fn push_name(&mut self, name: String) {
+        self.names.push(name);
+    }
+
+    /// Leaves the innermost `Named` behavior, giving back its name.
+    pub(crate) fn pop_name(&mut self) -> String {
+        self.names.pop().expect("pop_name without push_name")
+    }
+
+    /// Records that a sequence of `total` behaviors completed the one at
+    /// `completed`, if the steps are recorded.
+    pub(crate) fn step(&mut self, completed: usize, total: usize) {
+        if !self.record_steps {
+            return;
+        }
+        self.steps.push(SequenceStep {
+            name: self.names.last().cloned(),
+            completed,
+            total,
+        });
+    }
+
+    pub(crate) fn disable_tag(&mut self, tag: &str, status: Status) {
+        self.disabled_tags.insert(tag.to_string(), status);
+    }
+
+    pub(crate) fn enable_tag(&mut self, tag: &str) {
+        self.disabled_tags.remove(tag);
+    }
+
+    /// The status returned by behaviors tagged with `tag`, if it is disabled.
+    pub(crate) fn disabled_tag(&self, tag: &str) -> Option<Status> {
+        self.disabled_tags.get(tag).copied()
+    }
+
+    pub(crate) fn disabled_tags(&self) -> &HashMap<String, Status> {
+        &self.disabled_tags
+    }
+
+    pub(crate) fn steps(&self) -> &[SequenceStep] {
+        &self.steps
+    }
+
+    pub(crate) fn record_steps(&mut self, enabled: bool) {
+        self.record_steps = enabled;
+        self.steps.clear();
+    }
+
+    pub(crate) fn clear_steps(&mut self) {
+        self.steps.clear();
+    }
+
+    /// Stops the ticks once `deadline` has passed, until it is cleared.
+    pub(crate) fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Whether the tick being run has passed its deadline, so that no more
+    /// nodes are ticked.
+    pub(crate) fn past_deadline(&self) -> bool {
+        matches!(self.deadline, Some(deadline) if Instant::now() >= deadline)
+    }
+
+    /// Notes that a node which has to know when it is aborted runs, see
+    /// `aborts_handled`.
+    pub(crate) fn handle_aborts(&mut self) {
+        self.aborts_handled = true;
+    }
+
+    /// Whether a node which has to know when it is aborted has run, e.g. a
+    /// `Finally` behavior, so that the nodes have to be aborted.
+    pub(crate) fn aborts_handled(&self) -> bool {
+        self.aborts_handled
+    }
+
+    /// Amount left in the resource pool `key`, or `0.0` if it was never set.
+    pub fn resource_pool(&self, key: &str) -> f64 {
+        self.pools.get(key).copied().unwrap_or(0.0)
+    }
+
+    /// Sets the amount held by the resource pool `key`, see
+    /// `BT::set_resource_pool`.
+    pub fn set_resource_pool<S: Into<String>>(&mut self, key: S, amount: f64) {
+        self.pools.insert(key.into(), amount);
+    }
+
+    /// Takes `cost` out of the resource pool `key`, unless it holds less.
+    pub(crate) fn take_resource(&mut self, key: &str, cost: f64) -> bool {
+        if cost <= 0.0 {
+            return true;
+        }
+        match self.pools.get_mut(key) {
+            Some(amount) if *amount >= cost => {
+                *amount -= cost;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Gives `cost` back to the resource pool `key`, see `take_resource`.
+    pub(crate) fn refund_resource(&mut self, key: &str, cost: f64) {
+        if cost > 0.0 {
+            *self.pools.entry(key.to_string()).or_insert(0.0) += cost;
+        }
+    }
+
+    /// Seeds the random number generator of the nodes, and drops the
+    /// streams forked off it by the `Seeded` nodes, see `BT::set_seed`.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = Rng::new(seed);
+        self.streams.clear();
+    }
+
+    /// Draws from the stream forked off the current one for `salt` until
+    /// `leave_stream` is called with what this returns, see `Behavior::Seeded`.
+    pub(crate) fn enter_stream(&mut self, salt: u64) -> (u64, Rng) {
+        let seed = Rng::fork_seed(self.seed, salt);
+        let rng = self.streams.remove(&seed).unwrap_or_else(|| Rng::new(seed));
+        (std::mem::replace(&mut self.seed, seed), std::mem::replace(&mut self.rng, rng))
+    }
+
+    /// Goes back to drawing from the stream left by `enter_stream`, and keeps
+    /// the forked stream for the next time it is entered.
+    pub(crate) fn leave_stream(&mut self, (seed, rng): (u64, Rng)) {
+        let forked = std::mem::replace(&mut self.rng, rng);
+        self.streams.insert(std::mem::replace(&mut self.seed, seed), forked);
+    }
+
+    /// The estimated success rates of the behaviors of the `AdaptiveSelect`
+    /// nodes with `key`, see `BT::success_estimates`.
+    pub fn success_estimates(&self, key: &str) -> Option<&[f64]> {
+        self.estimates.get(key).map(|estimates| estimates.as_slice())
+    }
+
+    // The estimates for `key`, starting out undecided.
+    fn estimates_mut(&mut self, key: &str, len: usize) -> &mut Vec<f64> {
+        let estimates = self.estimates.entry(key.to_string()).or_default();
+        estimates.resize(len, 0.5);
+        estimates
+    }
+
+    /// The order in which an `AdaptiveSelect` with `key` tries its `len`
+    /// behaviors, where each next behavior is drawn with a chance in
+    /// proportion to its estimated success rate.
+    pub(crate) fn adaptive_order(&mut self, key: &str, len: usize) -> Vec<usize> {
+        // Behaviors that keep failing still get a chance, to explore.
+        const EXPLORATION: f64 = 0.05;
+        let weights: Vec<f64> = self.estimates_mut(key, len).iter().map(|e| e + EXPLORATION).collect();
+        let mut left: Vec<usize> = (0..len).collect();
+        let mut order = Vec::with_capacity(len);
+        while !left.is_empty() {
+            let total: f64 = left.iter().map(|&j| weights[j]).sum();
+            let mut x = self.rng.next_f64() * total;
+            let mut pick = left.len() - 1;
+            for (k, &j) in left.iter().enumerate() {
+                x -= weights[j];
+                if x < 0.0 {
+                    pick = k;
+                    break;
+                }
+            }
+            order.push(left.remove(pick));
+        }
+        order
+    }
+
+    /// The order in which a `TieBreakSelect` with `tie_break` tries its `len`
+    /// behaviors.
+    pub(crate) fn tie_break_order(&mut self, tie_break: &TieBreak, len: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..len).collect();
+        match tie_break {
+            TieBreak::DeclarationOrder => {}
+            TieBreak::Random => {
+                // Fisher-Yates shuffle.
+                for k in (1..len).rev() {
+                    let j = ((self.rng.next_f64() * (k + 1) as f64) as usize).min(k);
+                    order.swap(j, k);
+                }
+            }
+            TieBreak::RoundRobin(key) => {
+                let next = self.rotations.entry(key.clone()).or_insert(0);
+                let start = *next % len.max(1);
+                *next = start + 1;
+                order.rotate_left(start);
+            }
+        }
+        order
+    }
+
+    /// Moves the estimated success rate of the behavior at `index` of an
+    /// `AdaptiveSelect` with `key` towards its result, by `learning_rate`.
+    pub(crate) fn update_estimate(&mut self, key: &str, len: usize, index: usize, succeeded: bool, learning_rate: f64) {
+        let estimate = &mut self.estimates_mut(key, len)[index];
+        let target = if succeeded { 1.0 } else { 0.0 };
+        *estimate += learning_rate * (target - *estimate);
+    }
+
+    /// Queues the finalizer of an aborted `Finally` behavior, the behavior
+    /// at `index` of the node being aborted.
+    pub(crate) fn push_finalizer(&mut self, index: usize, finalizer: State<A>) {
+        let mut path = self.path.clone();
+        path.push(index);
+        self.finalizers.push((path, finalizer));
+    }
+
+    /// Queues again a finalizer taken with `take_finalizers`.
+    pub(crate) fn requeue_finalizer(&mut self, path: Vec<usize>, finalizer: State<A>) {
+        self.finalizers.push((path, finalizer));
+    }
+
+    pub(crate) fn take_finalizers(&mut self) -> Vec<(Vec<usize>, State<A>)> {
+        std::mem::take(&mut self.finalizers)
+    }
+
+    pub(crate) fn finalizers(&self) -> &[(Vec<usize>, State<A>)] {
+        &self.finalizers
+    }
+
+    /// Leaves a behavior running in the background, outside of the tree,
+    /// the behavior at `index` of the node being ticked.
+    pub(crate) fn push_background(&mut self, index: usize, behavior: State<A>) {
+        let mut path = self.path.clone();
+        path.push(index);
+        self.background.push((path, behavior));
+    }
+
+    /// Leaves again a behavior taken with `take_background` in the background.
+    pub(crate) fn requeue_background(&mut self, path: Vec<usize>, behavior: State<A>) {
+        self.background.push((path, behavior));
+    }
+
+    pub(crate) fn take_background(&mut self) -> Vec<(Vec<usize>, State<A>)> {
+        std::mem::take(&mut self.background)
+    }
+
+    pub(crate) fn background(&self) -> &[(Vec<usize>, State<A>)] {
+        &self.background
+    }
+
+    /// Replaces the finalizers and the behaviors running in the background,
+    /// e.g. with those of a restored state.
+    pub(crate) fn set_detached(
+        &mut self,
+        finalizers: Vec<(Vec<usize>, State<A>)>,
+        background: Vec<(Vec<usize>, State<A>)>,
+    ) {
+        self.finalizers = finalizers;
+        self.background = background;
+    }
+}
+
+impl<A: Clone> SharedState<A> {
+    /// Aborts the behaviors running in the background, e.g. when the tree is
+    /// reset, queueing their finalizers.
+    pub(crate) fn abort_background(&mut self) {
+        self.abort_background_where(|_| true);
+    }
+
+    /// Aborts the behaviors the node being ticked left running in the
+    /// background the last time it ran, e.g. on the previous iteration of a
+    /// `While`.
+    pub(crate) fn abort_background_of_node(&mut self) {
+        let node = self.path.clone();
+        self.abort_background_where(|path| path.len() == node.len() + 1 && path.starts_with(&node));
+    }
+
+    // Aborts the behaviors running in the background whose path matches,
+    // each at its path, queueing their finalizers.
+    fn abort_background_where<P: Fn(&[usize]) -> bool>(&mut self, matches: P) {
+        let (aborted, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.background)
+            .into_iter()
+            .partition(|(path, _)| matches(path));
+        self.background = kept;
+        for (path, mut behavior) in aborted {
+            let outer = self.replace_path(path);
+            behavior.abort(self);
+            self.replace_path(outer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SharedState;
+
+    #[test]
+    fn test_cooldown_expires() {
+        let mut shared = SharedState::<()>::new();
+        shared.start_cooldown("global", 1.0);
+        assert_eq!(shared.cooldown_remaining("global"), 1.0);
+
+        shared.advance(0.75);
+        assert_eq!(shared.cooldown_remaining("global"), 0.25);
+
+        shared.advance(0.25);
+        assert_eq!(shared.cooldown_remaining("global"), 0.0);
+        assert_eq!(shared.cooldown_remaining("other"), 0.0);
+    }
+}
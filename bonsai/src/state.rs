@@ -1,11 +1,18 @@
-use std::fmt::Debug;
+use std::any::Any;
+use std::fmt::{self, Debug};
+use std::ops::Range;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::event::UpdateEvent;
-use crate::sequence::{sequence, SequenceArgs};
+use crate::sequence::{ordered_select, sequence, OrderedSelectArgs, SequenceArgs};
+use crate::shared::SharedState;
 use crate::state::State::*;
+#[cfg(feature = "node-stats")]
+use crate::stats::Candidate;
 use crate::status::Status::*;
 use crate::when_all::when_all;
-use crate::{Behavior, Status};
+use crate::{Behavior, BuildError, Role, Status, TieBreak};
 
 // use serde_derive::{Deserialize, Serialize};
 
@@ -25,11 +32,208 @@ pub struct ActionArgs<'a, E: 'a, A: 'a> {
     pub action: &'a A,
 }
 
+/// Data an action can keep between ticks while it is running, e.g. a handle
+/// to background work that it polls on each tick.
+///
+/// The data is passed to the closure along with the [ActionArgs] when the
+/// tree is ticked with [State::tick_with_data] or `BT::tick_with_data`.
+///
+/// ```
+/// use std::sync::mpsc::{self, TryRecvError};
+/// use std::sync::Mutex;
+/// use bonsai_bt::{Action, ActionArgs, ActionData, Event, Failure, Running, SharedState, State, Success, UpdateArgs};
+///
+/// let mut state = State::new(Action("compute path"));
+/// let mut shared = SharedState::new();
+/// let e: Event = UpdateArgs { dt: 0.1 }.into();
+/// let mut poll = |args: ActionArgs<Event, &str>, data: &mut ActionData, _: &mut ()| {
+///     // starts the work on the first tick, and polls it on the next ones
+///     let receiver = data.get_or_insert_with(|| {
+///         let (sender, receiver) = mpsc::channel();
+///         std::thread::spawn(move || sender.send(42).unwrap());
+///         // the data has to be `Sync`, which a receiver is not on its own
+///         Mutex::new(receiver)
+///     });
+///     match receiver.get_mut().unwrap().try_recv() {
+///         Ok(_) => (Success, args.dt),
+///         Err(TryRecvError::Empty) => (Running, 0.0),
+///         Err(TryRecvError::Disconnected) => (Failure, args.dt),
+///     }
+/// };
+/// while state.tick_with_data(&e, &mut shared, &mut (), &mut poll).0 == Running {}
+/// ```
+///
+/// The data is dropped once the action succeeds or fails, and along with the
+/// state of the node when the node is aborted or the tree is reset, so the
+/// action starts from scratch the next time it runs. It is not cloned with
+/// the state, nor serialized.
+#[derive(Default)]
+pub struct ActionData(Option<Box<dyn Any + Send + Sync>>);
+
+impl ActionData {
+    /// The data, if there is data of type `T`.
+    pub fn get<T: Any>(&mut self) -> Option<&mut T> {
+        self.0.as_mut().and_then(|data| data.downcast_mut())
+    }
+
+    /// Replaces the data with `data`.
+    pub fn set<T: Any + Send + Sync>(&mut self, data: T) {
+        self.0 = Some(Box::new(data));
+    }
+
+    /// The data, which is created with `f` first if there is no data of type `T`.
+    pub fn get_or_insert_with<T: Any + Send + Sync, F: FnOnce() -> T>(&mut self, f: F) -> &mut T {
+        if self.get::<T>().is_none() {
+            self.set(f());
+        }
+        self.get().unwrap()
+    }
+
+    /// Removes the data, and returns it if it is of type `T`.
+    pub fn take<T: Any>(&mut self) -> Option<T> {
+        self.0.take().and_then(|data| data.downcast().ok()).map(|data| *data)
+    }
+
+    /// Whether there is any data.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Removes the data.
+    pub fn clear(&mut self) {
+        self.0 = None;
+    }
+
+    /// The next chunk of an operation on `len` items that is done `size`
+    /// items per tick, e.g. to go through a long list without stalling a
+    /// frame.
+    ///
+    /// The cursor of the operation is kept in the data, so the action
+    /// processes the items of the chunk and returns [Chunk::result], which
+    /// keeps it running until the last chunk. It starts over from the first
+    /// item if the action is aborted.
+    ///
+    /// ```
+    /// use bonsai_bt::{Action, ActionArgs, ActionData, Event, Running, SharedState, State, UpdateArgs};
+    ///
+    /// let paths = vec!["a", "b", "c", "d", "e"];
+    /// let mut state = State::new(Action("index files"));
+    /// let e: Event = UpdateArgs { dt: 0.1 }.into();
+    /// let mut progress = 0.0;
+    /// let mut index = |args: ActionArgs<Event, &str>, data: &mut ActionData, progress: &mut f64| {
+    ///     let chunk = data.next_chunk(paths.len(), 2);
+    ///     for path in &paths[chunk.items.clone()] {
+    ///         println!("indexing {}", path);
+    ///     }
+    ///     // surface the progress to the rest of the tree
+    ///     *progress = chunk.progress();
+    ///     chunk.result(args.dt)
+    /// };
+    /// assert_eq!(state.tick_with_data(&e, &mut SharedState::new(), &mut progress, &mut index).0, Running);
+    /// assert_eq!(progress, 0.4);
+    /// ```
+    pub fn next_chunk(&mut self, len: usize, size: usize) -> Chunk {
+        let cursor = self.get_or_insert_with(|| ChunkCursor(0));
+        let start = cursor.0;
+        let end = start.saturating_add(size.max(1)).min(len);
+        cursor.0 = end;
+        Chunk {
+            items: start..end,
+            done: end,
+            len,
+        }
+    }
+}
+
+// The cursor of an operation done in chunks, see `ActionData::next_chunk`.
+struct ChunkCursor(usize);
+
+/// A chunk of an operation done over several ticks, see [ActionData::next_chunk].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chunk {
+    /// The items to process on this tick.
+    pub items: Range<usize>,
+    /// Number of items processed once this chunk is.
+    pub done: usize,
+    /// Number of items of the operation.
+    pub len: usize,
+}
+
+impl Chunk {
+    /// Whether this is the last chunk of the operation.
+    pub fn is_last(&self) -> bool {
+        self.done == self.len
+    }
+
+    /// The part of the operation done once this chunk is, from 0 to 1.
+    pub fn progress(&self) -> f64 {
+        if self.len == 0 {
+            1.0
+        } else {
+            self.done as f64 / self.len as f64
+        }
+    }
+
+    /// What the action returns once it has processed the chunk: `Success`
+    /// with the remaining delta time `dt` after the last chunk, and
+    /// `Running` before it.
+    pub fn result(&self, dt: f64) -> (Status, f64) {
+        if self.is_last() {
+            (Success, dt)
+        } else {
+            RUNNING
+        }
+    }
+}
+
+// The data belongs to a single running node, so a clone of the state starts
+// without it, and it is left out when comparing states.
+impl Clone for ActionData {
+    fn clone(&self) -> Self {
+        ActionData::default()
+    }
+}
+
+impl PartialEq for ActionData {
+    fn eq(&self, _: &ActionData) -> bool {
+        true
+    }
+}
+
+impl Debug for ActionData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.is_empty() { "ActionData(None)" } else { "ActionData(..)" })
+    }
+}
+
+// An `ActionState` is serialized as its action alone, e.g. `{"ActionState":"shoot"}`,
+// as it was before it kept the data of the action.
+fn serialize_action<A, S>(action: &A, _: &ActionData, serializer: S) -> Result<S::Ok, S::Error>
+where
+    A: Serialize,
+    S: Serializer,
+{
+    action.serialize(serializer)
+}
+
+fn deserialize_action<'de, A, D>(deserializer: D) -> Result<(A, ActionData), D::Error>
+where
+    A: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Ok((A::deserialize(deserializer)?, ActionData::default()))
+}
+
 /// Keeps track of a behavior.
 #[derive(Clone, Debug, serde::Deserialize, serde::Serialize, PartialEq)]
 pub enum State<A> {
     /// Executes an action.
-    ActionState(A),
+    ///
+    /// ActionData: Data kept by the action while it is running
+    ///
+    /// Only the action is serialized, as the content of the variant.
+    #[serde(serialize_with = "serialize_action", deserialize_with = "deserialize_action")]
+    ActionState(A, ActionData),
     /// Converts `Success` into `Failure` and vice versa.
     InvertState(Box<State<A>>),
     /// Ignores failures and always return `Success`.
@@ -62,6 +266,113 @@ pub enum State<A> {
     WhenAnyState(Vec<Option<State<A>>>),
     /// Keeps track of an `After` behavior.
     AfterState(usize, Vec<State<A>>),
+    /// Keeps track of a `SharedCooldown` behavior.
+    ///
+    /// bool: Whether the behavior was let through the cooldown and is running
+    SharedCooldownState(String, f64, Box<Behavior<A>>, bool, Box<State<A>>),
+    /// Keeps track of a `TimeBudgetSequence` behavior.
+    ///
+    /// f64: Time budget in seconds
+    ///
+    /// f64: Time spent in seconds
+    TimeBudgetSequenceState(f64, f64, Vec<Behavior<A>>, usize, Box<State<A>>),
+    /// Keeps track of a `CooldownSelect` behavior.
+    ///
+    /// f64: Cooldown time in seconds
+    ///
+    /// Option<usize>: The behavior currently selected, if any
+    ///
+    /// The remaining cooldown time of the behaviors is kept in the shared
+    /// state, so that it outlives the node when it is entered anew.
+    CooldownSelectState(f64, Vec<Behavior<A>>, Option<usize>, Box<State<A>>),
+    /// Keeps track of a `Named` behavior.
+    NamedState(String, Box<State<A>>),
+    /// Keeps track of a `RestartOnFailure` behavior.
+    RestartOnFailureState(Box<Behavior<A>>, Box<State<A>>),
+    /// Keeps track of a `WithInit` behavior.
+    /// The bool is true once the initialization has succeeded.
+    WithInitState(Box<Behavior<A>>, bool, Box<State<A>>),
+    /// Keeps track of a `Tagged` behavior.
+    /// The bool is true while the behavior is running.
+    TaggedState(String, Box<Behavior<A>>, bool, Box<State<A>>),
+    /// Keeps track of a `DoWhile` behavior.
+    /// The bool is true while the condition is being checked.
+    DoWhileState(Box<Behavior<A>>, Box<Behavior<A>>, bool, Box<State<A>>),
+    /// Keeps track of an `ImpatientSelect` behavior.
+    /// Has the timeout, the behaviors, the current index and the time it has been running.
+    ImpatientSelectState(f64, Vec<Behavior<A>>, usize, f64, Box<State<A>>),
+    /// Keeps track of a `RoundRobinParallel` behavior.
+    ///
+    /// usize: Number of behaviors to tick per update
+    /// usize: Index of the next behavior to tick
+    /// Vec<f64>: Time passed since each behavior was last ticked
+    RoundRobinParallelState(usize, usize, Vec<Option<State<A>>>, Vec<f64>),
+    /// Keeps track of an `EarlySuccessParallel` behavior.
+    ///
+    /// usize: Number of behaviors that have succeeded
+    EarlySuccessParallelState(usize, usize, Vec<Option<State<A>>>),
+    /// Keeps track of an `IntervalGuard` behavior.
+    ///
+    /// f64: Time in seconds since the condition was last checked
+    ///
+    /// bool: Whether the condition has been checked since the node was entered
+    IntervalGuardState(f64, Box<Behavior<A>>, f64, bool, Box<State<A>>),
+    /// Keeps track of an `OrderedParallel` behavior.
+    ///
+    /// usize: Index of the next behavior to accept
+    ///
+    /// Vec<Status>: Result of each behavior, `Running` until it terminates
+    OrderedParallelState(usize, Vec<Option<State<A>>>, Vec<Status>),
+    /// Keeps track of a `Finally` behavior.
+    ///
+    /// Status: `Running` while the body runs, then the result of the body
+    /// while the finalizer runs
+    ///
+    /// bool: Whether the behavior is running
+    FinallyState(Box<Behavior<A>>, Box<Behavior<A>>, Status, bool, Box<State<A>>),
+    /// Keeps track of a `SelectReactive` behavior.
+    ///
+    /// usize: Index of the behavior that is running
+    SelectReactiveState(Vec<Behavior<A>>, usize, Box<State<A>>),
+    /// Keeps track of a `RoleParallel` behavior.
+    RoleParallelState(Vec<Role>, Vec<Option<State<A>>>),
+    /// Keeps track of a `WithTimeout` behavior.
+    ///
+    /// f64: Time in seconds the behavior is given to terminate
+    ///
+    /// f64: Time elapsed in seconds
+    ///
+    /// bool: Whether the behavior has timed out, and `on_timeout` runs
+    WithTimeoutState(f64, Box<Behavior<A>>, f64, bool, Box<State<A>>),
+    /// Keeps track of an `AdaptiveSelect` behavior.
+    ///
+    /// Vec<usize>: Order in which the behaviors are tried, drawn when the
+    /// node is entered, empty until then
+    ///
+    /// usize: Position in the order of the behavior running
+    AdaptiveSelectState(String, f64, Vec<Behavior<A>>, Vec<usize>, usize, Box<State<A>>),
+    /// Keeps track of a `ResourceParallel` behavior.
+    ///
+    /// Vec<f64>: Cost of each behavior
+    ///
+    /// Vec<bool>: Whether each behavior holds its cost, i.e. has started
+    /// and not terminated
+    ResourceParallelState(String, Vec<f64>, Vec<bool>, Vec<Option<State<A>>>),
+    /// Keeps track of a `BoundedWhile` behavior.
+    ///
+    /// usize: Maximum number of times the body runs per tick
+    BoundedWhileState(usize, Box<State<A>>, Vec<Behavior<A>>, usize, Box<State<A>>),
+    /// Keeps track of a `Seeded` behavior.
+    ///
+    /// u64: Salt mixed into the seed of the enclosing stream
+    SeededState(u64, Box<State<A>>),
+    /// Keeps track of a `TieBreakSelect` behavior.
+    ///
+    /// Vec<usize>: Order in which the behaviors are tried, settled when the
+    /// node is entered, empty until then
+    ///
+    /// usize: Position in the order of the behavior running
+    TieBreakSelectState(TieBreak, Vec<Behavior<A>>, Vec<usize>, usize, Box<State<A>>),
 }
 
 impl<A: Clone> State<A> {
@@ -72,9 +383,16 @@ impl<A: Clone> State<A> {
     /// representation that can be copied or shared between objects having same behavior.
     /// Behavior means the declarative representation of the behavior, and State represents
     /// the executing instance of that behavior.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the behavior has an unresolved [Behavior::Subtree], an
+    /// [Behavior::AdaptiveSelect] with a learning rate outside of `[0, 1]`,
+    /// or a [Behavior::ResourceParallel] with a cost that can never be met,
+    /// infinite or NaN, or a negative cost, see [State::try_new].
     pub fn new(behavior: Behavior<A>) -> Self {
         match behavior {
-            Behavior::Action(action) => State::ActionState(action),
+            Behavior::Action(action) => State::ActionState(action, ActionData::default()),
             Behavior::Invert(ev) => State::InvertState(Box::new(State::new(*ev))),
             Behavior::AlwaysSucceed(ev) => State::AlwaysSucceedState(Box::new(State::new(*ev))),
             Behavior::Period(dt) => State::WaitState(dt, 0.0),
@@ -99,6 +417,10 @@ impl<A: Clone> State<A> {
                 let state = State::new(rep[0].clone());
                 State::WhileState(Box::new(State::new(*ev)), rep, 0, Box::new(state))
             }
+            Behavior::BoundedWhile(max_iterations, ev, rep) => {
+                let state = State::new(rep[0].clone());
+                State::BoundedWhileState(max_iterations, Box::new(State::new(*ev)), rep, 0, Box::new(state))
+            }
             Behavior::WhenAll(all) => State::WhenAllState(all.into_iter().map(|ev| Some(State::new(ev))).collect()),
             Behavior::WhenAny(any) => State::WhenAnyState(any.into_iter().map(|ev| Some(State::new(ev))).collect()),
             Behavior::After(after_all) => State::AfterState(0, after_all.into_iter().map(State::new).collect()),
@@ -110,9 +432,111 @@ impl<A: Clone> State<A> {
                 );
                 State::RepeatSequenceState(Box::new(State::new(*ev)), rep, 0, true, Box::new(state))
             }
+            Behavior::SharedCooldown(key, duration, ev) => {
+                let state = State::new((*ev).clone());
+                State::SharedCooldownState(key, duration, ev, false, Box::new(state))
+            }
+            Behavior::TimeBudgetSequence(budget, seq) => {
+                let state = State::new(seq[0].clone());
+                State::TimeBudgetSequenceState(budget, 0.0, seq, 0, Box::new(state))
+            }
+            Behavior::CooldownSelect(cooldown, sel) => {
+                let state = State::new(sel[0].clone());
+                State::CooldownSelectState(cooldown, sel, None, Box::new(state))
+            }
+            Behavior::Named(name, ev) => State::NamedState(name, Box::new(State::new(*ev))),
+            Behavior::RestartOnFailure(ev) => {
+                let state = State::new((*ev).clone());
+                State::RestartOnFailureState(ev, Box::new(state))
+            }
+            Behavior::WithInit(init, body) => {
+                let state = State::new(*init);
+                State::WithInitState(body, false, Box::new(state))
+            }
+            Behavior::Tagged(tag, ev) => {
+                let state = State::new((*ev).clone());
+                State::TaggedState(tag, ev, false, Box::new(state))
+            }
+            Behavior::DoWhile(body, condition) => {
+                let state = State::new((*body).clone());
+                State::DoWhileState(body, condition, false, Box::new(state))
+            }
+            Behavior::ImpatientSelect(timeout, sel) => {
+                let state = State::new(sel[0].clone());
+                State::ImpatientSelectState(timeout, sel, 0, 0.0, Box::new(state))
+            }
+            Behavior::RoundRobinParallel(per_tick, all) => {
+                let pending = vec![0.0; all.len()];
+                let cursors = all.into_iter().map(|ev| Some(State::new(ev))).collect();
+                State::RoundRobinParallelState(per_tick, 0, cursors, pending)
+            }
+            Behavior::EarlySuccessParallel(threshold, all) => {
+                let cursors = all.into_iter().map(|ev| Some(State::new(ev))).collect();
+                State::EarlySuccessParallelState(threshold, 0, cursors)
+            }
+            Behavior::IntervalGuard(interval, condition, ev) => {
+                let state = State::new(*ev);
+                State::IntervalGuardState(interval, condition, 0.0, false, Box::new(state))
+            }
+            Behavior::OrderedParallel(all) => {
+                let results = vec![Running; all.len()];
+                let cursors = all.into_iter().map(|ev| Some(State::new(ev))).collect();
+                State::OrderedParallelState(0, cursors, results)
+            }
+            Behavior::Finally(body, finalizer) => {
+                let state = State::new((*body).clone());
+                State::FinallyState(body, finalizer, Status::Running, false, Box::new(state))
+            }
+            Behavior::SelectReactive(sel) => {
+                let state = State::new(sel[0].clone());
+                State::SelectReactiveState(sel, 0, Box::new(state))
+            }
+            Behavior::RoleParallel(all) => {
+                let (roles, cursors) = all.into_iter().map(|(role, ev)| (role, Some(State::new(ev)))).unzip();
+                State::RoleParallelState(roles, cursors)
+            }
+            Behavior::WithTimeout(timeout, ev, on_timeout) => {
+                let state = State::new(*ev);
+                State::WithTimeoutState(timeout, on_timeout, 0.0, false, Box::new(state))
+            }
+            // The first behavior is only known once the order is drawn.
+            Behavior::AdaptiveSelect(key, learning_rate, sel) => {
+                assert!(
+                    (0.0..=1.0).contains(&learning_rate),
+                    "AdaptiveSelect's learning rate must be between 0 and 1, got {}",
+                    learning_rate
+                );
+                State::AdaptiveSelectState(key, learning_rate, sel, vec![], 0, Box::new(State::WaitForeverState))
+            }
+            Behavior::ResourceParallel(pool, all) => {
+                for (cost, _) in &all {
+                    assert!(
+                        cost.is_finite() && *cost >= 0.0,
+                        "ResourceParallel's costs must be finite and not negative, got {}",
+                        cost
+                    );
+                }
+                let reserved = vec![false; all.len()];
+                let (costs, cursors) = all.into_iter().map(|(cost, ev)| (cost, Some(State::new(ev)))).unzip();
+                State::ResourceParallelState(pool, costs, reserved, cursors)
+            }
+            Behavior::Seeded(salt, ev) => State::SeededState(salt, Box::new(State::new(*ev))),
+            Behavior::TieBreakSelect(tie_break, sel) => {
+                State::TieBreakSelectState(tie_break, sel, vec![], 0, Box::new(State::WaitForeverState))
+            }
+            Behavior::Subtree(key) => panic!("subtree {:?} must be resolved before running the tree", key),
         }
     }
 
+    /// Same as `new`, but fails instead of panicking if the behavior has an
+    /// unresolved [Behavior::Subtree] or a parameter the tree cannot run
+    /// with, e.g. a deserialized tree.
+    pub fn try_new(behavior: Behavior<A>) -> Result<Self, BuildError> {
+        behavior.check_resolved()?;
+        behavior.check_parameters()?;
+        Ok(State::new(behavior))
+    }
+
     /// Updates the cursor that tracks an event.
     ///
     /// The action need to return status and remaining delta time.
@@ -125,30 +549,328 @@ impl<A: Clone> State<A> {
     /// function returns the result of the tree traversal, and how long
     /// it actually took to complete the traversal and propagate the
     /// results back up to the root node
+    ///
+    /// PS! state shared between nodes, such as the timers of `SharedCooldown`
+    /// and the finalizers of aborted `Finally` behaviors, starts over on every
+    /// call, as it is kept outside of the `State`. Tick with
+    /// [State::tick_with_shared] to keep it across ticks, as the `BT` does.
     pub fn tick<E, F, B>(&mut self, e: &E, blackboard: &mut B, f: &mut F) -> (Status, f64)
         where
             E: UpdateEvent,
             F: FnMut(ActionArgs<E, A>, &mut B) -> (Status, f64),
             A: Debug,
+    {
+        self.tick_with_shared(e, &mut SharedState::new(), blackboard, f)
+    }
+
+    /// Same as `tick`, but with the state shared between the nodes kept in
+    /// `shared`, so that it lasts across ticks, e.g. the timers of the
+    /// `SharedCooldown` nodes and the finalizers of aborted `Finally`
+    /// behaviors.
+    ///
+    /// ```
+    /// use bonsai_bt::{Action, Event, Failure, SharedCooldown, SharedState, State, Success, UpdateArgs};
+    ///
+    /// let mut state = State::new(SharedCooldown("shout".to_string(), 1.0, Box::new(Action("shout"))));
+    /// let mut shared = SharedState::new();
+    /// let e: Event = UpdateArgs { dt: 0.1 }.into();
+    /// let mut shout = |args: bonsai_bt::ActionArgs<Event, &str>, _: &mut ()| (Success, args.dt);
+    /// assert_eq!(state.tick_with_shared(&e, &mut shared, &mut (), &mut shout).0, Success);
+    ///
+    /// // the cooldown is still active on the next tick
+    /// let mut state = State::new(SharedCooldown("shout".to_string(), 1.0, Box::new(Action("shout"))));
+    /// assert_eq!(state.tick_with_shared(&e, &mut shared, &mut (), &mut shout).0, Failure);
+    /// ```
+    pub fn tick_with_shared<E, F, B>(
+        &mut self,
+        e: &E,
+        shared: &mut SharedState<A>,
+        blackboard: &mut B,
+        f: &mut F,
+    ) -> (Status, f64)
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut B) -> (Status, f64),
+            A: Debug,
+    {
+        self.tick_with_data(e, shared, blackboard, &mut |args, _, blackboard| f(args, blackboard))
+    }
+
+    /// Same as `tick_with_shared`, but also passes the closure the data the
+    /// action node keeps between ticks, see [ActionData].
+    pub fn tick_with_data<E, F, B>(
+        &mut self,
+        e: &E,
+        shared: &mut SharedState<A>,
+        blackboard: &mut B,
+        f: &mut F,
+    ) -> (Status, f64)
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut ActionData, &mut B) -> (Status, f64),
+            A: Debug,
+    {
+        shared.clear_steps();
+        if let Some(dt) = e.update(|args| args.dt) {
+            shared.advance(dt);
+        }
+        self.tick_root(e, shared, blackboard, f)
+    }
+
+    /// Ticks the state as the root of a tree, then runs the finalizers of
+    /// the `Finally` behaviors aborted so far.
+    pub(crate) fn tick_root<E, F, B>(
+        &mut self,
+        e: &E,
+        shared: &mut SharedState<A>,
+        blackboard: &mut B,
+        f: &mut F,
+    ) -> (Status, f64)
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut ActionData, &mut B) -> (Status, f64),
+            A: Debug,
+    {
+        // The behaviors left running in the background on earlier ticks go
+        // first, so that those left running on this tick are not ticked twice.
+        for (path, mut behavior) in shared.take_background() {
+            if behavior.tick_detached(&path, e, shared, blackboard, f) == Running {
+                shared.requeue_background(path, behavior);
+            }
+        }
+        let result = self.tick_shared(e, shared, blackboard, f);
+        if result.0 != Running && shared.aborts_handled() {
+            self.abort(shared);
+        }
+        for (path, mut finalizer) in shared.take_finalizers() {
+            if finalizer.tick_detached(&path, e, shared, blackboard, f) == Running {
+                shared.requeue_finalizer(path, finalizer);
+            }
+        }
+        result
+    }
+
+    // Ticks a behavior that runs on its own, outside of the tree, e.g. a
+    // finalizer, and aborts it once it has terminated. The behavior is ticked
+    // at `path`, the path of the behavior it was built from.
+    fn tick_detached<E, F, B>(
+        &mut self,
+        path: &[usize],
+        e: &E,
+        shared: &mut SharedState<A>,
+        blackboard: &mut B,
+        f: &mut F,
+    ) -> Status
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut ActionData, &mut B) -> (Status, f64),
+            A: Debug,
+    {
+        if shared.past_deadline() {
+            return Running;
+        }
+        #[cfg(feature = "node-stats")]
+        let counters = std::mem::take(&mut shared.counters);
+        let outer = shared.replace_path(path.to_vec());
+        let (status, _) = self.tick_shared(e, shared, blackboard, f);
+        if status != Running {
+            self.abort(shared);
+        }
+        shared.replace_path(outer);
+        #[cfg(feature = "node-stats")]
+        {
+            shared.counters = counters;
+        }
+        status
+    }
+
+    /// Aborts the behaviors of the state that are still running, once the
+    /// state has terminated or is about to be dropped. The path of the node
+    /// being ticked in the shared state is the one of the state.
+    ///
+    /// The finalizers of the running `Finally` behaviors are queued in the
+    /// shared state, to be run until they terminate, innermost first.
+    pub(crate) fn abort(&mut self, shared: &mut SharedState<A>) {
+        match self {
+            FinallyState(_, finalizer, status, running, cur) => {
+                if !*running {
+                    return;
+                }
+                *running = false;
+                let finalizer = if *status == Running {
+                    cur.abort_child(0, shared);
+                    State::new((**finalizer).clone())
+                } else {
+                    // The finalizer is already running, and goes on as it is.
+                    std::mem::replace(&mut **cur, WaitForeverState)
+                };
+                shared.push_finalizer(1, finalizer);
+            }
+            ResourceParallelState(pool, costs, reserved, cursors) => {
+                for (j, cur) in cursors.iter_mut().enumerate() {
+                    if let Some(cur) = cur {
+                        cur.abort_child(j, shared);
+                    }
+                    if reserved[j] {
+                        reserved[j] = false;
+                        shared.refund_resource(pool, costs[j]);
+                    }
+                }
+            }
+            // Names and tags do not add a level to the path.
+            NamedState(_, cur) | TaggedState(_, _, _, cur) => cur.abort(shared),
+            state => {
+                for (index, cur) in state.cursors_mut() {
+                    cur.abort_child(index, shared);
+                }
+            }
+        }
+    }
+
+    // Aborts the state of the child at `index` of the node being ticked or aborted.
+    fn abort_child(&mut self, index: usize, shared: &mut SharedState<A>) {
+        shared.push_path(index);
+        self.abort(shared);
+        shared.pop_path();
+    }
+
+    // The states of the children the state keeps track of, running or not,
+    // with their index in node paths. Names and tags have none.
+    fn cursors_mut(&mut self) -> Vec<(usize, &mut State<A>)> {
+        match self {
+            ActionState(_, _) | WaitState(_, _) | WaitForeverState | NamedState(_, _) | TaggedState(_, _, _, _) => {
+                vec![]
+            }
+            WhileState(ev_cursor, _, i, cur)
+            | RepeatSequenceState(ev_cursor, _, i, _, cur)
+            | BoundedWhileState(_, ev_cursor, _, i, cur) => {
+                // The body follows the condition, from 1 on.
+                vec![(0, &mut **ev_cursor), (*i + 1, &mut **cur)]
+            }
+            WhenAllState(cursors)
+            | WhenAnyState(cursors)
+            | RoundRobinParallelState(_, _, cursors, _)
+            | EarlySuccessParallelState(_, _, cursors)
+            | OrderedParallelState(_, cursors, _)
+            | RoleParallelState(_, cursors)
+            | ResourceParallelState(_, _, _, cursors) => cursors
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(j, cur)| cur.as_mut().map(|cur| (j, cur)))
+                .collect(),
+            AfterState(_, cursors) => cursors.iter_mut().enumerate().collect(),
+            IfState(_, _, status, cur) => {
+                let index = match status {
+                    Running => 0,
+                    Success => 1,
+                    Failure => 2,
+                };
+                vec![(index, &mut **cur)]
+            }
+            IfThenState(_, status, cur) => vec![(if *status == Success { 1 } else { 0 }, &mut **cur)],
+            SelectState(_, i, cur)
+            | SelectReactiveState(_, i, cur)
+            | SequenceState(_, i, cur)
+            | TimeBudgetSequenceState(_, _, _, i, cur)
+            | ImpatientSelectState(_, _, i, _, cur) => vec![(*i, &mut **cur)],
+            AdaptiveSelectState(_, _, _, order, i, cur) | TieBreakSelectState(_, _, order, i, cur) => {
+                vec![(order.get(*i).copied().unwrap_or(0), &mut **cur)]
+            }
+            CooldownSelectState(_, _, current, cur) => vec![(current.unwrap_or(0), &mut **cur)],
+            WithInitState(_, initialized, cur) => vec![(if *initialized { 1 } else { 0 }, &mut **cur)],
+            DoWhileState(_, _, checking, cur) => vec![(if *checking { 1 } else { 0 }, &mut **cur)],
+            FinallyState(_, _, status, _, cur) => vec![(if *status == Running { 0 } else { 1 }, &mut **cur)],
+            WithTimeoutState(_, _, _, timed_out, cur) => vec![(if *timed_out { 1 } else { 0 }, &mut **cur)],
+            // `IntervalGuard(interval, condition, behavior)` has the behavior at 1.
+            IntervalGuardState(_, _, _, _, cur) => vec![(1, &mut **cur)],
+            InvertState(cur)
+            | AlwaysSucceedState(cur)
+            | SharedCooldownState(_, _, _, _, cur)
+            | RestartOnFailureState(_, cur)
+            | SeededState(_, cur) => vec![(0, &mut **cur)],
+        }
+    }
+
+    /// Ticks the child at `index` of the node being ticked, where the index
+    /// is the one of the child in node paths.
+    ///
+    /// Past the deadline of a budgeted tick, the child is not ticked and
+    /// counts as `Running`, so a node must not take a `Running` child for
+    /// one that has run once `shared.past_deadline()`.
+    #[inline]
+    pub(crate) fn tick_child<E, F, B>(
+        &mut self,
+        index: usize,
+        e: &E,
+        shared: &mut SharedState<A>,
+        blackboard: &mut B,
+        f: &mut F,
+    ) -> (Status, f64)
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut ActionData, &mut B) -> (Status, f64),
+            A: Debug,
+    {
+        // Past the deadline of a budgeted tick, the nodes left wait for the next tick.
+        if shared.past_deadline() {
+            return RUNNING;
+        }
+        shared.push_path(index);
+        #[cfg(feature = "node-stats")]
+        shared.counters.push(index);
+        let result = self.tick_shared(e, shared, blackboard, f);
+        // The behaviors still running below a terminated node are dropped with it.
+        if result.0 != Running && shared.aborts_handled() {
+            self.abort(shared);
+        }
+        #[cfg(feature = "node-stats")]
+        {
+            shared.counters.record(result.0);
+            shared.counters.pop();
+        }
+        shared.pop_path();
+        result
+    }
+
+    /// Same as `tick`, but with the state shared between nodes passed in
+    /// so that it can outlive the tick.
+    pub(crate) fn tick_shared<E, F, B>(
+        &mut self,
+        e: &E,
+        shared: &mut SharedState<A>,
+        blackboard: &mut B,
+        f: &mut F,
+    ) -> (Status, f64)
+        where
+            E: UpdateEvent,
+            F: FnMut(ActionArgs<E, A>, &mut ActionData, &mut B) -> (Status, f64),
+            A: Debug,
     {
         let upd = e.update(|args| Some(args.dt)).unwrap_or(None);
 
         // double match statements
         match (upd, self) {
-            (_, &mut ActionState(ref action)) => {
+            (_, &mut ActionState(ref action, ref mut data)) => {
                 // println!("In ActionState: {:?}", action);
-                f(
+                let result = f(
                     ActionArgs {
                         event: e,
                         dt: upd.unwrap_or(0.0),
                         action,
                     },
+                    data,
                     blackboard,
-                )
+                );
+                if result.0 != Running {
+                    data.clear();
+                }
+                #[cfg(feature = "node-stats")]
+                shared.counters.action_time(upd.unwrap_or(0.0), result);
+                result
             }
             (_, &mut InvertState(ref mut cur)) => {
                 // println!("In InvertState: {:?}", cur);
-                match cur.tick(e, blackboard, f) {
+                match cur.tick_child(0, e, shared, blackboard, f) {
                     (Running, dt) => (Running, dt),
                     (Failure, dt) => (Success, dt),
                     (Success, dt) => (Failure, dt),
@@ -156,7 +878,7 @@ impl<A: Clone> State<A> {
             }
             (_, &mut AlwaysSucceedState(ref mut cur)) => {
                 // println!("In AlwaysSucceedState: {:?}", cur);
-                match cur.tick(e, blackboard, f) {
+                match cur.tick_child(0, e, shared, blackboard, f) {
                     (Running, dt) => (Running, dt),
                     (_, dt) => (Success, dt),
                 }
@@ -180,7 +902,7 @@ impl<A: Clone> State<A> {
                 // remaining delta time after condition.
                 loop {
                     *status = match *status {
-                        Running => match state.tick(e, blackboard, f) {
+                        Running => match state.tick_child(0, e, shared, blackboard, f) {
                             (Running, dt) => {
                                 return (Running, dt);
                             }
@@ -195,8 +917,10 @@ impl<A: Clone> State<A> {
                                 Failure
                             }
                         },
-                        _ => {
-                            return state.tick(
+                        branch => {
+                            let index = if branch == Success { 1 } else { 2 };
+                            return state.tick_child(
+                                index,
                                 match upd {
                                     Some(_) => {
                                         remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
@@ -204,6 +928,7 @@ impl<A: Clone> State<A> {
                                     }
                                     _ => e,
                                 },
+                                shared,
                                 blackboard,
                                 f,
                             );
@@ -219,7 +944,7 @@ impl<A: Clone> State<A> {
                 // remaining delta time after condition.
                 loop {
                     *status = match *status {
-                        Running => match state.tick(e, blackboard, f) {
+                        Running => match state.tick_child(0, e, shared, blackboard, f) {
                             (Running, dt) => {
                                 return (Running, dt);
                             }
@@ -237,7 +962,8 @@ impl<A: Clone> State<A> {
                             return (Failure, remaining_dt);
                         }
                         _ => {
-                            return state.tick(
+                            return state.tick_child(
+                                1,
                                 match upd {
                                     Some(_) => {
                                         remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
@@ -245,6 +971,7 @@ impl<A: Clone> State<A> {
                                     }
                                     _ => e,
                                 },
+                                shared,
                                 blackboard,
                                 f,
                             );
@@ -262,6 +989,7 @@ impl<A: Clone> State<A> {
                     i,
                     cursor,
                     e,
+                    shared,
                     f,
                     blackboard,
                 })
@@ -276,6 +1004,7 @@ impl<A: Clone> State<A> {
                     i,
                     cursor,
                     e,
+                    shared,
                     f,
                     blackboard,
                 })
@@ -283,7 +1012,7 @@ impl<A: Clone> State<A> {
             (_, &mut WhileState(ref mut ev_cursor, ref rep, ref mut i, ref mut cursor)) => {
                 // println!("In WhileState: {:?}", ev_cursor);
                 // If the event terminates, do not execute the loop.
-                match ev_cursor.tick(e, blackboard, f) {
+                match ev_cursor.tick_child(0, e, shared, blackboard, f) {
                     (Running, _) => {}
                     x => return x,
                 };
@@ -292,7 +1021,8 @@ impl<A: Clone> State<A> {
                 let remaining_dt = upd.unwrap_or(0.0);
                 let remaining_e;
 
-                match cur.tick(
+                match cur.tick_child(
+                    *i + 1,
                     match upd {
                         Some(_) => {
                             remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
@@ -300,6 +1030,7 @@ impl<A: Clone> State<A> {
                         }
                         _ => e,
                     },
+                    shared,
                     blackboard,
                     f,
                 ) {
@@ -314,6 +1045,7 @@ impl<A: Clone> State<A> {
                         }
                     }
                 };
+                shared.step(*i, rep.len());
                 *i += 1;
                 // If end of repeated events,
                 // start over from the first one.
@@ -326,22 +1058,63 @@ impl<A: Clone> State<A> {
 
                 RUNNING
             }
+            (_, &mut BoundedWhileState(max_iterations, ref mut ev_cursor, ref rep, ref mut i, ref mut cursor)) => {
+                // If the event terminates, do not execute the loop.
+                match ev_cursor.tick_child(0, e, shared, blackboard, f) {
+                    (Running, _) => {}
+                    x => return x,
+                };
+                let mut remaining_dt = upd.unwrap_or(0.0);
+                let mut iterations = 0;
+                loop {
+                    let remaining_e;
+                    match cursor.tick_child(
+                        *i + 1,
+                        match upd {
+                            Some(_) => {
+                                remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
+                                &remaining_e
+                            }
+                            _ => e,
+                        },
+                        shared,
+                        blackboard,
+                        f,
+                    ) {
+                        (Failure, x) => return (Failure, x),
+                        (Running, _) => return RUNNING,
+                        (Success, new_dt) => remaining_dt = new_dt,
+                    };
+                    shared.step(*i, rep.len());
+                    *i += 1;
+                    if *i >= rep.len() {
+                        *i = 0;
+                        iterations += 1;
+                    }
+                    **cursor = State::new(rep[*i].clone());
+                    // Other events are 'consumed' and not passed to next,
+                    // and the loop yields once it is done for this tick.
+                    if upd.is_none() || (*i == 0 && iterations >= max_iterations) {
+                        return RUNNING;
+                    }
+                }
+            }
             (_, &mut WhenAllState(ref mut cursors)) => {
                 // println!("In WhenAllState: {:?}", cursors);
                 let any = false;
-                when_all(any, upd, cursors, e, f, blackboard)
+                when_all(any, upd, cursors, e, shared, f, blackboard)
             }
             (_, &mut WhenAnyState(ref mut cursors)) => {
                 // println!("In WhenAnyState: {:?}", cursors);
                 let any = true;
-                when_all(any, upd, cursors, e, f, blackboard)
+                when_all(any, upd, cursors, e, shared, f, blackboard)
             }
             (_, &mut AfterState(ref mut i, ref mut cursors)) => {
                 // println!("In AfterState: {}", i);
                 // Get the least delta time left over.
                 let mut min_dt = f64::MAX;
                 for (j, item) in cursors.iter_mut().enumerate().skip(*i) {
-                    match item.tick(e, blackboard, f) {
+                    match item.tick_child(j, e, shared, blackboard, f) {
                         (Running, _) => {
                             min_dt = 0.0;
                         }
@@ -386,7 +1159,7 @@ impl<A: Clone> State<A> {
                             *cur_seq_idx == 0,
                             "sequence index should always be 0 when condition is checked!"
                         );
-                        match condition_behavior.tick(e, blackboard, f) {
+                        match condition_behavior.tick_child(0, e, shared, blackboard, f) {
                             // if running, move to sequence:
                             (Running, _) => {}
                             // if success or failure, get out:
@@ -403,13 +1176,14 @@ impl<A: Clone> State<A> {
                         _ => e,
                     };
 
-                    match current_sequence_behavior.tick(ev, blackboard, f) {
+                    match current_sequence_behavior.tick_child(*cur_seq_idx + 1, ev, shared, blackboard, f) {
                         (Failure, x) => return (Failure, x),
                         (Running, _) => {
                             break;
                         }
                         (Success, new_dt) => {
                             // only success moves the sequence cursor forward:
+                            shared.step(*cur_seq_idx, all_sequence_behaviors.len());
                             *cur_seq_idx += 1;
 
                             // If end of repeated events,
@@ -429,7 +1203,685 @@ impl<A: Clone> State<A> {
                 }
                 RUNNING
             }
+            (_, &mut SharedCooldownState(ref key, duration, ref behavior, ref mut running, ref mut cursor)) => {
+                if !*running && shared.cooldown_remaining(key) > 0.0 {
+                    return (Failure, upd.unwrap_or(0.0));
+                }
+                match cursor.tick_child(0, e, shared, blackboard, f) {
+                    // Past the deadline of a budgeted tick, the behavior may not have run.
+                    (Running, dt) if shared.past_deadline() => (Running, dt),
+                    (Running, dt) => {
+                        *running = true;
+                        (Running, dt)
+                    }
+                    (status, dt) => {
+                        if status == Success {
+                            shared.start_cooldown(key, duration);
+                        }
+                        // Start over with a fresh cursor the next time
+                        // the cooldown lets the behavior through.
+                        *running = false;
+                        **cursor = State::new((**behavior).clone());
+                        (status, dt)
+                    }
+                }
+            }
+            (_, &mut TimeBudgetSequenceState(budget, ref mut spent, ref seq, ref mut i, ref mut cursor)) => {
+                // Only let the sequence use what is left of the budget.
+                let dt = upd.unwrap_or(0.0);
+                let slice = dt.min((budget - *spent).max(0.0));
+                let slice_e;
+                let select = false;
+                match sequence(SequenceArgs {
+                    select,
+                    upd: upd.map(|_| slice),
+                    seq,
+                    i,
+                    cursor,
+                    e: match upd {
+                        Some(_) => {
+                            slice_e = UpdateEvent::from_dt(slice, e).unwrap();
+                            &slice_e
+                        }
+                        _ => e,
+                    },
+                    shared,
+                    f,
+                    blackboard,
+                }) {
+                    (Running, _) => {
+                        *spent += slice;
+                        if upd.is_some() && *spent >= budget {
+                            (Failure, dt - slice)
+                        } else {
+                            RUNNING
+                        }
+                    }
+                    (status, remaining_dt) => (status, remaining_dt + dt - slice),
+                }
+            }
+            (_, &mut CooldownSelectState(cooldown, ref sel, ref mut current, ref mut cursor)) => {
+                let mut remaining_dt = upd.unwrap_or(0.0);
+                let mut j = current.unwrap_or(0);
+                loop {
+                    if current.is_none() {
+                        // Select the next behavior which is not on cooldown.
+                        let ready = (j..sel.len()).find(|&k| shared.child_cooldown(k) <= 0.0);
+                        // The behaviors before the one found are on cooldown.
+                        #[cfg(feature = "node-stats")]
+                        for k in j..ready.unwrap_or(sel.len()) {
+                            shared.counters.candidate(k, sel.len(), Candidate::OnCooldown);
+                        }
+                        match ready {
+                            Some(k) => {
+                                j = k;
+                                *current = Some(k);
+                                **cursor = State::new(sel[k].clone());
+                            }
+                            None => return (Failure, remaining_dt),
+                        }
+                    }
+                    let remaining_e;
+                    match cursor.tick_child(
+                        j,
+                        match upd {
+                            Some(_) => {
+                                remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
+                                &remaining_e
+                            }
+                            _ => e,
+                        },
+                        shared,
+                        blackboard,
+                        f,
+                    ) {
+                        (Running, _) => {
+                            #[cfg(feature = "node-stats")]
+                            shared.counters.candidate(j, sel.len(), Candidate::Chosen);
+                            return RUNNING;
+                        }
+                        (Success, new_dt) => {
+                            #[cfg(feature = "node-stats")]
+                            shared.counters.candidate(j, sel.len(), Candidate::Chosen);
+                            *current = None;
+                            return (Success, new_dt);
+                        }
+                        (Failure, new_dt) => {
+                            #[cfg(feature = "node-stats")]
+                            shared.counters.candidate(j, sel.len(), Candidate::Failed);
+                            shared.start_child_cooldown(j, cooldown);
+                            *current = None;
+                            j += 1;
+                            remaining_dt = new_dt;
+                        }
+                    }
+                }
+            }
+            (_, &mut NamedState(ref mut name, ref mut cur)) => {
+                // Lend the name to the shared state while ticking, so that
+                // the nodes below can refer to it without copying it.
+                shared.push_name(std::mem::take(name));
+                let result = cur.tick_shared(e, shared, blackboard, f);
+                *name = shared.pop_name();
+                result
+            }
+            (_, &mut RestartOnFailureState(ref behavior, ref mut cur)) => {
+                match cur.tick_child(0, e, shared, blackboard, f) {
+                    (Failure, _) => {
+                        // Start over on the next tick.
+                        **cur = State::new((**behavior).clone());
+                        RUNNING
+                    }
+                    x => x,
+                }
+            }
+            (_, &mut WithInitState(ref body, ref mut initialized, ref mut cur)) => {
+                let mut remaining_dt = upd.unwrap_or(0.0);
+                if !*initialized {
+                    match cur.tick_child(0, e, shared, blackboard, f) {
+                        (Success, new_dt) => {
+                            *initialized = true;
+                            **cur = State::new((**body).clone());
+                            match upd {
+                                // Change update event with remaining delta time.
+                                Some(_) => remaining_dt = new_dt,
+                                // Other events are 'consumed' and not passed to next.
+                                _ => return RUNNING,
+                            }
+                        }
+                        x => return x,
+                    }
+                }
+                let remaining_e;
+                match cur.tick_child(
+                    1,
+                    match upd {
+                        Some(_) => {
+                            remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
+                            &remaining_e
+                        }
+                        _ => e,
+                    },
+                    shared,
+                    blackboard,
+                    f,
+                ) {
+                    (Success, _) => {
+                        // Start the body over on the next tick.
+                        **cur = State::new((**body).clone());
+                        RUNNING
+                    }
+                    x => x,
+                }
+            }
+            (_, &mut TaggedState(ref tag, ref behavior, ref mut running, ref mut cursor)) => {
+                if let Some(status) = shared.disabled_tag(tag) {
+                    if *running {
+                        // Drop the running behavior, so that it starts over
+                        // once the tag is enabled again.
+                        *running = false;
+                        cursor.abort(shared);
+                        **cursor = State::new((**behavior).clone());
+                    }
+                    return (status, upd.unwrap_or(0.0));
+                }
+                let (status, dt) = cursor.tick_shared(e, shared, blackboard, f);
+                // Past the deadline of a budgeted tick, the behavior may not have run.
+                if status != Running || !shared.past_deadline() {
+                    *running = status == Running;
+                }
+                (status, dt)
+            }
+            (_, &mut DoWhileState(ref body, ref condition, ref mut checking, ref mut cur)) => {
+                let mut remaining_dt = upd.unwrap_or(0.0);
+                if !*checking {
+                    match cur.tick_child(0, e, shared, blackboard, f) {
+                        (Success, new_dt) => {
+                            *checking = true;
+                            **cur = State::new((**condition).clone());
+                            match upd {
+                                // Change update event with remaining delta time.
+                                Some(_) => remaining_dt = new_dt,
+                                // Other events are 'consumed' and not passed to next.
+                                _ => return RUNNING,
+                            }
+                        }
+                        x => return x,
+                    }
+                }
+                let remaining_e;
+                match cur.tick_child(
+                    1,
+                    match upd {
+                        Some(_) => {
+                            remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
+                            &remaining_e
+                        }
+                        _ => e,
+                    },
+                    shared,
+                    blackboard,
+                    f,
+                ) {
+                    (Running, _) => RUNNING,
+                    (Success, _) => {
+                        // Run the body again on the next tick.
+                        *checking = false;
+                        **cur = State::new((**body).clone());
+                        RUNNING
+                    }
+                    (Failure, new_dt) => (Success, new_dt),
+                }
+            }
+            (_, &mut ImpatientSelectState(timeout, ref sel, ref mut i, ref mut elapsed, ref mut cursor)) => {
+                let mut remaining_dt = upd.unwrap_or(0.0);
+                let mut remaining_e;
+                loop {
+                    match cursor.tick_child(
+                        *i,
+                        match upd {
+                            Some(_) => {
+                                remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
+                                &remaining_e
+                            }
+                            _ => e,
+                        },
+                        shared,
+                        blackboard,
+                        f,
+                    ) {
+                        (Running, _) => {
+                            *elapsed += remaining_dt;
+                            if *elapsed < timeout {
+                                #[cfg(feature = "node-stats")]
+                                shared.counters.candidate(*i, sel.len(), Candidate::Chosen);
+                                return RUNNING;
+                            }
+                            #[cfg(feature = "node-stats")]
+                            shared.counters.candidate(*i, sel.len(), Candidate::TimedOut);
+                            cursor.abort_child(*i, shared);
+                            // Give up on the behavior, and pass on the time past the timeout.
+                            remaining_dt = *elapsed - timeout;
+                        }
+                        (Success, new_dt) => {
+                            #[cfg(feature = "node-stats")]
+                            shared.counters.candidate(*i, sel.len(), Candidate::Chosen);
+                            return (Success, new_dt);
+                        }
+                        (Failure, new_dt) => {
+                            #[cfg(feature = "node-stats")]
+                            shared.counters.candidate(*i, sel.len(), Candidate::Failed);
+                            remaining_dt = new_dt;
+                        }
+                    };
+                    *i += 1;
+                    *elapsed = 0.0;
+                    if *i >= sel.len() {
+                        return (Failure, remaining_dt);
+                    }
+                    // Create a new cursor for next event.
+                    // Use the same pointer to avoid allocation.
+                    **cursor = State::new(sel[*i].clone());
+                    if upd.is_none() {
+                        // Other events are 'consumed' and not passed to next.
+                        return RUNNING;
+                    }
+                }
+            }
+            (_, &mut RoundRobinParallelState(per_tick, ref mut next, ref mut cursors, ref mut pending)) => {
+                let dt = upd.unwrap_or(0.0);
+                for (cur, pending) in cursors.iter().zip(pending.iter_mut()) {
+                    if cur.is_some() {
+                        *pending += dt;
+                    }
+                }
+                // Get the least delta time left over.
+                let mut min_dt = f64::MAX;
+                let mut ticked = 0;
+                let mut j = *next;
+                for _ in 0..cursors.len() {
+                    // Other events are passed to all the running behaviors.
+                    if upd.is_some() && ticked >= per_tick.max(1) {
+                        break;
+                    }
+                    if let Some(cur) = &mut cursors[j] {
+                        ticked += 1;
+                        let remaining_e;
+                        let status = cur.tick_child(
+                            j,
+                            match upd {
+                                Some(_) => {
+                                    // Catch up on the time passed since the behavior was last ticked.
+                                    remaining_e = UpdateEvent::from_dt(pending[j], e).unwrap();
+                                    &remaining_e
+                                }
+                                _ => e,
+                            },
+                            shared,
+                            blackboard,
+                            f,
+                        );
+                        if upd.is_some() {
+                            pending[j] = 0.0;
+                        }
+                        match status {
+                            (Running, _) => {}
+                            // The time left over can not be more than the time of this tick.
+                            (Failure, new_dt) => return (Failure, new_dt.min(dt)),
+                            (Success, new_dt) => {
+                                min_dt = min_dt.min(new_dt.min(dt));
+                                cursors[j] = None;
+                            }
+                        }
+                    }
+                    j = (j + 1) % cursors.len();
+                }
+                *next = j;
+                if cursors.iter().any(|cur| cur.is_some()) {
+                    RUNNING
+                } else if ticked == 0 {
+                    // If there are no behaviors, there is a whole 'dt' left.
+                    (Success, dt)
+                } else {
+                    (Success, min_dt)
+                }
+            }
 
+            (_, &mut EarlySuccessParallelState(threshold, ref mut successes, ref mut cursors)) => {
+                let dt = upd.unwrap_or(0.0);
+                // The delta time left over by the behavior that meets the
+                // threshold, or by the last one to fail.
+                let mut success_dt = None;
+                let mut failure_dt = dt;
+                for (j, cursor) in cursors.iter_mut().enumerate() {
+                    if let Some(cur) = cursor {
+                        match cur.tick_child(j, e, shared, blackboard, f) {
+                            (Running, _) => continue,
+                            (Success, new_dt) => {
+                                *successes += 1;
+                                if *successes == threshold {
+                                    success_dt = Some(new_dt);
+                                }
+                            }
+                            (Failure, new_dt) => failure_dt = new_dt,
+                        }
+                        *cursor = None;
+                    }
+                }
+                let running = cursors.iter().filter(|cur| cur.is_some()).count();
+                if *successes >= threshold {
+                    // The behaviors which are still running go on in the background,
+                    // instead of those left there the last time the node ran.
+                    shared.abort_background_of_node();
+                    for (j, cursor) in cursors.iter_mut().enumerate() {
+                        if let Some(cur) = cursor.take() {
+                            shared.push_background(j, cur);
+                        }
+                    }
+                    (Success, success_dt.unwrap_or(dt))
+                } else if *successes + running < threshold {
+                    (Failure, failure_dt)
+                } else {
+                    RUNNING
+                }
+            }
+            (_, &mut IntervalGuardState(interval, ref condition, ref mut elapsed, ref mut checked, ref mut cur)) => {
+                if *checked {
+                    *elapsed += upd.unwrap_or(0.0);
+                }
+                if !*checked || *elapsed >= interval {
+                    let mut check = State::new((**condition).clone());
+                    match check.tick_child(0, e, shared, blackboard, f) {
+                        (Failure, dt) => {
+                            *checked = false;
+                            return (Failure, dt);
+                        }
+                        // Past the deadline of a budgeted tick, the condition may not have
+                        // been checked, so it is checked on the next tick.
+                        (Running, _) if shared.past_deadline() => return RUNNING,
+                        _ => {}
+                    }
+                    *checked = true;
+                    *elapsed = 0.0;
+                }
+                match cur.tick_child(1, e, shared, blackboard, f) {
+                    (Running, _) => RUNNING,
+                    x => {
+                        // The condition is checked again when the node is entered anew.
+                        *checked = false;
+                        x
+                    }
+                }
+            }
+            (_, &mut OrderedParallelState(ref mut next, ref mut cursors, ref mut results)) => {
+                // The least delta time left over by the behaviors terminating
+                // on this tick, since the results can only be accepted then.
+                let mut min_dt = upd.unwrap_or(0.0);
+                for (j, cursor) in cursors.iter_mut().enumerate() {
+                    if let Some(cur) = cursor {
+                        let (status, new_dt) = cur.tick_child(j, e, shared, blackboard, f);
+                        if status != Running {
+                            results[j] = status;
+                            min_dt = min_dt.min(new_dt);
+                            *cursor = None;
+                        }
+                    }
+                }
+                while *next < results.len() {
+                    match results[*next] {
+                        Running => return RUNNING,
+                        Failure => return (Failure, min_dt),
+                        Success => {
+                            shared.step(*next, results.len());
+                            *next += 1;
+                        }
+                    }
+                }
+                (Success, min_dt)
+            }
+            (_, &mut FinallyState(ref body, ref finalizer, ref mut status, ref mut running, ref mut cur)) => {
+                shared.handle_aborts();
+                *running = true;
+                let mut remaining_dt = upd.unwrap_or(0.0);
+                if *status == Running {
+                    match cur.tick_child(0, e, shared, blackboard, f) {
+                        (Running, _) => return RUNNING,
+                        (body_status, new_dt) => {
+                            *status = body_status;
+                            **cur = State::new((**finalizer).clone());
+                            match upd {
+                                // Change update event with remaining delta time.
+                                Some(_) => remaining_dt = new_dt,
+                                // Other events are 'consumed' and not passed to next.
+                                _ => return RUNNING,
+                            }
+                        }
+                    }
+                }
+                let remaining_e;
+                match cur.tick_child(
+                    1,
+                    match upd {
+                        Some(_) => {
+                            remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
+                            &remaining_e
+                        }
+                        _ => e,
+                    },
+                    shared,
+                    blackboard,
+                    f,
+                ) {
+                    (Running, _) => RUNNING,
+                    (finalizer_status, new_dt) => {
+                        let result = if finalizer_status == Failure { Failure } else { *status };
+                        // Start over with the body the next time.
+                        *status = Running;
+                        *running = false;
+                        **cur = State::new((**body).clone());
+                        (result, new_dt)
+                    }
+                }
+            }
+            (_, &mut SelectReactiveState(ref sel, ref mut i, ref mut cursor)) => {
+                let mut remaining_dt = upd.unwrap_or(0.0);
+                let mut remaining_e;
+                for j in 0..sel.len() {
+                    let ev = match upd {
+                        Some(_) => {
+                            remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
+                            &remaining_e
+                        }
+                        _ => e,
+                    };
+                    let status = if j < *i {
+                        // Check the behaviors before the running one again, from the start.
+                        let mut state = State::new(sel[j].clone());
+                        let status = state.tick_child(j, ev, shared, blackboard, f);
+                        if status.0 == Running && shared.past_deadline() {
+                            // Past the deadline of a budgeted tick, the behavior may not
+                            // have run, so the running one keeps going until it is checked
+                            // again on the next tick.
+                            return RUNNING;
+                        }
+                        if status.0 != Failure {
+                            // The behavior takes over from the one that was running.
+                            cursor.abort_child(*i, shared);
+                            #[cfg(feature = "node-stats")]
+                            shared.counters.candidate(*i, sel.len(), Candidate::NotTried);
+                            **cursor = state;
+                            *i = j;
+                        }
+                        status
+                    } else {
+                        if j > *i {
+                            // Create a new cursor for next event.
+                            // Use the same pointer to avoid allocation.
+                            **cursor = State::new(sel[j].clone());
+                            *i = j;
+                        }
+                        cursor.tick_child(j, ev, shared, blackboard, f)
+                    };
+                    match status {
+                        (Running, _) => {
+                            #[cfg(feature = "node-stats")]
+                            shared.counters.candidate(j, sel.len(), Candidate::Chosen);
+                            return RUNNING;
+                        }
+                        (Success, new_dt) => {
+                            #[cfg(feature = "node-stats")]
+                            shared.counters.candidate(j, sel.len(), Candidate::Chosen);
+                            return (Success, new_dt);
+                        }
+                        (Failure, new_dt) => {
+                            #[cfg(feature = "node-stats")]
+                            shared.counters.candidate(j, sel.len(), Candidate::Failed);
+                            remaining_dt = new_dt;
+                        }
+                    }
+                }
+                (Failure, remaining_dt)
+            }
+            (_, &mut RoleParallelState(ref roles, ref mut cursors)) => {
+                // Get the least delta time left over by the required behaviors.
+                let mut min_dt = upd.unwrap_or(0.0);
+                for (j, cursor) in cursors.iter_mut().enumerate() {
+                    if let Some(cur) = cursor {
+                        let (status, new_dt) = cur.tick_child(j, e, shared, blackboard, f);
+                        if status == Running {
+                            continue;
+                        }
+                        *cursor = None;
+                        if roles[j] == Role::Required {
+                            if status == Failure {
+                                return (Failure, new_dt);
+                            }
+                            min_dt = min_dt.min(new_dt);
+                        }
+                    }
+                }
+                let required_running = cursors
+                    .iter()
+                    .zip(roles)
+                    .any(|(cur, role)| cur.is_some() && *role == Role::Required);
+                if required_running {
+                    RUNNING
+                } else {
+                    (Success, min_dt)
+                }
+            }
+            (_, &mut ResourceParallelState(ref pool, ref costs, ref mut reserved, ref mut cursors)) => {
+                // The costs of the running behaviors are given back if they are aborted.
+                shared.handle_aborts();
+                let mut min_dt = upd.unwrap_or(0.0);
+                for (j, cursor) in cursors.iter_mut().enumerate() {
+                    if let Some(cur) = cursor {
+                        if !reserved[j] {
+                            if !shared.take_resource(pool, costs[j]) {
+                                // Deferred until the pool can afford it.
+                                continue;
+                            }
+                            reserved[j] = true;
+                        }
+                        let (status, new_dt) = cur.tick_child(j, e, shared, blackboard, f);
+                        if status == Running {
+                            continue;
+                        }
+                        *cursor = None;
+                        reserved[j] = false;
+                        if status == Failure {
+                            shared.refund_resource(pool, costs[j]);
+                            return (Failure, new_dt);
+                        }
+                        min_dt = min_dt.min(new_dt);
+                    }
+                }
+                if cursors.iter().any(Option::is_some) {
+                    RUNNING
+                } else {
+                    (Success, min_dt)
+                }
+            }
+            (_, &mut WithTimeoutState(timeout, ref on_timeout, ref mut elapsed, ref mut timed_out, ref mut cur)) => {
+                let mut remaining_dt = upd.unwrap_or(0.0);
+                if !*timed_out {
+                    match cur.tick_child(0, e, shared, blackboard, f) {
+                        (Running, _) => {
+                            *elapsed += remaining_dt;
+                            if *elapsed < timeout {
+                                return RUNNING;
+                            }
+                            cur.abort_child(0, shared);
+                            *timed_out = true;
+                            **cur = State::new((**on_timeout).clone());
+                            // Pass on the time past the timeout.
+                            remaining_dt = *elapsed - timeout;
+                        }
+                        x => return x,
+                    }
+                }
+                let remaining_e;
+                cur.tick_child(
+                    1,
+                    match upd {
+                        Some(_) => {
+                            remaining_e = UpdateEvent::from_dt(remaining_dt, e).unwrap();
+                            &remaining_e
+                        }
+                        _ => e,
+                    },
+                    shared,
+                    blackboard,
+                    f,
+                )
+            }
+            (
+                _,
+                &mut AdaptiveSelectState(ref key, learning_rate, ref sel, ref mut order, ref mut i, ref mut cursor),
+            ) => {
+                if order.is_empty() {
+                    *order = shared.adaptive_order(key, sel.len());
+                    *i = 0;
+                    **cursor = State::new(sel[order[0]].clone());
+                }
+                ordered_select(OrderedSelectArgs {
+                    upd,
+                    sel,
+                    order,
+                    i,
+                    cursor,
+                    learning: Some((key.as_str(), learning_rate)),
+                    e,
+                    shared,
+                    f,
+                    blackboard,
+                })
+            }
+            (_, &mut SeededState(salt, ref mut cur)) => {
+                let enclosing = shared.enter_stream(salt);
+                let result = cur.tick_child(0, e, shared, blackboard, f);
+                shared.leave_stream(enclosing);
+                result
+            }
+            (_, &mut TieBreakSelectState(ref tie_break, ref sel, ref mut order, ref mut i, ref mut cursor)) => {
+                if order.is_empty() {
+                    *order = shared.tie_break_order(tie_break, sel.len());
+                    *i = 0;
+                    **cursor = State::new(sel[order[0]].clone());
+                }
+                ordered_select(OrderedSelectArgs {
+                    upd,
+                    sel,
+                    order,
+                    i,
+                    cursor,
+                    learning: None,
+                    e,
+                    shared,
+                    f,
+                    blackboard,
+                })
+            }
             // WaitForeverState, WaitState
             _ => RUNNING,
         }
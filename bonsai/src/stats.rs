@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::Status;
+
+/// How often a node has run, as returned by `BT::node_stats`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeStats {
+    /// Child indices leading from the root to the node, like the paths of `BT::active_timers`.
+    pub path: Vec<usize>,
+    /// Number of times the node was entered, i.e. ticked while not already running.
+    pub activations: u64,
+    /// Number of times the node succeeded.
+    pub successes: u64,
+    /// Number of times the node failed.
+    pub failures: u64,
+}
+
+/// Why a selector chose the behavior it did, as returned by `BT::last_selection`.
+///
+/// Selectors try their behaviors in order, so a behavior is chosen because
+/// it is the first one that did not fail or get skipped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectionInfo {
+    /// Index of the behavior that is running or has succeeded, if any.
+    pub chosen: Option<usize>,
+    /// What happened to each behavior of the selector, by index.
+    pub candidates: Vec<Candidate>,
+}
+
+/// How many behaviors a selector tries before one succeeds, over the
+/// session, as returned by `BT::selector_depths`.
+///
+/// The behaviors tried are those that failed or timed out, and the one that
+/// succeeded, but not those skipped on cooldown. A selector whose first
+/// behavior keeps failing, e.g. a high priority branch that rarely applies,
+/// has an average above 1, and could try it later instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectorDepth {
+    /// Child indices leading from the root to the selector, like the paths of `BT::active_timers`.
+    pub path: Vec<usize>,
+    /// Number of times the selector succeeded.
+    pub successes: u64,
+    /// Number of behaviors tried, summed over the successes.
+    pub total_tried: u64,
+    /// Most behaviors tried before a success.
+    pub max_tried: usize,
+}
+
+impl SelectorDepth {
+    /// Average number of behaviors tried before a success.
+    pub fn average(&self) -> f64 {
+        self.total_tried as f64 / self.successes as f64
+    }
+}
+
+/// What happened to a behavior of a selector during its last activation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Candidate {
+    /// The selector did not get to the behavior.
+    NotTried,
+    /// The behavior failed.
+    Failed,
+    /// The behavior was skipped because it was on cooldown, see `CooldownSelect`.
+    OnCooldown,
+    /// The behavior was given up on after running too long, see `ImpatientSelect`.
+    TimedOut,
+    /// The behavior is running or has succeeded.
+    Chosen,
+}
+
+/// How long an action has been running each time it completed, as returned
+/// by `BT::action_timings`.
+///
+/// The durations are in the time of the tree, i.e. the delta time of the
+/// ticks the action ran for, up to the time it left over when it completed.
+/// An action which completes on the tick it starts takes no time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    /// Upper bounds of the buckets, in increasing order, see `BT::set_timing_buckets`.
+    pub bounds: Vec<f64>,
+    /// Number of completions per bucket: `counts[i]` counts the durations
+    /// up to `bounds[i]` and above the bound before it, and the last count,
+    /// past the bounds, the durations above all the bounds.
+    pub counts: Vec<u64>,
+}
+
+impl Histogram {
+    fn new(bounds: &[f64]) -> Self {
+        Histogram {
+            bounds: bounds.to_vec(),
+            counts: vec![0; bounds.len() + 1],
+        }
+    }
+
+    fn add(&mut self, duration: f64) {
+        let i = self.bounds.iter().position(|bound| duration <= *bound).unwrap_or(self.bounds.len());
+        self.counts[i] += 1;
+    }
+
+    /// Number of completions counted.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// The bounds of the buckets of the action timings, unless set with
+/// `BT::set_timing_buckets`.
+pub(crate) const DEFAULT_TIMING_BUCKETS: [f64; 6] = [0.0, 0.01, 0.1, 1.0, 10.0, 60.0];
+
+#[derive(Clone, Debug, Default)]
+struct Counter {
+    activations: u64,
+    successes: u64,
+    failures: u64,
+    // Whether the node returned `Running` the last time it was ticked.
+    running: bool,
+    // The candidates of a selector, and the activation they belong to.
+    selection: Option<(u64, Vec<Candidate>)>,
+    // How many behaviors a selector tried each time it succeeded.
+    depth: Option<SelectorDepth>,
+    // How long an action has been running during its current activation.
+    running_time: f64,
+    // How long an action ran for each time it completed.
+    timings: Option<Histogram>,
+}
+
+/// Counts how often each node runs, across ticks and resets.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct NodeCounters {
+    /// Path of the node being ticked.
+    path: Vec<usize>,
+    counters: BTreeMap<Vec<usize>, Counter>,
+    /// Bounds of the buckets of the action timings, if not the default ones.
+    timing_buckets: Option<Vec<f64>>,
+}
+
+impl NodeCounters {
+    /// Enters the child at `index` of the node being ticked.
+    pub(crate) fn push(&mut self, index: usize) {
+        self.path.push(index);
+    }
+
+    /// Leaves the node being ticked, see `push`.
+    pub(crate) fn pop(&mut self) {
+        self.path.pop();
+    }
+
+    /// Counts a tick of the node being ticked which returned `status`.
+    pub(crate) fn record(&mut self, status: Status) {
+        let counter = self.counters.entry(self.path.clone()).or_default();
+        if !counter.running {
+            counter.activations += 1;
+        }
+        counter.running = status == Status::Running;
+        if let (Status::Success, Some((activation, candidates))) = (status, &counter.selection) {
+            if *activation == counter.activations {
+                let tried = candidates
+                    .iter()
+                    .filter(|c| matches!(c, Candidate::Failed | Candidate::TimedOut | Candidate::Chosen))
+                    .count();
+                let depth = counter.depth.get_or_insert_with(|| SelectorDepth {
+                    path: self.path.clone(),
+                    successes: 0,
+                    total_tried: 0,
+                    max_tried: 0,
+                });
+                depth.successes += 1;
+                depth.total_tried += tried as u64;
+                depth.max_tried = depth.max_tried.max(tried);
+            }
+        }
+        match status {
+            Status::Success => counter.successes += 1,
+            Status::Failure => counter.failures += 1,
+            Status::Running => return,
+        }
+        // The nodes below are done too, and are entered anew next time,
+        // even those which were still running when they were dropped.
+        let path = &self.path;
+        for (_, counter) in self.counters.range_mut(path.clone()..).take_while(|(p, _)| p.starts_with(path)) {
+            counter.running = false;
+        }
+    }
+
+    /// Records what happened to the behavior at `index` of the selector being
+    /// ticked, which has `len` behaviors.
+    pub(crate) fn candidate(&mut self, index: usize, len: usize, candidate: Candidate) {
+        let counter = self.counters.entry(self.path.clone()).or_default();
+        // The activation being ticked, which is only counted once it returns.
+        let activation = counter.activations + u64::from(!counter.running);
+        if !matches!(&counter.selection, Some((a, _)) if *a == activation) {
+            counter.selection = Some((activation, vec![Candidate::NotTried; len]));
+        }
+        if let Some((_, candidates)) = &mut counter.selection {
+            candidates[index] = candidate;
+        }
+    }
+
+    /// Adds the time the action being ticked ran for out of `dt` on this
+    /// tick, given its `result`, and once it completes, counts how long it
+    /// ran in its timings.
+    pub(crate) fn action_time(&mut self, dt: f64, result: (Status, f64)) {
+        let buckets = self.timing_buckets.as_deref().unwrap_or(&DEFAULT_TIMING_BUCKETS);
+        let counter = self.counters.entry(self.path.clone()).or_default();
+        if !counter.running {
+            counter.running_time = 0.0;
+        }
+        match result {
+            (Status::Running, _) => counter.running_time += dt,
+            (_, remaining_dt) => {
+                counter.running_time += (dt - remaining_dt).max(0.0);
+                let running_time = counter.running_time;
+                counter.timings.get_or_insert_with(|| Histogram::new(buckets)).add(running_time);
+            }
+        }
+    }
+
+    /// Replaces the bounds of the buckets of the action timings, which
+    /// clears the timings.
+    pub(crate) fn set_timing_buckets(&mut self, bounds: Vec<f64>) {
+        self.timing_buckets = Some(bounds);
+        for counter in self.counters.values_mut() {
+            counter.timings = None;
+        }
+    }
+
+    pub(crate) fn timings(&self) -> HashMap<Vec<usize>, Histogram> {
+        self.counters
+            .iter()
+            .filter_map(|(path, counter)| Some((path.clone(), counter.timings.clone()?)))
+            .collect()
+    }
+
+    /// Forgets which nodes are running, keeping the counts.
+    pub(crate) fn reset(&mut self) {
+        self.path.clear();
+        for counter in self.counters.values_mut() {
+            counter.running = false;
+        }
+    }
+
+    pub(crate) fn stats(&self) -> Vec<NodeStats> {
+        self.counters
+            .iter()
+            .map(|(path, counter)| NodeStats {
+                path: path.clone(),
+                activations: counter.activations,
+                successes: counter.successes,
+                failures: counter.failures,
+            })
+            .collect()
+    }
+
+    pub(crate) fn depths(&self) -> Vec<SelectorDepth> {
+        self.counters.values().filter_map(|counter| counter.depth.clone()).collect()
+    }
+
+    pub(crate) fn selection(&self, path: &[usize]) -> Option<SelectionInfo> {
+        let (_, candidates) = self.counters.get(path)?.selection.as_ref()?;
+        Some(SelectionInfo {
+            chosen: candidates.iter().position(|c| *c == Candidate::Chosen),
+            candidates: candidates.clone(),
+        })
+    }
+}
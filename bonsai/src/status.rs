@@ -21,3 +21,72 @@ pub enum Status {
     /// with no clear definition of an end-state
     Running,
 }
+
+impl Status {
+    /// Combines two statuses like a logical and, e.g. of two conditions
+    /// that must both hold.
+    ///
+    /// `Failure` takes precedence over `Running`, which takes precedence
+    /// over `Success`:
+    ///
+    /// | `and`     | Success | Failure | Running |
+    /// |-----------|---------|---------|---------|
+    /// | Success   | Success | Failure | Running |
+    /// | Failure   | Failure | Failure | Failure |
+    /// | Running   | Running | Failure | Running |
+    pub fn and(self, other: Status) -> Status {
+        match (self, other) {
+            (Status::Failure, _) | (_, Status::Failure) => Status::Failure,
+            (Status::Running, _) | (_, Status::Running) => Status::Running,
+            (Status::Success, Status::Success) => Status::Success,
+        }
+    }
+
+    /// Combines two statuses like a logical or, e.g. of two conditions
+    /// of which one must hold.
+    ///
+    /// `Success` takes precedence over `Running`, which takes precedence
+    /// over `Failure`:
+    ///
+    /// | `or`      | Success | Failure | Running |
+    /// |-----------|---------|---------|---------|
+    /// | Success   | Success | Success | Success |
+    /// | Failure   | Success | Failure | Running |
+    /// | Running   | Success | Running | Running |
+    pub fn or(self, other: Status) -> Status {
+        match (self, other) {
+            (Status::Success, _) | (_, Status::Success) => Status::Success,
+            (Status::Running, _) | (_, Status::Running) => Status::Running,
+            (Status::Failure, Status::Failure) => Status::Failure,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Status::{self, Failure, Running, Success};
+
+    #[test]
+    fn test_and_or() {
+        let invert = |s: Status| match s {
+            Success => Failure,
+            Failure => Success,
+            Running => Running,
+        };
+        let all = [Success, Failure, Running];
+        for a in all {
+            for b in all {
+                assert_eq!(a.and(b), b.and(a));
+                assert_eq!(a.or(b), b.or(a));
+                // inverting the statuses swaps `and` and `or`
+                assert_eq!(invert(a.and(b)), invert(a).or(invert(b)));
+            }
+        }
+        assert_eq!(Success.and(Success), Success);
+        assert_eq!(Success.and(Running), Running);
+        assert_eq!(Running.and(Failure), Failure);
+        assert_eq!(Failure.or(Failure), Failure);
+        assert_eq!(Failure.or(Running), Running);
+        assert_eq!(Running.or(Success), Success);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::Behavior;
+
+/// Starts a sequence of steps, some of which can be optional, see [Steps].
+pub fn steps<A>() -> Steps<A> {
+    Steps { steps: vec![] }
+}
+
+/// Builds a [Behavior::Sequence] whose steps can be skipped unless a
+/// condition holds, e.g. to reload only when low on ammo, instead of
+/// wrapping each of them in an `If` by hand.
+///
+/// A step added with `step_if` expands to `If(condition, behavior,
+/// WhenAll([]))`: the condition is run first, and if it fails, the step is
+/// skipped and succeeds right away on any event, so that the sequence goes
+/// on with the next step. If the condition succeeds, the step ends with the result of
+/// the behavior, like any other step.
+///
+/// ```
+/// use bonsai_bt::{steps, Action, Behavior, If, Sequence, WhenAll};
+///
+/// let attack: Behavior<&str> = steps()
+///     .step(Action("aim"))
+///     .step_if(Action("low on ammo"), Action("reload"))
+///     .step(Action("shoot"))
+///     .build();
+/// assert_eq!(
+///     attack,
+///     Sequence(vec![
+///         Action("aim"),
+///         If(Box::new(Action("low on ammo")), Box::new(Action("reload")), Box::new(WhenAll(vec![]))),
+///         Action("shoot"),
+///     ])
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Steps<A> {
+    steps: Vec<Behavior<A>>,
+}
+
+impl<A> Steps<A> {
+    /// Runs `behavior` as the next step.
+    pub fn step(mut self, behavior: Behavior<A>) -> Self {
+        self.steps.push(behavior);
+        self
+    }
+
+    /// Runs `behavior` as the next step if `condition` succeeds, and skips
+    /// it otherwise.
+    pub fn step_if(mut self, condition: Behavior<A>, behavior: Behavior<A>) -> Self {
+        self.steps.push(Behavior::If(
+            Box::new(condition),
+            Box::new(behavior),
+            Box::new(Behavior::WhenAll(vec![])),
+        ));
+        self
+    }
+
+    /// Builds the sequence of the steps, which must not be empty.
+    pub fn build(self) -> Behavior<A> {
+        Behavior::Sequence(self.steps)
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use crate::{Behavior, Status, BT};
+
+/// The definition of a `BT`, without its runtime state and its blackboard,
+/// as returned by `BT::template`.
+///
+/// Meant for spawning many agents of the same kind from one tree: each call
+/// to `instantiate` builds a tree that starts from scratch, as if built with
+/// `BT::new`, instead of cloning the state, the node counts and the
+/// blackboard of a tree that has already run. The definition itself is not
+/// copied: the template and the trees built from it share it, and only the
+/// runtime state is built for each tree. Tags disabled in the tree the
+/// template was taken from are disabled in the new trees as well, since
+/// they are configuration rather than state.
+///
+/// ```
+/// use bonsai_bt::{Action, Sequence, BT};
+///
+/// let bt = BT::new(Sequence(vec![Action("aim"), Action("shoot")]), 0);
+/// let template = bt.template();
+///
+/// // every agent gets a blackboard and a random stream of its own
+/// let agents: Vec<BT<&str, u32>> = (0..3).map(|i| template.instantiate(i, i as u64)).collect();
+/// assert_eq!(*agents[2].get_blackboard().get_db(), 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BtTemplate<A> {
+    behavior: Arc<Behavior<A>>,
+    disabled_tags: HashMap<String, Status>,
+}
+
+impl<A: Clone + Debug> BtTemplate<A> {
+    pub(crate) fn new(behavior: Arc<Behavior<A>>, disabled_tags: HashMap<String, Status>) -> Self {
+        BtTemplate {
+            behavior,
+            disabled_tags,
+        }
+    }
+
+    /// The behavior the trees are built from.
+    pub fn behavior(&self) -> &Behavior<A> {
+        &self.behavior
+    }
+
+    /// Builds a tree ready to be ticked, with `blackboard` and with its
+    /// random number generator seeded with `seed`, see `BT::set_seed`.
+    pub fn instantiate<K: Debug>(&self, blackboard: K, seed: u64) -> BT<A, K> {
+        let mut bt = BT::from_shared(Arc::clone(&self.behavior), blackboard);
+        for (tag, status) in &self.disabled_tags {
+            bt.disable_tag(tag, *status);
+        }
+        bt.set_seed(seed);
+        bt
+    }
+}
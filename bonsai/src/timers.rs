@@ -0,0 +1,203 @@
+use crate::shared::SharedState;
+use crate::state::State::{self, *};
+use crate::status::Status;
+
+/// A time-based node which is currently active, as returned by `BT::active_timers`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimerInfo {
+    /// Child indices leading from the root to the node.
+    ///
+    /// The indices refer to the children as they are declared in the behavior,
+    /// e.g. `If(condition, success, failure)` has the children 0, 1 and 2, and
+    /// `While(condition, [A, B])` has the condition at 0 followed by `A` and `B`.
+    pub path: Vec<usize>,
+    /// Time elapsed in seconds.
+    pub elapsed: f64,
+    /// Total duration in seconds.
+    pub total: f64,
+}
+
+// Walks the currently active cursors of the state and collects the
+// time-based nodes with time left on them.
+pub(crate) fn active_timers<A>(
+    state: &State<A>,
+    shared: &SharedState<A>,
+    path: &mut Vec<usize>,
+    timers: &mut Vec<TimerInfo>,
+) {
+    match state {
+        WaitState(total, elapsed) => {
+            if elapsed < total {
+                timers.push(TimerInfo {
+                    path: path.clone(),
+                    elapsed: *elapsed,
+                    total: *total,
+                });
+            }
+        }
+        ActionState(_, _) | WaitForeverState => {}
+        // Names do not add a level to the path.
+        NamedState(_, cur) => active_timers(cur, shared, path, timers),
+        TaggedState(_, _, running, cur) => {
+            if *running {
+                active_timers(cur, shared, path, timers)
+            }
+        }
+        InvertState(cur) | AlwaysSucceedState(cur) | RestartOnFailureState(_, cur) | SeededState(_, cur) => {
+            child(0, cur, shared, path, timers)
+        }
+        IfState(_, _, status, cur) => {
+            let index = match status {
+                Status::Running => 0,
+                Status::Success => 1,
+                Status::Failure => 2,
+            };
+            child(index, cur, shared, path, timers)
+        }
+        IfThenState(_, status, cur) => match status {
+            Status::Running => child(0, cur, shared, path, timers),
+            Status::Success => child(1, cur, shared, path, timers),
+            Status::Failure => {}
+        },
+        SelectState(seq, i, cur) | SelectReactiveState(seq, i, cur) | SequenceState(seq, i, cur) => {
+            if *i < seq.len() {
+                child(*i, cur, shared, path, timers)
+            }
+        }
+        WhileState(ev_cursor, _, i, cur)
+        | RepeatSequenceState(ev_cursor, _, i, _, cur)
+        | BoundedWhileState(_, ev_cursor, _, i, cur) => {
+            child(0, ev_cursor, shared, path, timers);
+            child(*i + 1, cur, shared, path, timers)
+        }
+        WhenAllState(cursors)
+        | WhenAnyState(cursors)
+        | RoundRobinParallelState(_, _, cursors, _)
+        | EarlySuccessParallelState(_, _, cursors)
+        | OrderedParallelState(_, cursors, _)
+        | RoleParallelState(_, cursors)
+        | ResourceParallelState(_, _, _, cursors) => {
+            for (j, cur) in cursors.iter().enumerate() {
+                if let Some(cur) = cur {
+                    child(j, cur, shared, path, timers)
+                }
+            }
+        }
+        AfterState(i, cursors) => {
+            for (j, cur) in cursors.iter().enumerate().skip(*i) {
+                child(j, cur, shared, path, timers)
+            }
+        }
+        SharedCooldownState(key, duration, _, running, cur) => {
+            let remaining = shared.cooldown_remaining(key);
+            if remaining > 0.0 {
+                timers.push(TimerInfo {
+                    path: path.clone(),
+                    elapsed: (duration - remaining).max(0.0),
+                    total: *duration,
+                });
+            }
+            if *running {
+                child(0, cur, shared, path, timers)
+            }
+        }
+        CooldownSelectState(cooldown, sel, current, cur) => {
+            // The cooldowns are reported at the path of the behavior they apply to.
+            for j in 0..sel.len() {
+                path.push(j);
+                let remaining = shared.select_cooldown(path);
+                if remaining > 0.0 {
+                    timers.push(TimerInfo {
+                        path: path.clone(),
+                        elapsed: (cooldown - remaining).max(0.0),
+                        total: *cooldown,
+                    });
+                }
+                path.pop();
+            }
+            if let Some(j) = current {
+                child(*j, cur, shared, path, timers)
+            }
+        }
+        FinallyState(_, _, status, running, cur) => {
+            // `Finally(body, finalizer)` has `body` at 0 and `finalizer` at 1.
+            if *running {
+                let index = if *status == Status::Running { 0 } else { 1 };
+                child(index, cur, shared, path, timers)
+            }
+        }
+        DoWhileState(_, _, checking, cur) => {
+            // `DoWhile(body, condition)` has `body` at 0 and `condition` at 1.
+            let index = if *checking { 1 } else { 0 };
+            child(index, cur, shared, path, timers)
+        }
+        WithInitState(_, initialized, cur) => {
+            // `WithInit(init, body)` has `init` at 0 and `body` at 1.
+            let index = if *initialized { 1 } else { 0 };
+            child(index, cur, shared, path, timers)
+        }
+        AdaptiveSelectState(_, _, _, order, i, cur) | TieBreakSelectState(_, _, order, i, cur) => {
+            if *i < order.len() {
+                child(order[*i], cur, shared, path, timers)
+            }
+        }
+        ImpatientSelectState(timeout, sel, i, elapsed, cur) => {
+            if *i < sel.len() {
+                timers.push(TimerInfo {
+                    path: path.clone(),
+                    elapsed: *elapsed,
+                    total: *timeout,
+                });
+                child(*i, cur, shared, path, timers)
+            }
+        }
+        WithTimeoutState(timeout, _, elapsed, timed_out, cur) => {
+            // `WithTimeout(timeout, behavior, on_timeout)` has `behavior` at 0 and `on_timeout` at 1.
+            if *timed_out {
+                child(1, cur, shared, path, timers)
+            } else {
+                timers.push(TimerInfo {
+                    path: path.clone(),
+                    elapsed: *elapsed,
+                    total: *timeout,
+                });
+                child(0, cur, shared, path, timers)
+            }
+        }
+        IntervalGuardState(interval, _, elapsed, checked, cur) => {
+            if *checked {
+                timers.push(TimerInfo {
+                    path: path.clone(),
+                    elapsed: *elapsed,
+                    total: *interval,
+                });
+            }
+            // `IntervalGuard(interval, condition, behavior)` has the behavior at 1.
+            child(1, cur, shared, path, timers)
+        }
+        TimeBudgetSequenceState(budget, spent, seq, i, cur) => {
+            if spent < budget {
+                timers.push(TimerInfo {
+                    path: path.clone(),
+                    elapsed: *spent,
+                    total: *budget,
+                });
+            }
+            if *i < seq.len() {
+                child(*i, cur, shared, path, timers)
+            }
+        }
+    }
+}
+
+fn child<A>(
+    index: usize,
+    state: &State<A>,
+    shared: &SharedState<A>,
+    path: &mut Vec<usize>,
+    timers: &mut Vec<TimerInfo>,
+) {
+    path.push(index);
+    active_timers(state, shared, path, timers);
+    path.pop();
+}
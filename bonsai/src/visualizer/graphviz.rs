@@ -1,6 +1,6 @@
 #![allow(dead_code, unused_imports, unused_variables)]
 
-use crate::{Behavior, Select, Sequence, State, BT};
+use crate::{Behavior, Role, Select, Sequence, State, TieBreak, BT};
 use petgraph::{graph::Graph, stable_graph::NodeIndex, Direction::Outgoing};
 use std::{collections::VecDeque, fmt::Debug};
 
@@ -22,9 +22,32 @@ pub enum NodeType<A> {
     WhenAll,
     WhenAny,
     After,
+    SharedCooldown(String, f64),
+    TimeBudgetSequence(f64),
+    CooldownSelect(f64),
+    Named(String, Box<NodeType<A>>),
+    RestartOnFailure,
+    WithInit,
+    Tagged(String),
+    DoWhile,
+    ImpatientSelect(f64),
+    RoundRobinParallel(usize),
+    EarlySuccessParallel(usize),
+    IntervalGuard(f64),
+    OrderedParallel,
+    Subtree(String),
+    Finally,
+    SelectReactive,
+    RoleParallel(Vec<Role>),
+    WithTimeout(f64),
+    AdaptiveSelect(String, f64),
+    ResourceParallel(String, Vec<f64>),
+    BoundedWhile(usize),
+    Seeded(u64),
+    TieBreakSelect(TieBreak),
 }
 
-impl<A: Clone + Debug, K: Debug> BT<A, K> {
+impl<A: Clone + Debug, K: Debug, O> BT<A, K, O> {
     pub fn dfs_recursive(
         graph: &mut Graph<NodeType<A>, u32, petgraph::Directed>,
         behavior: Behavior<A>,
@@ -35,6 +58,10 @@ impl<A: Clone + Debug, K: Debug> BT<A, K> {
                 let node_id = graph.add_node(NodeType::Action(action));
                 graph.add_edge(parent_node, node_id, 1);
             }
+            Behavior::Subtree(key) => {
+                let node_id = graph.add_node(NodeType::Subtree(key));
+                graph.add_edge(parent_node, node_id, 1);
+            }
             Behavior::Invert(ev) => {
                 let node_id = graph.add_node(NodeType::Invert);
                 graph.add_edge(parent_node, node_id, 1);
@@ -112,6 +139,20 @@ impl<A: Clone + Debug, K: Debug> BT<A, K> {
                     }
                 }
             }
+            Behavior::BoundedWhile(max_iterations, ev, seq) => {
+                let node_id = graph.add_node(NodeType::BoundedWhile(max_iterations));
+                graph.add_edge(parent_node, node_id, 1);
+
+                Self::dfs_recursive(graph, *ev, node_id);
+
+                if seq.len() > 1 {
+                    Self::dfs_recursive(graph, Sequence(seq), node_id)
+                } else {
+                    for b in seq {
+                        Self::dfs_recursive(graph, b, node_id)
+                    }
+                }
+            }
             Behavior::RepeatSequence(ev, seq) => {
                 let node_id = graph.add_node(NodeType::RepeatSequence);
                 graph.add_edge(parent_node, node_id, 1);
@@ -145,7 +186,181 @@ impl<A: Clone + Debug, K: Debug> BT<A, K> {
                     Self::dfs_recursive(graph, b, node_id)
                 }
             }
+            Behavior::SharedCooldown(key, duration, ev) => {
+                let node_id = graph.add_node(NodeType::SharedCooldown(key, duration));
+                graph.add_edge(parent_node, node_id, 1);
+                Self::dfs_recursive(graph, *ev, node_id)
+            }
+            Behavior::TimeBudgetSequence(budget, seq) => {
+                let node_id = graph.add_node(NodeType::TimeBudgetSequence(budget));
+                graph.add_edge(parent_node, node_id, 1);
+                for b in seq {
+                    Self::dfs_recursive(graph, b, node_id)
+                }
+            }
+            Behavior::CooldownSelect(cooldown, sel) => {
+                let node_id = graph.add_node(NodeType::CooldownSelect(cooldown));
+                graph.add_edge(parent_node, node_id, 1);
+                for b in sel {
+                    Self::dfs_recursive(graph, b, node_id)
+                }
+            }
+            Behavior::Named(name, ev) => {
+                // The named behavior is the next node to be added, relabel it
+                // once it (and its children) are in the graph.
+                let node_id = NodeIndex::new(graph.node_count());
+                Self::dfs_recursive(graph, *ev, parent_node);
+                if let Some(node) = graph.node_weight_mut(node_id) {
+                    let kind = std::mem::replace(node, NodeType::Root);
+                    *node = NodeType::Named(name, Box::new(kind));
+                }
+            }
+            Behavior::RestartOnFailure(ev) => {
+                let node_id = graph.add_node(NodeType::RestartOnFailure);
+                graph.add_edge(parent_node, node_id, 1);
+                Self::dfs_recursive(graph, *ev, node_id)
+            }
+            Behavior::WithInit(init, body) => {
+                let node_id = graph.add_node(NodeType::WithInit);
+                graph.add_edge(parent_node, node_id, 1);
+                Self::dfs_recursive(graph, *init, node_id);
+                Self::dfs_recursive(graph, *body, node_id)
+            }
+            Behavior::Tagged(tag, ev) => {
+                let node_id = graph.add_node(NodeType::Tagged(tag));
+                graph.add_edge(parent_node, node_id, 1);
+                Self::dfs_recursive(graph, *ev, node_id)
+            }
+            Behavior::DoWhile(body, condition) => {
+                let node_id = graph.add_node(NodeType::DoWhile);
+                graph.add_edge(parent_node, node_id, 1);
+                Self::dfs_recursive(graph, *body, node_id);
+                Self::dfs_recursive(graph, *condition, node_id)
+            }
+            Behavior::ImpatientSelect(timeout, sel) => {
+                let node_id = graph.add_node(NodeType::ImpatientSelect(timeout));
+                graph.add_edge(parent_node, node_id, 1);
+                for b in sel {
+                    Self::dfs_recursive(graph, b, node_id)
+                }
+            }
+            Behavior::RoundRobinParallel(per_tick, all) => {
+                let node_id = graph.add_node(NodeType::RoundRobinParallel(per_tick));
+                graph.add_edge(parent_node, node_id, 1);
+                for b in all {
+                    Self::dfs_recursive(graph, b, node_id)
+                }
+            }
+            Behavior::EarlySuccessParallel(threshold, all) => {
+                let node_id = graph.add_node(NodeType::EarlySuccessParallel(threshold));
+                graph.add_edge(parent_node, node_id, 1);
+                for b in all {
+                    Self::dfs_recursive(graph, b, node_id)
+                }
+            }
+            Behavior::IntervalGuard(interval, condition, ev) => {
+                let node_id = graph.add_node(NodeType::IntervalGuard(interval));
+                graph.add_edge(parent_node, node_id, 1);
+                Self::dfs_recursive(graph, *condition, node_id);
+                Self::dfs_recursive(graph, *ev, node_id)
+            }
+            Behavior::AdaptiveSelect(key, learning_rate, sel) => {
+                let node_id = graph.add_node(NodeType::AdaptiveSelect(key, learning_rate));
+                graph.add_edge(parent_node, node_id, 1);
+                for b in sel {
+                    Self::dfs_recursive(graph, b, node_id)
+                }
+            }
+            Behavior::WithTimeout(timeout, ev, on_timeout) => {
+                let node_id = graph.add_node(NodeType::WithTimeout(timeout));
+                graph.add_edge(parent_node, node_id, 1);
+                Self::dfs_recursive(graph, *ev, node_id);
+                Self::dfs_recursive(graph, *on_timeout, node_id)
+            }
+            Behavior::OrderedParallel(all) => {
+                let node_id = graph.add_node(NodeType::OrderedParallel);
+                graph.add_edge(parent_node, node_id, 1);
+                for b in all {
+                    Self::dfs_recursive(graph, b, node_id)
+                }
+            }
+            Behavior::Finally(body, finalizer) => {
+                let node_id = graph.add_node(NodeType::Finally);
+                graph.add_edge(parent_node, node_id, 1);
+                Self::dfs_recursive(graph, *body, node_id);
+                Self::dfs_recursive(graph, *finalizer, node_id)
+            }
+            Behavior::SelectReactive(sel) => {
+                let node_id = graph.add_node(NodeType::SelectReactive);
+                graph.add_edge(parent_node, node_id, 1);
+                for b in sel {
+                    Self::dfs_recursive(graph, b, node_id)
+                }
+            }
+            Behavior::RoleParallel(all) => {
+                let (roles, all): (Vec<_>, Vec<_>) = all.into_iter().unzip();
+                let node_id = graph.add_node(NodeType::RoleParallel(roles));
+                graph.add_edge(parent_node, node_id, 1);
+                for b in all {
+                    Self::dfs_recursive(graph, b, node_id)
+                }
+            }
+            Behavior::ResourceParallel(pool, all) => {
+                let (costs, all): (Vec<_>, Vec<_>) = all.into_iter().unzip();
+                let node_id = graph.add_node(NodeType::ResourceParallel(pool, costs));
+                graph.add_edge(parent_node, node_id, 1);
+                for b in all {
+                    Self::dfs_recursive(graph, b, node_id)
+                }
+            }
+            Behavior::Seeded(salt, ev) => {
+                let node_id = graph.add_node(NodeType::Seeded(salt));
+                graph.add_edge(parent_node, node_id, 1);
+                Self::dfs_recursive(graph, *ev, node_id)
+            }
+            Behavior::TieBreakSelect(tie_break, sel) => {
+                let node_id = graph.add_node(NodeType::TieBreakSelect(tie_break));
+                graph.add_edge(parent_node, node_id, 1);
+                for b in sel {
+                    Self::dfs_recursive(graph, b, node_id)
+                }
+            }
+        }
+    }
+}
+
+// The node path of each node of the graph built by `BT::dfs_recursive`, by
+// node index, or `None` for the nodes which are not ticked on their own: the
+// root, and the sequence drawn for the body of a `While` or `RepeatSequence`.
+// A `Tagged` node does not count as a level in node paths, so it has the
+// path of its behavior.
+pub(crate) fn graph_paths<A>(behavior: &Behavior<A>) -> Vec<Option<Vec<usize>>> {
+    let mut paths = vec![None];
+    collect_graph_paths(behavior, &mut vec![], &mut paths);
+    paths
+}
+
+fn collect_graph_paths<A>(behavior: &Behavior<A>, path: &mut Vec<usize>, paths: &mut Vec<Option<Vec<usize>>>) {
+    let body = match behavior {
+        // the name relabels the node of the behavior instead of adding one
+        Behavior::Named(_, ev) => return collect_graph_paths(ev, path, paths),
+        Behavior::Tagged(_, ev) => {
+            paths.push(Some(path.clone()));
+            return collect_graph_paths(ev, path, paths);
         }
+        Behavior::While(_, seq) | Behavior::BoundedWhile(_, _, seq) => seq.len() > 1,
+        Behavior::RepeatSequence(_, _) => true,
+        _ => false,
+    };
+    paths.push(Some(path.clone()));
+    for (i, child) in behavior.children().into_iter().enumerate() {
+        // the body after the condition is drawn under a sequence of its own
+        if body && i == 1 {
+            paths.push(None);
+        }
+        path.push(i);
+        collect_graph_paths(child, path, paths);
+        path.pop();
     }
 }
 
@@ -440,6 +655,28 @@ mod tests {
         assert_eq!(g.node_count(), 9);
     }
 
+    #[test]
+    fn test_viz_named() {
+        let behavior = Sequence(vec![
+            Action(Inc).named("increment"),
+            Sequence(vec![Action(Dec), Action(Dec)]).named("decrement twice"),
+        ]);
+
+        let h: HashMap<String, i32> = HashMap::new();
+        let mut bt = BT::new(behavior, h);
+        let (_, g) = bt.get_graphviz_with_graph_instance();
+
+        println!("{:?}", Dot::with_config(&g, &[Config::EdgeNoLabel]));
+
+        // naming does not add any nodes
+        assert_eq!(g.edge_count(), 5);
+        assert_eq!(g.node_count(), 6);
+        assert!(matches!(&g[NodeIndex::new(2)], NodeType::Named(name, kind)
+            if name == "increment" && matches!(**kind, NodeType::Action(Inc))));
+        assert!(matches!(&g[NodeIndex::new(3)], NodeType::Named(name, kind)
+            if name == "decrement twice" && matches!(**kind, NodeType::Sequence)));
+    }
+
     #[test]
     fn test_viz_after_action_wait() {
         let behavior = After(vec![
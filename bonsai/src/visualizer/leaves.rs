@@ -0,0 +1,325 @@
+use crate::{Behavior, NodeType, Role};
+use petgraph::{graph::Graph, stable_graph::NodeIndex};
+
+// How control flows through a behavior, in terms of the actions it contains.
+struct Flow {
+    // Actions the behavior can start with.
+    first: Vec<NodeIndex>,
+    // Actions the behavior can succeed with.
+    last: Vec<NodeIndex>,
+    // Whether the behavior can succeed without running any action.
+    empty: bool,
+}
+
+impl Flow {
+    // A behavior that succeeds without running any action, e.g. `Period`.
+    fn empty() -> Flow {
+        Flow {
+            first: vec![],
+            last: vec![],
+            empty: true,
+        }
+    }
+
+    // A behavior that never succeeds, e.g. `Forever`.
+    fn never() -> Flow {
+        Flow {
+            first: vec![],
+            last: vec![],
+            empty: false,
+        }
+    }
+}
+
+fn union(mut a: Vec<NodeIndex>, b: Vec<NodeIndex>) -> Vec<NodeIndex> {
+    for n in b {
+        if !a.contains(&n) {
+            a.push(n);
+        }
+    }
+    a
+}
+
+// Builds a graph of only the actions of the behavior, see `BT::get_leaves_graph_instance`.
+pub(crate) fn leaves_graph<A>(behavior: Behavior<A>) -> Graph<NodeType<A>, u32> {
+    let mut graph = Graph::<NodeType<A>, u32, petgraph::Directed>::new();
+    let root_id = graph.add_node(NodeType::Root);
+
+    let flow = leaves_recursive(&mut graph, behavior);
+    connect(&mut graph, &[root_id], &flow.first);
+
+    graph
+}
+
+fn leaves_recursive<A>(graph: &mut Graph<NodeType<A>, u32>, behavior: Behavior<A>) -> Flow {
+    match behavior {
+        Behavior::Action(action) => {
+            let node_id = graph.add_node(NodeType::Action(action));
+            Flow {
+                first: vec![node_id],
+                last: vec![node_id],
+                empty: false,
+            }
+        }
+        // An unresolved subtree stands in for the actions it will contain.
+        Behavior::Subtree(key) => {
+            let node_id = graph.add_node(NodeType::Subtree(key));
+            Flow {
+                first: vec![node_id],
+                last: vec![node_id],
+                empty: false,
+            }
+        }
+        Behavior::Named(name, ev) => match *ev {
+            // Keep the name of named actions, other names have no node to go on.
+            Behavior::Action(action) => {
+                let node_id = graph.add_node(NodeType::Named(name, Box::new(NodeType::Action(action))));
+                Flow {
+                    first: vec![node_id],
+                    last: vec![node_id],
+                    empty: false,
+                }
+            }
+            ev => leaves_recursive(graph, ev),
+        },
+        Behavior::Period(_) => Flow::empty(),
+        Behavior::Forever => Flow::never(),
+        Behavior::Invert(ev)
+        | Behavior::AlwaysSucceed(ev)
+        | Behavior::SharedCooldown(_, _, ev)
+        | Behavior::Tagged(_, ev)
+        | Behavior::Seeded(_, ev) => leaves_recursive(graph, *ev),
+        Behavior::RestartOnFailure(ev) => {
+            let flow = leaves_recursive(graph, *ev);
+            connect(graph, &flow.last, &flow.first);
+            flow
+        }
+        Behavior::Select(sel)
+        | Behavior::CooldownSelect(_, sel)
+        | Behavior::ImpatientSelect(_, sel)
+        | Behavior::SelectReactive(sel)
+        | Behavior::AdaptiveSelect(_, _, sel)
+        | Behavior::TieBreakSelect(_, sel) => sel
+            .into_iter()
+            .map(|b| leaves_recursive(graph, b))
+            .fold(Flow::never(), either),
+        Behavior::Sequence(seq) | Behavior::TimeBudgetSequence(_, seq) => sequence(graph, seq),
+        Behavior::If(condition, success, failure) => {
+            let condition = leaves_recursive(graph, *condition);
+            let success = leaves_recursive(graph, *success);
+            let failure = leaves_recursive(graph, *failure);
+            then(graph, condition, either(success, failure))
+        }
+        Behavior::IfThen(condition, success) | Behavior::IntervalGuard(_, condition, success) => {
+            let condition = leaves_recursive(graph, *condition);
+            let success = leaves_recursive(graph, *success);
+            then(graph, condition, success)
+        }
+        Behavior::While(condition, body) | Behavior::BoundedWhile(_, condition, body) => {
+            // The condition runs alongside the looping body.
+            let condition = leaves_recursive(graph, *condition);
+            let body = sequence(graph, body);
+            connect(graph, &body.last, &body.first);
+            both(condition, body)
+        }
+        Behavior::RepeatSequence(condition, body) => {
+            // The condition is checked before each round of the body.
+            let condition = leaves_recursive(graph, *condition);
+            let body = sequence(graph, body);
+            connect(graph, &body.last, &condition.first);
+            if condition.empty {
+                connect(graph, &body.last, &body.first);
+            }
+            // Succeeds when the condition does, after the last round of the body.
+            let (last, empty) = if condition.empty {
+                (union(condition.last.clone(), body.last.clone()), true)
+            } else {
+                (condition.last.clone(), false)
+            };
+            let flow = then(graph, condition, body);
+            Flow {
+                first: flow.first,
+                last,
+                empty,
+            }
+        }
+        Behavior::WhenAll(all)
+        | Behavior::After(all)
+        | Behavior::RoundRobinParallel(_, all)
+        | Behavior::OrderedParallel(all) => all
+            .into_iter()
+            .map(|b| leaves_recursive(graph, b))
+            .fold(Flow::empty(), both),
+        Behavior::ResourceParallel(_, all) => all
+            .into_iter()
+            .map(|(_, b)| leaves_recursive(graph, b))
+            .fold(Flow::empty(), both),
+        Behavior::RoleParallel(all) => all.into_iter().fold(Flow::empty(), |flow, (role, b)| {
+            let child = leaves_recursive(graph, b);
+            match role {
+                Role::Required => both(flow, child),
+                // Starts along with the others, but the node does not wait for it.
+                Role::Optional => Flow {
+                    first: union(flow.first, child.first),
+                    ..flow
+                },
+            }
+        }),
+        Behavior::WhenAny(any) | Behavior::EarlySuccessParallel(_, any) => any
+            .into_iter()
+            .map(|b| leaves_recursive(graph, b))
+            .fold(Flow::never(), either),
+        Behavior::DoWhile(body, condition) => {
+            // The body runs again after the condition succeeds.
+            let body = leaves_recursive(graph, *body);
+            let condition = leaves_recursive(graph, *condition);
+            connect(graph, &condition.last, &body.first);
+            then(graph, body, condition)
+        }
+        Behavior::Finally(body, finalizer) => {
+            let body = leaves_recursive(graph, *body);
+            let finalizer = leaves_recursive(graph, *finalizer);
+            then(graph, body, finalizer)
+        }
+        Behavior::WithTimeout(_, ev, on_timeout) => {
+            let ev = leaves_recursive(graph, *ev);
+            let on_timeout = leaves_recursive(graph, *on_timeout);
+            // Gives up on the behavior while it runs.
+            connect(graph, &ev.first, &on_timeout.first);
+            Flow {
+                first: ev.first,
+                last: union(ev.last, on_timeout.last),
+                empty: ev.empty || on_timeout.empty,
+            }
+        }
+        Behavior::WithInit(init, body) => {
+            let init = leaves_recursive(graph, *init);
+            let body = leaves_recursive(graph, *body);
+            connect(graph, &body.last, &body.first);
+            let flow = then(graph, init, body);
+            // The body starts over forever.
+            Flow {
+                first: flow.first,
+                last: vec![],
+                empty: false,
+            }
+        }
+    }
+}
+
+fn sequence<A>(graph: &mut Graph<NodeType<A>, u32>, seq: Vec<Behavior<A>>) -> Flow {
+    seq.into_iter().fold(Flow::empty(), |flow, b| {
+        let next = leaves_recursive(graph, b);
+        then(graph, flow, next)
+    })
+}
+
+// `a` followed by `b`.
+fn then<A>(graph: &mut Graph<NodeType<A>, u32>, a: Flow, b: Flow) -> Flow {
+    connect(graph, &a.last, &b.first);
+    Flow {
+        first: if a.empty { union(a.first, b.first) } else { a.first },
+        last: if b.empty { union(b.last, a.last) } else { b.last },
+        empty: a.empty && b.empty,
+    }
+}
+
+// Either `a` or `b`.
+fn either(a: Flow, b: Flow) -> Flow {
+    Flow {
+        first: union(a.first, b.first),
+        last: union(a.last, b.last),
+        empty: a.empty || b.empty,
+    }
+}
+
+// Both `a` and `b`, at the same time.
+fn both(a: Flow, b: Flow) -> Flow {
+    Flow {
+        first: union(a.first, b.first),
+        last: union(a.last, b.last),
+        empty: a.empty && b.empty,
+    }
+}
+
+fn connect<A>(graph: &mut Graph<NodeType<A>, u32>, from: &[NodeIndex], to: &[NodeIndex]) {
+    for &a in from {
+        for &b in to {
+            if graph.find_edge(a, b).is_none() {
+                graph.add_edge(a, b, 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Action, Forever, If, NodeType, Period, Select, Sequence, While, BT};
+    use petgraph::Graph;
+
+    // Edges between the labels of the nodes, sorted.
+    fn edges(graph: &Graph<NodeType<&'static str>, u32>) -> Vec<(String, String)> {
+        let label = |node: &NodeType<&'static str>| match node {
+            NodeType::Action(a) => a.to_string(),
+            node => format!("{:?}", node),
+        };
+        let mut edges: Vec<_> = graph
+            .edge_indices()
+            .map(|e| {
+                let (a, b) = graph.edge_endpoints(e).unwrap();
+                (label(&graph[a]), label(&graph[b]))
+            })
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    fn edge(a: &str, b: &str) -> (String, String) {
+        (a.to_string(), b.to_string())
+    }
+
+    #[test]
+    fn test_leaves_sequence_and_select() {
+        let behavior = Sequence(vec![
+            Action("aim"),
+            Period(1.0),
+            Select(vec![Action("shoot"), Action("throw")]),
+            Action("reload"),
+        ]);
+        let bt = BT::new(behavior, ());
+        let graph = bt.get_leaves_graph_instance();
+
+        assert_eq!(graph.node_count(), 5);
+        assert_eq!(
+            edges(&graph),
+            vec![
+                edge("Root", "aim"),
+                edge("aim", "shoot"),
+                edge("aim", "throw"),
+                edge("shoot", "reload"),
+                edge("throw", "reload"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_leaves_if_and_loop() {
+        let behavior = While(
+            Box::new(Forever),
+            vec![If(Box::new(Action("see")), Box::new(Action("chase")), Box::new(Action("patrol")))],
+        );
+        let bt = BT::new(behavior, ());
+        let graph = bt.get_leaves_graph_instance();
+
+        assert_eq!(
+            edges(&graph),
+            vec![
+                edge("Root", "see"),
+                edge("chase", "see"),
+                edge("patrol", "see"),
+                edge("see", "chase"),
+                edge("see", "patrol"),
+            ]
+        );
+    }
+}
@@ -1,4 +1,6 @@
 #![allow(dead_code, unused_imports, unused_variables)]
+use std::collections::HashMap;
+use std::fmt::Debug;
 use std::fmt::Display;
 use std::fmt::Write;
 use std::fmt::{self};
@@ -10,6 +12,14 @@ use petgraph::visit::IntoNodeReferences;
 use petgraph::visit::NodeIndexable;
 use petgraph::visit::NodeRef;
 
+use crate::bt::BlackBoard;
+#[cfg(feature = "node-stats")]
+use crate::stats::NodeStats;
+#[cfg(feature = "node-stats")]
+use crate::visualizer::graph_paths;
+#[cfg(feature = "node-stats")]
+use crate::Behavior;
+
 pub struct Mermaid<'a, G>
     where
         G: IntoEdgeReferences + IntoNodeReferences,
@@ -18,10 +28,24 @@ pub struct Mermaid<'a, G>
     get_edge_attributes: &'a dyn Fn(G, G::EdgeRef) -> String,
     get_node_attributes: &'a dyn Fn(G, G::NodeRef) -> String,
     config: Configs,
+    // The escaped key/value pairs of the blackboard, if it is shown.
+    blackboard: Option<Vec<(String, String)>>,
+    // The activation count of each node by node index, if the nodes are
+    // colored by it.
+    heat: Option<Vec<Option<u64>>>,
 }
 
 static EDGE: [&str; 2] = ["---", "-->"];
 static INDENT: &str = "    ";
+// The classes of the heatmap, from never activated to most activated.
+static HEAT_CLASSES: [(&str, &str); 6] = [
+    ("cold", "fill:#eeeeee,stroke:#999999,stroke-dasharray:4"),
+    ("heat1", "fill:#fff5cc"),
+    ("heat2", "fill:#ffe08a"),
+    ("heat3", "fill:#ffb347"),
+    ("heat4", "fill:#ff7043"),
+    ("heat5", "fill:#e53935,color:#ffffff"),
+];
 
 impl<'a, G> Mermaid<'a, G>
     where
@@ -52,8 +76,97 @@ impl<'a, G> Mermaid<'a, G>
             get_edge_attributes,
             get_node_attributes,
             config,
+            blackboard: None,
+            heat: None,
+        }
+    }
+
+    /// Show the entries of a blackboard next to the tree, in a node of their
+    /// own, so that a single diagram has both the structure of the tree and
+    /// the data it runs on.
+    ///
+    /// The entries are sorted by key, and the values are formatted with `Debug`.
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use bonsai_bt::{Action, Mermaid, NodeType, BT};
+    ///
+    /// let mut db = HashMap::new();
+    /// db.insert("ammo".to_string(), 3);
+    /// let bt = BT::new(Action("shoot"), db);
+    ///
+    /// let graph = bt.get_graph_instance(NodeType::Root);
+    /// let mermaid = format!("{:?}", Mermaid::new(&graph).with_blackboard(bt.get_blackboard()));
+    /// assert!(mermaid.contains("ammo = 3"));
+    /// ```
+    pub fn with_blackboard<K, V>(mut self, blackboard: &BlackBoard<HashMap<K, V>>) -> Self
+        where
+            K: Display,
+            V: Debug,
+    {
+        let mut entries: Vec<_> = blackboard
+            .get_db()
+            .iter()
+            .map(|(key, value)| (escape_label(&key.to_string()), escape_label(&format!("{:?}", value))))
+            .collect();
+        entries.sort();
+        self.blackboard = Some(entries);
+        self
+    }
+
+    /// Color the nodes by how often they have been activated, as a heatmap,
+    /// e.g. to spot the branches of a tree that never run.
+    ///
+    /// `stats` are the stats of the nodes of `behavior`, see `BT::node_stats`,
+    /// and the graph must be the one built from `behavior`, see
+    /// `BT::get_graph_instance`, so that each node is matched with its stats
+    /// by path. Nodes that have never been activated are grey with a dashed
+    /// border, and the others are shaded from yellow to red relative to the
+    /// most activated node. Nodes which are not ticked on their own, such as
+    /// the root, are left as they are.
+    ///
+    /// See `BT::to_mermaid_heatmap` to color a tree by its own node stats.
+    ///
+    /// Only available with the `node-stats` feature.
+    #[cfg(feature = "node-stats")]
+    pub fn with_heatmap<A>(mut self, behavior: &Behavior<A>, stats: &[NodeStats]) -> Self {
+        let activations: HashMap<&[usize], u64> =
+            stats.iter().map(|stats| (stats.path.as_slice(), stats.activations)).collect();
+        let heat = graph_paths(behavior)
+            .into_iter()
+            .map(|path| path.map(|path| activations.get(path.as_slice()).copied().unwrap_or(0)))
+            .collect();
+        self.heat = Some(heat);
+        self
+    }
+}
+
+// The index in `HEAT_CLASSES` of a node activated `count` times, out of
+// `max` times for the most activated node.
+fn heat_class(count: u64, max: u64) -> usize {
+    if count == 0 {
+        return 0;
+    }
+    let level = (count as f64 / max as f64 * 5.0).ceil();
+    level.clamp(1.0, 5.0) as usize
+}
+
+// Escapes text for a quoted mermaid label, where quotes and markup must be
+// written as entity codes.
+fn escape_label(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '#' => escaped.push_str("#35;"),
+            '"' => escaped.push_str("#quot;"),
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            '&' => escaped.push_str("#amp;"),
+            '\n' => escaped.push_str("<br/>"),
+            c => escaped.push(c),
         }
     }
+    escaped
 }
 
 /// `Mermaid` configuration.
@@ -136,6 +249,40 @@ impl<'a, G> Mermaid<'a, G>
             )?;
             writeln!(f, "{}", (self.get_edge_attributes)(g, edge))?;
         }
+        // output the heatmap, as a class per level of activation
+        if let Some(heat) = &self.heat {
+            let max = heat.iter().flatten().copied().max().unwrap_or(0);
+            let mut classes = vec![vec![]; HEAT_CLASSES.len()];
+            for (index, count) in heat.iter().enumerate().take(g.node_bound()) {
+                if let Some(count) = count {
+                    classes[heat_class(*count, max)].push(index.to_string());
+                }
+            }
+            for (name, style) in HEAT_CLASSES.iter() {
+                writeln!(f, "{}classDef {} {}", INDENT, name, style)?;
+            }
+            for ((name, _), nodes) in HEAT_CLASSES.iter().zip(classes) {
+                if !nodes.is_empty() {
+                    writeln!(f, "{}class {} {}", INDENT, nodes.join(","), name)?;
+                }
+            }
+        }
+        // output the blackboard, unconnected to the tree
+        if let Some(entries) = &self.blackboard {
+            writeln!(f, "{}subgraph blackboard [Blackboard]", INDENT)?;
+            write!(f, "{}{}blackboard_entries[\"", INDENT, INDENT)?;
+            if entries.is_empty() {
+                write!(f, "empty")?;
+            }
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    write!(f, "<br/>")?;
+                }
+                write!(f, "{} = {}", key, value)?;
+            }
+            writeln!(f, "\"]")?;
+            writeln!(f, "{}end", INDENT)?;
+        }
 
         Ok(())
     }
@@ -224,9 +371,12 @@ mod test {
     use petgraph::prelude::Graph;
     use petgraph::visit::NodeRef;
 
+    use std::collections::HashMap;
+
     use super::Config;
     use super::Escaper;
     use super::Mermaid;
+    use crate::bt::BlackBoard;
 
     #[test]
     fn test_escape() {
@@ -245,4 +395,44 @@ mod test {
         graph.add_edge(a, b, "edge_label");
         graph
     }
+
+    #[test]
+    fn test_blackboard() {
+        let mut db = HashMap::new();
+        db.insert("target", "\"orc\" <3>");
+        db.insert("ammo", "3");
+        let blackboard = BlackBoard::new(db);
+        let graph = simple_graph();
+
+        let mermaid = format!("{}", Mermaid::new(&graph).with_blackboard(&blackboard));
+        assert!(mermaid.ends_with(concat!(
+            "    subgraph blackboard [Blackboard]\n",
+            "        blackboard_entries[\"ammo = #quot;3#quot;<br/>",
+            "target = #quot;\\#quot;orc\\#quot; #lt;3#gt;#quot;\"]\n",
+            "    end\n",
+        )));
+    }
+
+    #[cfg(feature = "node-stats")]
+    #[test]
+    fn test_heatmap() {
+        use crate::{Action, NodeStats, NodeType, Select, BT};
+
+        let behavior = Select(vec![Action("A"), Action("B")]);
+        let graph = BT::new(behavior.clone(), ()).get_graph_instance(NodeType::Root);
+        let stats = |path: Vec<usize>, activations| NodeStats {
+            path,
+            activations,
+            successes: activations,
+            failures: 0,
+        };
+
+        // `B` has no stats, as if it never ran
+        let stats = [stats(vec![], 10), stats(vec![0], 10)];
+        let mermaid = format!("{:?}", Mermaid::new(&graph).with_heatmap(&behavior, &stats));
+        assert!(mermaid.contains("    classDef cold fill:#eeeeee,stroke:#999999,stroke-dasharray:4\n"));
+        assert!(mermaid.contains("    class 3 cold\n"));
+        assert!(mermaid.contains("    class 1,2 heat5\n"));
+        assert!(!mermaid.contains("class 0"));
+    }
 }
@@ -1,7 +1,10 @@
 #![allow(dead_code, unused_imports, unused_variables)]
 
 mod graphviz;
+mod leaves;
 mod mermaid;
 
 pub use graphviz::*;
+pub(crate) use graphviz::graph_paths;
+pub(crate) use leaves::leaves_graph;
 pub use mermaid::*;
\ No newline at end of file
@@ -1,5 +1,6 @@
+use crate::shared::SharedState;
 use crate::status::Status::*;
-use crate::{event::UpdateEvent, ActionArgs, State, Status, RUNNING};
+use crate::{event::UpdateEvent, ActionArgs, ActionData, State, Status, RUNNING};
 use std::fmt::Debug;
 
 // `WhenAll` and `WhenAny` share same algorithm.
@@ -12,13 +13,14 @@ pub fn when_all<A, E, F, B>(
     upd: Option<f64>,
     cursors: &mut [Option<State<A>>],
     e: &E,
+    shared: &mut SharedState<A>,
     f: &mut F,
     blackboard: &mut B,
 ) -> (Status, f64)
 where
     A: Clone,
     E: UpdateEvent,
-    F: FnMut(ActionArgs<E, A>, &mut B) -> (Status, f64),
+    F: FnMut(ActionArgs<E, A>, &mut ActionData, &mut B) -> (Status, f64),
     A: Debug,
 {
     let (status, inv_status) = if any {
@@ -32,11 +34,11 @@ where
     let mut min_dt = f64::MAX;
     // Count number of terminated events.
     let mut terminated = 0;
-    for cur in cursors.iter_mut() {
+    for (j, cur) in cursors.iter_mut().enumerate() {
         match *cur {
             None => {}
             Some(ref mut cur) => {
-                match cur.tick(e, blackboard, f) {
+                match cur.tick_child(j, e, shared, blackboard, f) {
                     (Running, _) => {
                         continue;
                     }
@@ -1,13 +1,18 @@
 #![allow(dead_code, unused_imports, unused_variables)]
 
 use crate::behavior_tests::TestActions::{Dec, Inc, LessThan, LessThanRunningSuccess};
-use bonsai_bt::Behavior::{IfThen, RepeatSequence};
+use bonsai_bt::Behavior::{
+    AdaptiveSelect, BoundedWhile, CooldownSelect, DoWhile, EarlySuccessParallel, Finally, IfThen, ImpatientSelect,
+    IntervalGuard, OrderedParallel, RepeatSequence, ResourceParallel, RestartOnFailure, RoleParallel,
+    RoundRobinParallel, Seeded, SelectReactive, TieBreakSelect, TimeBudgetSequence, WithInit, WithTimeout,
+};
 use bonsai_bt::{
-    Action, ActionArgs,
+    Action, ActionArgs, ActionData,
+    Role::{Optional, Required},
     Behavior::{After, AlwaysSucceed, If, Invert, Select},
-    Event, Failure, Sequence, State,
+    Event, Failure, Sequence, SharedState, Signal, State, TieBreak,
     Status::Running,
-    Success, UpdateArgs, Period, Forever, WhenAll, While,
+    Success, UpdateArgs, Period, Forever, WhenAll, WhenAny, While,
 };
 
 /// Some test actions.
@@ -24,11 +29,22 @@ enum TestActions {
 }
 
 // A test state machine that can increment and decrement.
-fn tick(mut acc: i32, dt: f64, state: &mut State<TestActions>) -> (i32, bonsai_bt::Status, f64) {
+fn tick(acc: i32, dt: f64, state: &mut State<TestActions>) -> (i32, bonsai_bt::Status, f64) {
+    tick_shared(acc, dt, state, &mut SharedState::new())
+}
+
+// Same as `tick`, but with the state shared between the nodes kept across ticks.
+fn tick_shared(
+    mut acc: i32,
+    dt: f64,
+    state: &mut State<TestActions>,
+    shared: &mut SharedState<TestActions>,
+) -> (i32, bonsai_bt::Status, f64) {
     let e: Event = UpdateArgs { dt }.into();
     println!("acc {}", acc);
-    let (s, t) = state.tick(
+    let (s, t) = state.tick_with_shared(
         &e,
+        shared,
         &mut (),
         &mut |args: ActionArgs<Event, TestActions>, _| match *args.action {
             Inc => {
@@ -243,6 +259,33 @@ fn test_if_less_than() {
     assert_eq!(s, Success);
 }
 
+#[test]
+fn test_if_remembers_branch_until_it_ends() {
+    let condition = Sequence(vec![Period(1.0), Action(LessThan(1))]);
+    let success = Sequence(vec![Action(Inc), Period(1.0)]);
+    let behavior = If(Box::new(condition), Box::new(success), Box::new(Action(Dec)));
+    let mut state = State::new(While(Box::new(Forever), vec![behavior]));
+
+    // no branch runs while the condition is running
+    let (a, s, _) = tick(0, 0.5, &mut state);
+    assert_eq!((a, s), (0, Running));
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!((a, s), (1, Running));
+
+    // the condition would fail by now, but is not checked while the branch runs
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!((a, s), (1, Running));
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!((a, s), (1, Running));
+
+    // entered again by the loop, the condition is checked anew, and the
+    // other branch runs before the loop goes on
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!((a, s), (1, Running));
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!((a, s), (0, Running));
+}
+
 #[test]
 fn test_if_then_less_than_true() {
     let a: i32 = 1;
@@ -664,3 +707,938 @@ fn test_repeat_sequence_empty() {
     // panics because no behaviors...
     let _state = State::new(after);
 }
+
+#[test]
+fn test_adaptive_select_learns_across_ticks() {
+    let behavior = AdaptiveSelect("attack".to_string(), 0.5, vec![Action(LessThan(0)), Action(Inc)]);
+    let mut state = State::new(behavior);
+    let mut shared = SharedState::new();
+
+    // the estimates are kept in the shared state over the ticks of the state
+    let (a, s, _) = tick_shared(0, 0.1, &mut state, &mut shared);
+    assert_eq!((a, s), (1, Success));
+    assert_eq!(shared.success_estimates("attack").unwrap()[1], 0.75);
+    let (a, s, _) = tick_shared(a, 0.1, &mut state, &mut shared);
+    assert_eq!((a, s), (2, Success));
+    assert_eq!(shared.success_estimates("attack").unwrap()[1], 0.875);
+}
+
+#[test]
+#[should_panic]
+fn test_adaptive_select_learning_rate_out_of_range() {
+    let _state: State<TestActions> = State::new(AdaptiveSelect("attack".to_string(), 1.5, vec![Action(Inc)]));
+}
+
+#[test]
+fn test_seeded_stream_goes_on_across_ticks() {
+    let e: Event = UpdateArgs { dt: 0.1 }.into();
+    // whether `Inc` is picked first, on each of a few runs of the seeded behavior
+    let picks = |shared: &mut SharedState<TestActions>| -> Vec<bool> {
+        (0..16)
+            .map(|_| {
+                let pick = AdaptiveSelect("pick".to_string(), 0.0, vec![Action(Inc), Action(Dec)]);
+                let mut state = State::new(Seeded(1, Box::new(pick)));
+                let mut picked = None;
+                state.tick_with_shared(&e, shared, &mut (), &mut |args: ActionArgs<Event, TestActions>, _| {
+                    picked.get_or_insert(matches!(args.action, Inc));
+                    (Success, args.dt)
+                });
+                picked.unwrap()
+            })
+            .collect()
+    };
+
+    // the stream goes on from one run to the next, as the shared state is kept
+    let mut shared = SharedState::new();
+    shared.set_seed(42);
+    let first = picks(&mut shared);
+    assert!(first.iter().any(|inc| *inc != first[0]));
+
+    // and the same seed gives the same picks
+    let mut shared = SharedState::new();
+    shared.set_seed(42);
+    assert_eq!(picks(&mut shared), first);
+}
+
+#[test]
+fn test_tie_break_select_goes_on_across_ticks() {
+    let e: Event = UpdateArgs { dt: 0.1 }.into();
+    // the behavior picked on each of a few runs of the node, where every behavior succeeds
+    let picks = |tie_break: &TieBreak, shared: &mut SharedState<TestActions>| -> Vec<usize> {
+        (0..12)
+            .map(|_| {
+                let select = TieBreakSelect(tie_break.clone(), vec![Action(Inc), Action(Dec), Action(LessThan(0))]);
+                let mut state = State::new(select);
+                let mut picked = 0;
+                state.tick_with_shared(&e, shared, &mut (), &mut |args: ActionArgs<Event, TestActions>, _| {
+                    picked = match args.action {
+                        Inc => 0,
+                        Dec => 1,
+                        _ => 2,
+                    };
+                    (Success, args.dt)
+                });
+                picked
+            })
+            .collect()
+    };
+
+    // the rotation goes on from one run to the next, as the shared state is kept
+    let rotated = picks(&TieBreak::RoundRobin("patrol".to_string()), &mut SharedState::new());
+    assert_eq!(rotated, (0..12).map(|k| k % 3).collect::<Vec<_>>());
+
+    // and so does the random stream, which gives the same picks for the same seed
+    let mut shared = SharedState::new();
+    shared.set_seed(7);
+    let shuffled = picks(&TieBreak::Random, &mut shared);
+    assert!(shuffled.iter().any(|j| *j != shuffled[0]));
+    let mut shared = SharedState::new();
+    shared.set_seed(7);
+    assert_eq!(picks(&TieBreak::Random, &mut shared), shuffled);
+}
+
+#[test]
+fn test_resource_parallel_waits_for_pool_across_ticks() {
+    let behavior = ResourceParallel("ap".to_string(), vec![(2.0, Action(Inc)), (2.0, Action(Inc))]);
+    let mut state = State::new(behavior);
+    let mut shared = SharedState::new();
+    shared.set_resource_pool("ap", 2.0);
+
+    // the second behavior waits until the pool is refilled
+    let (a, s, _) = tick_shared(0, 0.1, &mut state, &mut shared);
+    assert_eq!((a, s), (1, Running));
+    let (a, s, _) = tick_shared(a, 0.1, &mut state, &mut shared);
+    assert_eq!((a, s), (1, Running));
+    assert_eq!(shared.resource_pool("ap"), 0.0);
+    shared.set_resource_pool("ap", 2.0);
+    let (a, s, _) = tick_shared(a, 0.1, &mut state, &mut shared);
+    assert_eq!((a, s), (2, Success));
+    assert_eq!(shared.resource_pool("ap"), 0.0);
+}
+
+#[test]
+#[should_panic]
+fn test_resource_parallel_cost_never_met() {
+    let _state: State<TestActions> = State::new(ResourceParallel("ap".to_string(), vec![(f64::INFINITY, Action(Inc))]));
+}
+
+#[test]
+fn test_time_budget_sequence_within_budget() {
+    let a: i32 = 0;
+    let seq = TimeBudgetSequence(2.0, vec![Period(0.5), Action(Inc), Period(0.5), Action(Inc)]);
+    let mut state = State::new(seq);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+    let (a, s, dt) = tick(a, 0.6, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Success);
+    assert!((dt - 0.1).abs() < 1e-9);
+}
+
+#[test]
+fn test_time_budget_sequence_exceeded_mid_child() {
+    let a: i32 = 0;
+    let seq = TimeBudgetSequence(1.0, vec![Period(0.5), Action(Inc), Period(1.0), Action(Inc)]);
+    let mut state = State::new(seq);
+
+    let (a, s, _) = tick(a, 0.6, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+    // the second wait is still running when the budget runs out
+    let (a, s, dt) = tick(a, 0.6, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Failure);
+    assert!((dt - 0.2).abs() < 1e-9);
+}
+
+#[test]
+fn test_time_budget_sequence_resets_on_reentry() {
+    let a: i32 = 0;
+    let w = While(
+        Box::new(Forever),
+        vec![TimeBudgetSequence(1.0, vec![Period(0.8), Action(Inc)])],
+    );
+    let mut state = State::new(w);
+
+    let (a, _, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 0);
+    let (a, _, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    // a fresh budget for the next iteration
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Running);
+}
+
+#[test]
+fn test_cooldown_select_skips_failed_behavior() {
+    let a: i32 = 3;
+    let sel = CooldownSelect(1.0, vec![Action(LessThan(5)), Action(Inc), Action(Dec)]);
+    let mut state = State::new(sel);
+    let mut shared = SharedState::new();
+
+    // LessThan(5) succeeds
+    let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 3);
+    assert_eq!(s, Success);
+    // LessThan(5) succeeds again, since the selection starts over
+    let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 3);
+    assert_eq!(s, Success);
+
+    let (a, s, _) = tick_shared(a + 2, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 6);
+    assert_eq!(s, Success);
+    // LessThan(5) failed and is on cooldown for one second
+    let (a, s, _) = tick_shared(a - 2, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 5);
+    assert_eq!(s, Success);
+    let (a, s, _) = tick_shared(a - 2, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 3);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_cooldown_select_all_on_cooldown() {
+    let a: i32 = 3;
+    let sel = CooldownSelect(1.0, vec![Action(LessThan(1)), Action(LessThan(2))]);
+    let mut state = State::new(sel);
+    let mut shared = SharedState::new();
+
+    let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!(s, Failure);
+    // both behaviors are on cooldown
+    let (a, s, _) = tick_shared(a - 3, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 0);
+    assert_eq!(s, Failure);
+    let (_, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_cooldown_select_in_loop() {
+    // the loop enters the node anew on every tick, while the cooldowns last
+    let sel = CooldownSelect(1.0, vec![Action(LessThan(5)), Action(Inc)]);
+    let mut state = State::new(While(Box::new(Forever), vec![sel]));
+    let mut shared = SharedState::new();
+
+    let (a, s, _) = tick_shared(5, 0.5, &mut state, &mut shared);
+    assert_eq!((a, s), (6, Running));
+    // LessThan(5) is on cooldown, so Inc runs
+    let (a, _, _) = tick_shared(a - 3, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 4);
+    let (a, _, _) = tick_shared(a - 1, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 3);
+}
+
+#[test]
+fn test_restart_on_failure_in_when_all() {
+    let a: i32 = 3;
+    let all = WhenAll(vec![
+        RestartOnFailure(Box::new(Action(LessThan(2)))),
+        Sequence(vec![Period(1.0), Action(Inc)]),
+    ]);
+    let mut state = State::new(all);
+
+    // LessThan(2) fails and is restarted instead of failing the WhenAll
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 3);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a - 2, 0.5, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_when_all_without_restart_fails() {
+    let a: i32 = 3;
+    let all = WhenAll(vec![Action(LessThan(2)), Sequence(vec![Period(1.0), Action(Inc)])]);
+    let mut state = State::new(all);
+
+    let (_, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(s, Failure);
+}
+
+#[test]
+fn test_with_init_runs_init_once() {
+    let a: i32 = 0;
+    let behavior = WithInit(
+        Box::new(Action(Dec)),
+        Box::new(Sequence(vec![Period(1.0), Action(Inc)])),
+    );
+    let mut state = State::new(behavior);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, -1);
+    assert_eq!(s, Running);
+    let (mut a, _, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 0);
+
+    // the body starts over each second, without running init again
+    for _ in 0..10 {
+        let (acc, s, _) = tick(a, 1.0, &mut state);
+        assert_eq!(s, Running);
+        a = acc;
+    }
+    assert_eq!(a, 10);
+}
+
+#[test]
+fn test_with_init_fails_if_init_fails() {
+    let a: i32 = 3;
+    let behavior = WithInit(Box::new(Action(LessThan(2))), Box::new(Action(Inc)));
+    let mut state = State::new(behavior);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 3);
+    assert_eq!(s, Failure);
+}
+
+#[test]
+fn test_do_while_runs_body_once_if_condition_fails() {
+    let a: i32 = 0;
+    let behavior = DoWhile(Box::new(Action(Inc)), Box::new(Action(LessThan(0))));
+    let mut state = State::new(behavior);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_do_while_repeats_while_condition_succeeds() {
+    let a: i32 = 0;
+    let behavior = DoWhile(Box::new(Action(Inc)), Box::new(Action(LessThan(3))));
+    let mut state = State::new(behavior);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 3);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_do_while_fails_if_body_fails() {
+    let a: i32 = 3;
+    let behavior = DoWhile(Box::new(Action(LessThan(2))), Box::new(Action(Inc)));
+    let mut state = State::new(behavior);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 3);
+    assert_eq!(s, Failure);
+}
+
+#[test]
+fn test_impatient_select_falls_through_on_timeout() {
+    let a: i32 = 0;
+    let sel = ImpatientSelect(1.0, vec![Sequence(vec![Period(5.0), Action(Inc)]), Action(Dec)]);
+    let mut state = State::new(sel);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 0);
+    assert_eq!(s, Running);
+    // gives up on the first behavior, and passes the rest of the time on
+    let (a, s, dt) = tick(a, 0.75, &mut state);
+    assert_eq!(a, -1);
+    assert_eq!(s, Success);
+    assert_eq!(dt, 0.25);
+}
+
+#[test]
+fn test_impatient_select_restarts_timer_for_next_behavior() {
+    let a: i32 = 0;
+    let sel = ImpatientSelect(
+        1.0,
+        vec![
+            Sequence(vec![Period(5.0), Action(Inc)]),
+            Sequence(vec![Period(0.75), Action(Inc)]),
+        ],
+    );
+    let mut state = State::new(sel);
+
+    let (a, s, _) = tick(a, 1.5, &mut state);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_impatient_select_fails_when_last_times_out() {
+    let sel: bonsai_bt::Behavior<TestActions> = ImpatientSelect(1.0, vec![Period(5.0)]);
+    let mut state = State::new(sel);
+
+    let (_, s, dt) = tick(0, 1.5, &mut state);
+    assert_eq!(s, Failure);
+    assert_eq!(dt, 0.5);
+}
+
+#[test]
+fn test_round_robin_parallel_ticks_in_turns() {
+    let a: i32 = 0;
+    let all = RoundRobinParallel(2, vec![Action(Inc), Action(Inc), Action(Inc)]);
+    let mut state = State::new(all);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Running);
+    let (a, s, dt) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 3);
+    assert_eq!(s, Success);
+    assert_eq!(dt, 0.5);
+}
+
+#[test]
+fn test_round_robin_parallel_catches_up_on_time() {
+    let a: i32 = 0;
+    let all = RoundRobinParallel(
+        1,
+        vec![
+            Sequence(vec![Period(1.0), Action(Inc)]),
+            Sequence(vec![Period(1.0), Action(Inc)]),
+        ],
+    );
+    let mut state = State::new(all);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(s, Running);
+    // the second behavior is given the time of both ticks
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+    let (a, s, dt) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Success);
+    assert_eq!(dt, 0.5);
+}
+
+#[test]
+fn test_round_robin_parallel_keeps_time_over_other_events() {
+    let a: i32 = 0;
+    let all = RoundRobinParallel(
+        1,
+        vec![
+            Sequence(vec![Period(1.0), Action(Inc)]),
+            Sequence(vec![Period(1.0), Action(Inc)]),
+        ],
+    );
+    let mut state = State::new(all);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(s, Running);
+    // a signal reaches both behaviors, but moves neither of them in time
+    let signal: Event = Signal::new("ping").into();
+    let (s, _) = state.tick(&signal, &mut (), &mut |args: ActionArgs<Event, TestActions>, _| {
+        (Running, args.dt)
+    });
+    assert_eq!(s, Running);
+    // so the second behavior is still given the time of both updates
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+}
+
+#[test]
+fn test_round_robin_parallel_fails_when_any_fails() {
+    let a: i32 = 0;
+    let all = RoundRobinParallel(1, vec![Forever, Action(LessThan(0))]);
+    let mut state = State::new(all);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(s, Running);
+    let (_, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(s, Failure);
+}
+
+// Counts its ticks in the data of the node, and succeeds on the third one.
+fn tick_counting(dt: f64, state: &mut State<TestActions>) -> (Vec<u32>, bonsai_bt::Status) {
+    let e: Event = UpdateArgs { dt }.into();
+    let mut counts = vec![];
+    let mut shared = SharedState::new();
+    let (s, _) = state.tick_with_data(&e, &mut shared, &mut (), &mut |args: ActionArgs<Event, TestActions>, data, _| {
+        let count = data.get_or_insert_with(|| 0u32);
+        *count += 1;
+        counts.push(*count);
+        if *count == 3 {
+            (Success, args.dt)
+        } else {
+            (Running, 0.0)
+        }
+    });
+    (counts, s)
+}
+
+#[test]
+fn test_action_data_kept_while_running() {
+    let mut state = State::new(Sequence(vec![Action(Inc), Action(Inc)]));
+
+    assert_eq!(tick_counting(0.1, &mut state), (vec![1], Running));
+    assert_eq!(tick_counting(0.1, &mut state), (vec![2], Running));
+    // the second action starts from scratch
+    assert_eq!(tick_counting(0.1, &mut state), (vec![3, 1], Running));
+    assert_eq!(tick_counting(0.1, &mut state), (vec![2], Running));
+    assert_eq!(tick_counting(0.1, &mut state), (vec![3], Success));
+}
+
+#[test]
+fn test_action_state_serialized_as_action() {
+    let state = State::new(Action("shoot".to_string()));
+    let json = serde_json::to_string(&state).unwrap();
+    assert_eq!(json, r#"{"ActionState":"shoot"}"#);
+    assert_eq!(serde_json::from_str::<State<String>>(&json).unwrap(), state);
+}
+
+#[test]
+fn test_state_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<State<TestActions>>();
+}
+
+#[test]
+fn test_action_data_chunked_operation() {
+    let items: Vec<i32> = (1..=10).collect();
+    let mut state = State::new(Action(Inc));
+    let e: Event = UpdateArgs { dt: 0.1 }.into();
+    let mut sum = 0;
+    let mut progress = 0.0;
+    let mut reported = vec![];
+
+    let mut shared = SharedState::new();
+    let mut handler = |args: ActionArgs<Event, TestActions>, data: &mut ActionData, progress: &mut f64| {
+        let chunk = data.next_chunk(items.len(), 3);
+        sum += items[chunk.items.clone()].iter().sum::<i32>();
+        *progress = chunk.progress();
+        chunk.result(args.dt)
+    };
+
+    // 10 items, 3 per tick, take 4 ticks
+    let mut status = Running;
+    while status == Running {
+        status = state.tick_with_data(&e, &mut shared, &mut progress, &mut handler).0;
+        reported.push(progress);
+    }
+    assert_eq!(status, Success);
+    assert_eq!(reported, vec![0.3, 0.6, 0.9, 1.0]);
+    assert_eq!(sum, 55);
+}
+
+#[test]
+fn test_action_data_dropped_on_abort() {
+    // the action is aborted by the period, and runs again in the next round
+    let every_second = While(Box::new(Forever), vec![WhenAny(vec![Action(Inc), Period(1.0)])]);
+    let mut state = State::new(every_second);
+
+    assert_eq!(tick_counting(0.5, &mut state), (vec![1], Running));
+    assert_eq!(tick_counting(0.5, &mut state), (vec![2], Running));
+    assert_eq!(tick_counting(0.5, &mut state), (vec![1], Running));
+}
+
+#[test]
+fn test_early_success_parallel_keeps_others_running() {
+    let a: i32 = 0;
+    let any = EarlySuccessParallel(1, vec![Action(Inc), Sequence(vec![Period(1.0), Action(Inc)])]);
+    let mut state = State::new(any);
+    let mut shared = SharedState::new();
+
+    let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 1);
+    assert_eq!(s, Success);
+    // still succeeding, while the other behavior completes in the background
+    let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 2);
+    assert_eq!(s, Success);
+    let (a, s, dt) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 2);
+    assert_eq!(s, Success);
+    assert_eq!(dt, 0.5);
+}
+
+#[test]
+fn test_early_success_parallel_keeps_others_running_when_nested() {
+    let a: i32 = 0;
+    let any = EarlySuccessParallel(1, vec![Action(Inc), Sequence(vec![Period(1.0), Action(Inc)])]);
+    let mut state = State::new(Sequence(vec![any, Period(5.0)]));
+    let mut shared = SharedState::new();
+
+    let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+    // the sequence has moved on, but the other behavior still completes
+    let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 2);
+    assert_eq!(s, Running);
+    let (a, _, _) = tick_shared(a, 1.0, &mut state, &mut shared);
+    assert_eq!(a, 2);
+}
+
+#[test]
+fn test_early_success_parallel_aborts_background_when_reentered() {
+    let a: i32 = 0;
+    let cleanup = Finally(Box::new(Forever), Box::new(Action(Dec)));
+    let any = EarlySuccessParallel(1, vec![Action(Inc), cleanup]);
+    let mut state = State::new(While(Box::new(Forever), vec![any]));
+    let mut shared = SharedState::new();
+
+    let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!((a, s), (1, Running));
+    // each iteration aborts the behavior left in the background by the last
+    // one, running its finalizer, instead of piling up another copy
+    for _ in 0..3 {
+        let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+        assert_eq!((a, s), (1, Running));
+    }
+}
+
+#[test]
+fn test_early_success_parallel_ignores_later_failures() {
+    let a: i32 = 0;
+    let any = EarlySuccessParallel(1, vec![Action(Inc), Sequence(vec![Period(1.0), Action(LessThan(0))])]);
+    let mut state = State::new(any);
+    let mut shared = SharedState::new();
+
+    let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!(s, Success);
+    let (_, s, _) = tick_shared(a, 1.0, &mut state, &mut shared);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_early_success_parallel_fails_when_threshold_unreachable() {
+    let a: i32 = 0;
+    let any = EarlySuccessParallel(2, vec![Action(LessThan(0)), Forever]);
+    let mut state = State::new(any);
+
+    let (_, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(s, Failure);
+}
+
+#[test]
+fn test_interval_guard_checks_on_interval() {
+    let a: i32 = 0;
+    let count = While(Box::new(Forever), vec![Action(Inc)]);
+    let guard = IntervalGuard(1.0, Box::new(Action(LessThan(2))), Box::new(count));
+    let mut state = State::new(guard);
+
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+    // the condition no longer holds, but is not checked yet
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Failure);
+}
+
+#[test]
+fn test_interval_guard_succeeds_with_behavior() {
+    let a: i32 = 0;
+    let guard = IntervalGuard(
+        1.0,
+        Box::new(Action(LessThan(1))),
+        Box::new(Sequence(vec![Period(2.0), Action(Inc)])),
+    );
+    let mut state = State::new(guard);
+
+    for _ in 0..3 {
+        let (_, s, _) = tick(a, 0.5, &mut state);
+        assert_eq!(s, Running);
+    }
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_ordered_parallel_fails_in_order() {
+    let a: i32 = 0;
+    let behavior = OrderedParallel(vec![
+        Sequence(vec![Period(1.0), Action(Inc)]),
+        Action(LessThan(0)),
+        Sequence(vec![Period(0.5), Action(Inc)]),
+    ]);
+    let mut state = State::new(behavior);
+
+    // the failure is only accepted once the first behavior has succeeded
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Failure);
+}
+
+#[test]
+fn test_finally_keeps_result_of_body() {
+    let a: i32 = 0;
+    let behavior = Finally(
+        Box::new(Action(LessThan(0))),
+        Box::new(Sequence(vec![Period(0.5), Action(Inc)])),
+    );
+    let mut state = State::new(behavior);
+
+    // the body fails, and the finalizer still runs
+    let (a, s, _) = tick(a, 0.25, &mut state);
+    assert_eq!(a, 0);
+    assert_eq!(s, Running);
+    let (a, s, dt) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Failure);
+    assert_eq!(dt, 0.25);
+
+    // a failing finalizer makes the behavior fail
+    let mut state = State::new(Finally(Box::new(Action(Inc)), Box::new(Action(LessThan(0)))));
+    let (a, s, _) = tick(a, 0.0, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Failure);
+}
+
+#[test]
+fn test_finally_finalizer_outlasts_abort() {
+    // the `WhenAny` aborts the `Finally` once the first behavior succeeds
+    let finally = Finally(Box::new(Period(10.0)), Box::new(Sequence(vec![Period(1.0), Action(Dec)])));
+    let any = WhenAny(vec![Sequence(vec![Period(0.5), Action(Inc)]), finally]);
+    let mut state = State::new(Sequence(vec![any, Period(10.0)]));
+    let mut shared = SharedState::new();
+
+    let (a, s, _) = tick_shared(0, 0.25, &mut state, &mut shared);
+    assert_eq!((a, s), (0, Running));
+    let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!((a, s), (1, Running));
+    // the finalizer is kept in the shared state, and goes on over the next ticks
+    let (a, s, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!((a, s), (0, Running));
+}
+
+#[test]
+fn test_select_reactive_preempts_running_behavior() {
+    // the first behavior becomes viable once the second one has started
+    let children = vec![Action(LessThan(0)), Sequence(vec![Action(Dec), Period(10.0)])];
+
+    // a select remembers the running behavior and keeps it
+    let mut state = State::new(Select(children.clone()));
+    let (a, s, _) = tick(0, 1.0, &mut state);
+    assert_eq!(a, -1);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 1.0, &mut state);
+    assert_eq!(a, -1);
+    assert_eq!(s, Running);
+
+    // a reactive select checks the first behavior again, and switches to it
+    let mut state = State::new(SelectReactive(children));
+    let (a, s, _) = tick(0, 1.0, &mut state);
+    assert_eq!(a, -1);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 1.0, &mut state);
+    assert_eq!(a, -1);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_role_parallel_ignores_optional_behaviors() {
+    let a: i32 = 0;
+    let behavior = RoleParallel(vec![
+        (Required, Sequence(vec![Period(1.0), Action(Inc)])),
+        (Optional, Action(LessThan(0))),
+        (Optional, Forever),
+    ]);
+    let mut state = State::new(behavior);
+
+    // the optional failure is ignored, and the optional behavior still running is dropped
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 0);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Success);
+
+    // a required failure fails the node
+    let mut state = State::new(RoleParallel(vec![(Optional, Action(Inc)), (Required, Action(LessThan(0)))]));
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Failure);
+}
+
+#[test]
+fn test_with_timeout_runs_on_timeout_behavior() {
+    let a: i32 = 0;
+    let behavior = WithTimeout(1.0, Box::new(Action(LessThanRunningSuccess(5))), Box::new(Action(Dec)));
+    let mut state = State::new(behavior);
+
+    // the action keeps running until it times out
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 0);
+    assert_eq!(s, Running);
+    let (a, s, dt) = tick(a, 0.75, &mut state);
+    assert_eq!(a, -1);
+    assert_eq!(s, Success);
+    assert_eq!(dt, 0.25);
+
+    // an action terminating in time gives the result
+    let mut state = State::new(WithTimeout(1.0, Box::new(Action(Inc)), Box::new(Action(Dec))));
+    let (a, s, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 0);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_cond_chain_runs_first_matching_branch() {
+    use bonsai_bt::cond_chain;
+
+    let chain = cond_chain()
+        .when(Action(LessThan(0)), Action(Dec))
+        .when(Action(LessThan(2)), Action(Inc));
+    let behavior = chain.clone().otherwise(Action(Dec)).build();
+
+    let mut state = State::new(behavior.clone());
+    let (a, s, _) = tick(0, 0.1, &mut state);
+    assert_eq!(a, 1);
+    assert_eq!(s, Success);
+
+    // none of the conditions succeed
+    let mut state = State::new(behavior);
+    let (a, s, _) = tick(3, 0.1, &mut state);
+    assert_eq!(a, 2);
+    assert_eq!(s, Success);
+
+    // without otherwise, the chain fails
+    let mut state = State::new(chain.build());
+    let (a, s, _) = tick(3, 0.1, &mut state);
+    assert_eq!(a, 3);
+    assert_eq!(s, Failure);
+}
+
+#[test]
+fn test_cond_chain_empty_fails_on_any_event() {
+    use bonsai_bt::cond_chain;
+
+    let mut state = State::new(cond_chain().build());
+    let (_, s, dt) = tick(0, 0.1, &mut state);
+    assert_eq!((s, dt), (Failure, 0.1));
+
+    let mut state = State::new(cond_chain().build());
+    let signal: Event = Signal::new("ping").into();
+    let (s, _) = state.tick(&signal, &mut (), &mut |args: ActionArgs<Event, TestActions>, _| {
+        (Running, args.dt)
+    });
+    assert_eq!(s, Failure);
+}
+
+#[test]
+fn test_bounded_while_caps_iterations_per_tick() {
+    // the condition never terminates and the body is instant
+    let mut state = State::new(BoundedWhile(3, Box::new(Forever), vec![Action(Inc)]));
+    let (a, s, _) = tick(0, 0.1, &mut state);
+    assert_eq!(a, 3);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 0.1, &mut state);
+    assert_eq!(a, 6);
+    assert_eq!(s, Running);
+
+    // the loop ends with the condition
+    let mut state = State::new(BoundedWhile(10, Box::new(Period(1.0)), vec![Action(Inc), Action(Inc)]));
+    let (a, s, _) = tick(0, 0.6, &mut state);
+    assert_eq!(a, 20);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 0.6, &mut state);
+    assert_eq!(a, 20);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_steps_skip_steps_unless_condition_holds() {
+    use bonsai_bt::steps;
+
+    let behavior = steps()
+        .step(Action(Inc))
+        .step_if(Action(LessThan(5)), Action(Inc))
+        .step_if(Action(LessThan(0)), Action(Dec))
+        .step(Action(Inc))
+        .build();
+    let mut state = State::new(behavior);
+
+    // the second step is taken, the third is skipped
+    let (a, s, _) = tick(0, 0.1, &mut state);
+    assert_eq!(a, 3);
+    assert_eq!(s, Success);
+
+    // a step is skipped right away on other events too
+    let mut state = State::new(steps().step_if(Action(LessThan(0)), Action(Dec)).build());
+    let signal: Event = Signal::new("ping").into();
+    let (s, _) = state.tick(&signal, &mut (), &mut |args: ActionArgs<Event, TestActions>, _| {
+        (Failure, args.dt)
+    });
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_composite_dispatcher_nests_dispatchers() {
+    use bonsai_bt::CompositeDispatcher;
+
+    let mut acc = 0;
+    let mut unhandled = 0;
+    let mut inner = CompositeDispatcher::new().register(|args: ActionArgs<Event, TestActions>, _: &mut ()| {
+        match args.action {
+            Inc => {
+                acc += 1;
+                Some((Success, args.dt))
+            }
+            _ => None,
+        }
+    });
+    let mut outer = CompositeDispatcher::new()
+        .register(|args: ActionArgs<Event, TestActions>, blackboard: &mut ()| inner.try_dispatch(args, blackboard))
+        .register(|args, _| match args.action {
+            Dec => Some((Success, args.dt)),
+            _ => None,
+        })
+        .fallback(|args, _| {
+            unhandled += 1;
+            (Success, args.dt)
+        });
+
+    let behavior = Sequence(vec![Action(Inc), Action(Dec), Action(LessThan(0)), Action(Inc)]);
+    let mut state = State::new(behavior);
+    let e: Event = UpdateArgs { dt: 0.1 }.into();
+    let (s, _) = state.tick(&e, &mut (), &mut |args, blackboard| outer.dispatch(args, blackboard));
+    assert_eq!(s, Success);
+    drop(outer);
+    drop(inner);
+    assert_eq!((acc, unhandled), (2, 1));
+}
+
+#[test]
+fn test_shared_state_lasts_across_ticks() {
+    use bonsai_bt::SharedCooldown;
+
+    let behavior = || {
+        let inc = SharedCooldown("gcd".to_string(), 1.0, Box::new(Action(Inc)));
+        While(Box::new(Forever), vec![Select(vec![inc, Action(Dec)])])
+    };
+
+    // the cooldown is kept in the shared state, and only lets `Inc` through every other tick
+    let mut state = State::new(behavior());
+    let mut shared = SharedState::new();
+    let (a, _, _) = tick_shared(0, 0.5, &mut state, &mut shared);
+    let (a, _, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    let (a, _, _) = tick_shared(a, 0.5, &mut state, &mut shared);
+    assert_eq!(a, 1);
+
+    // without it, the cooldown starts over on every tick
+    let mut state = State::new(behavior());
+    let (a, _, _) = tick(0, 0.5, &mut state);
+    let (a, _, _) = tick(a, 0.5, &mut state);
+    let (a, _, _) = tick(a, 0.5, &mut state);
+    assert_eq!(a, 3);
+}
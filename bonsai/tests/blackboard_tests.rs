@@ -1,7 +1,7 @@
 #![allow(dead_code, unused_imports, unused_variables)]
 use std::collections::HashMap;
 
-use bonsai_bt::{Action, Event, Sequence, Success, UpdateArgs, Period, BT};
+use bonsai_bt::{Action, Event, Expiring, Sequence, Success, UpdateArgs, Period, BT};
 
 use crate::blackboard_tests::TestActions::{Dec, Inc};
 
@@ -68,3 +68,37 @@ fn test_crate_bt() {
     let count = bb.get_db_mut().get("count").unwrap();
     assert_eq!(*count, 1);
 }
+
+#[test]
+fn test_entry_expires() {
+    let seq = Sequence(vec![Period(10.0), Action(Inc)]);
+    let h: HashMap<String, Expiring<i32>> = HashMap::new();
+    let mut bt = BT::new(seq, h);
+    let tick = |bt: &mut BT<TestActions, HashMap<String, Expiring<i32>>>, dt| {
+        let e: Event = UpdateArgs { dt }.into();
+        bt.tick(&e, &mut |args, _| (Success, args.dt));
+    };
+    bt.get_blackboard_mut().insert_with_ttl("enemy", 3, 1.0);
+    bt.get_blackboard_mut().get_db_mut().insert("home".to_string(), 7.into());
+
+    tick(&mut bt, 0.5);
+    assert_eq!(bt.get_blackboard().get("enemy"), Some(&3));
+    assert_eq!(bt.get_blackboard().ttl("enemy"), Some(0.5));
+    tick(&mut bt, 0.5);
+    assert_eq!(bt.get_blackboard().get("enemy"), None);
+    assert!(!bt.get_blackboard().get_db().contains_key("enemy"));
+    assert_eq!(bt.get_blackboard().get("home"), Some(&7));
+
+    // the entry no longer expires once kept
+    bt.get_blackboard_mut().insert_with_ttl("enemy", 4, 1.0);
+    bt.get_blackboard_mut().keep("enemy");
+    tick(&mut bt, 5.0);
+    assert_eq!(bt.get_blackboard().get("enemy"), Some(&4));
+
+    // nor once a plain value is written over it
+    bt.get_blackboard_mut().insert_with_ttl("enemy", 5, 1.0);
+    bt.get_blackboard_mut().get_db_mut().insert("enemy".to_string(), 6.into());
+    tick(&mut bt, 5.0);
+    assert_eq!(bt.get_blackboard().get("enemy"), Some(&6));
+    assert_eq!(bt.get_blackboard().ttl("enemy"), None);
+}
@@ -1,10 +1,17 @@
 use std::collections::HashMap;
 
 use crate::bt_tests::TestActions::{Dec, Inc, LessThan};
-use bonsai_bt::{Action, Behavior::Select, Event, Failure, Success, UpdateArgs, BT};
+use bonsai_bt::{
+    Action, ActionArgs,
+    Behavior::{
+        AdaptiveSelect, EarlySuccessParallel, Finally, OrderedParallel, ResourceParallel, Select, Sequence, WhenAny,
+    },
+    replay, CancelToken, Event, Failure, Forever, Period, Running, SequenceStep, SharedCooldown, Signal, Success,
+    TimerInfo, UpdateArgs, WhenAll, While, WithInit, BT,
+};
 
 /// Some test actions.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
 enum TestActions {
     /// Increment accumulator.
     Inc,
@@ -67,3 +74,1262 @@ fn test_select_succeed_on_second_last() {
     assert_eq!(a, 0);
     assert_eq!(s, Success);
 }
+
+#[test]
+fn test_shared_cooldown_blocks_other_nodes() {
+    let a: i32 = 0;
+    let seq = Sequence(vec![
+        SharedCooldown("gcd".to_string(), 1.0, Box::new(Action(Inc))),
+        SharedCooldown("gcd".to_string(), 1.0, Box::new(Action(Inc))),
+    ]);
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(seq, h);
+
+    // the first node starts the cooldown, so the second one fails
+    let (a, s, _) = tick(a, 0.1, &mut bt);
+    assert_eq!(a, 1);
+    assert_eq!(s, Failure);
+}
+
+#[test]
+fn test_shared_cooldown_different_keys() {
+    let a: i32 = 0;
+    let seq = Sequence(vec![
+        SharedCooldown("a".to_string(), 1.0, Box::new(Action(Inc))),
+        SharedCooldown("b".to_string(), 1.0, Box::new(Action(Inc))),
+    ]);
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(seq, h);
+
+    let (a, s, _) = tick(a, 0.1, &mut bt);
+    assert_eq!(a, 2);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_shared_cooldown_expires() {
+    let a: i32 = 0;
+    let behavior = While(
+        Box::new(Forever),
+        vec![Select(vec![
+            SharedCooldown("gcd".to_string(), 1.0, Box::new(Action(Inc))),
+            Period(0.5),
+        ])],
+    );
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h);
+
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 1);
+    // still cooling down, falls through to the wait
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 1);
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 2);
+
+    // resetting the tree also resets the shared cooldowns
+    let (a, _, _) = tick(a, 0.1, &mut bt);
+    assert_eq!(a, 2);
+    bt.reset_bt();
+    let (a, _, _) = tick(a, 0.1, &mut bt);
+    assert_eq!(a, 3);
+}
+
+#[test]
+fn test_tick_with_ctx() {
+    struct World {
+        acc: i32,
+    }
+
+    let seq = Sequence(vec![Action(Inc), Period(1.0), Action(Inc), Action(Dec)]);
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(seq, h);
+
+    let mut handler = |args: ActionArgs<Event, TestActions>, _: &mut _, world: &mut World| {
+        match *args.action {
+            Inc => {
+                world.acc += 1;
+                (Success, args.dt)
+            }
+            Dec => {
+                world.acc -= 1;
+                (Success, args.dt)
+            }
+            LessThan(v) => {
+                if world.acc < v {
+                    (Success, args.dt)
+                } else {
+                    (Failure, args.dt)
+                }
+            }
+        }
+    };
+
+    let e: Event = UpdateArgs { dt: 0.5 }.into();
+    let mut world = World { acc: 0 };
+    let (s, _) = bt.tick_with_ctx(&e, &mut world, &mut handler);
+    assert_eq!(world.acc, 1);
+    assert_eq!(s, Running);
+
+    let (s, _) = bt.tick_with_ctx(&e, &mut world, &mut handler);
+    assert_eq!(world.acc, 1);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_active_timers() {
+    let a: i32 = 0;
+    let seq = Sequence(vec![
+        Period(1.0),
+        WhenAll(vec![Period(2.0), Sequence(vec![Action(Inc), Period(3.0)])]),
+    ]);
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(seq, h);
+    assert_eq!(
+        bt.active_timers(),
+        vec![TimerInfo {
+            path: vec![0],
+            elapsed: 0.0,
+            total: 1.0
+        }]
+    );
+
+    let (a, _, _) = tick(a, 1.5, &mut bt);
+    assert_eq!(
+        bt.active_timers(),
+        vec![
+            TimerInfo {
+                path: vec![1, 0],
+                elapsed: 0.5,
+                total: 2.0
+            },
+            TimerInfo {
+                path: vec![1, 1, 1],
+                elapsed: 0.5,
+                total: 3.0
+            },
+        ]
+    );
+
+    // the first branch of the WhenAll terminates
+    let (_, _, _) = tick(a, 1.5, &mut bt);
+    assert_eq!(
+        bt.active_timers(),
+        vec![TimerInfo {
+            path: vec![1, 1, 1],
+            elapsed: 2.0,
+            total: 3.0
+        }]
+    );
+}
+
+#[test]
+fn test_next_wakeup() {
+    let behavior = Sequence(vec![WhenAll(vec![Period(1.0), Period(2.5)]), Action(Inc)]);
+    let mut bt = BT::new(behavior, HashMap::new());
+    assert_eq!(bt.next_wakeup(), Some(1.0));
+
+    let (a, _, _) = tick(0, 0.5, &mut bt);
+    assert_eq!(bt.next_wakeup(), Some(0.5));
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(bt.next_wakeup(), Some(1.5));
+
+    // no timer is left once the periods are over
+    let (a, s, _) = tick(a, 1.5, &mut bt);
+    assert_eq!((a, s), (1, Success));
+    assert_eq!(bt.next_wakeup(), None);
+}
+
+#[test]
+fn test_debug_tree() {
+    let behavior = Select(vec![Action(LessThan(0)), Sequence(vec![Action(Inc), Period(1.0)])]);
+    let mut bt = BT::new(behavior, HashMap::new());
+
+    let (a, s, _) = tick(0, 0.5, &mut bt);
+    assert_eq!((a, s), (1, Running));
+    let expected = "\
+> Select [Running]
+    Action(LessThan(0)) [Failure]
+>   Sequence [Running]
+      Action(Inc) [Success]
+>     Period(1.0) [Running 0.5/1.0]
+";
+    assert_eq!(bt.debug_tree(), expected);
+
+    // once the tree has terminated, nothing is running anymore
+    let (_, s, _) = tick(a, 1.0, &mut bt);
+    assert_eq!(s, Success);
+    let tree = bt.debug_tree();
+    assert!(tree.starts_with("  Select [Success]\n"));
+    assert!(!tree.contains('>'));
+}
+
+#[test]
+fn test_tick_n() {
+    let mut a: i32 = 0;
+    let seq = Sequence(vec![Period(1.0), Action(Inc), Period(1.0), Action(Inc)]);
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(seq, h);
+
+    let mut handler = |args: ActionArgs<Event, TestActions>, _: &mut _| {
+        a += 1;
+        (Success, args.dt)
+    };
+    assert_eq!(bt.tick_n(3, 0.5, &mut handler), vec![Running, Running, Running]);
+    // stops once the sequence succeeds
+    assert_eq!(bt.tick_n(3, 0.5, &mut handler), vec![Success]);
+    assert_eq!(a, 2);
+}
+
+#[test]
+fn test_is_running_until_complete() {
+    let seq = Sequence(vec![Period(1.0), Action(Inc)]);
+    let mut bt = BT::new(seq, HashMap::new());
+    assert!(bt.is_running());
+    assert!(!bt.is_complete());
+
+    let mut a = 0;
+    let mut ticks = 0;
+    while bt.is_running() {
+        (a, _, _) = tick(a, 0.5, &mut bt);
+        ticks += 1;
+    }
+    assert_eq!(ticks, 2);
+    assert_eq!(a, 1);
+    assert!(bt.is_complete());
+
+    bt.reset_bt();
+    assert!(bt.is_running());
+}
+
+#[test]
+fn test_tick_with_output() {
+    let seq = Sequence(vec![Action(Inc), Period(1.0), Action(Dec)]);
+    let mut bt = BT::new(seq, ()).with_output::<TestActions>();
+    let mut handler = |args: ActionArgs<Event, TestActions>, _: &mut _, output: &mut Vec<TestActions>| {
+        output.push(args.action.clone());
+        (Success, args.dt)
+    };
+
+    // the events are kept until drained
+    let e: Event = UpdateArgs { dt: 0.5 }.into();
+    bt.tick_with_output(&e, &mut handler);
+    bt.tick_with_output(&e, &mut handler);
+    assert_eq!(bt.drain_events(), vec![Inc, Dec]);
+    assert_eq!(bt.drain_events(), vec![]);
+
+    // trees emit signals by default
+    let mut bt = BT::new(Action(Inc), ());
+    bt.tick_with_output(&e, &mut |args, _, output| {
+        output.push(Signal::new(format!("{:?}", args.action)));
+        (Success, args.dt)
+    });
+    assert_eq!(bt.drain_events(), vec![Signal::new("Inc")]);
+}
+
+#[test]
+fn test_sequence_steps() {
+    let a: i32 = 0;
+    let work = Sequence(vec![Period(1.0), Action(Inc), Period(1.0)]).named("work");
+    let behavior = While(Box::new(Forever), vec![work]);
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h);
+    bt.record_sequence_steps(true);
+
+    let step = |name: Option<&str>, completed, total| SequenceStep {
+        name: name.map(String::from),
+        completed,
+        total,
+    };
+
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    assert!(bt.sequence_steps().is_empty());
+
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(bt.sequence_steps(), [step(Some("work"), 0, 3), step(Some("work"), 1, 3)]);
+
+    // the loop starts over once the named sequence is done
+    let (a, _, _) = tick(a, 1.0, &mut bt);
+    assert_eq!(bt.sequence_steps(), [step(Some("work"), 2, 3), step(None, 0, 1)]);
+
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    assert!(bt.sequence_steps().is_empty());
+    assert_eq!(a, 1);
+
+    // no steps are recorded once recording stops
+    bt.record_sequence_steps(false);
+    let (a, _, _) = tick(a, 1.0, &mut bt);
+    assert!(bt.sequence_steps().is_empty());
+    assert_eq!(a, 2);
+}
+
+#[test]
+fn test_ordered_parallel_steps_in_order() {
+    let a: i32 = 0;
+    let wait = Sequence(vec![Period(1.0), Action(Inc)]).named("wait");
+    let behavior = OrderedParallel(vec![wait, Action(Inc)]).named("both");
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h);
+    bt.record_sequence_steps(true);
+    let step = |name: &str, completed| SequenceStep {
+        name: Some(name.to_string()),
+        completed,
+        total: 2,
+    };
+
+    // the second behavior is done first, but waits for the first one
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+    assert!(bt.sequence_steps().is_empty());
+
+    let (a, s, dt) = tick(a, 0.75, &mut bt);
+    assert_eq!(a, 2);
+    assert_eq!(s, Success);
+    assert_eq!(dt, 0.25);
+    assert_eq!(
+        bt.sequence_steps(),
+        [step("wait", 0), step("wait", 1), step("both", 0), step("both", 1)]
+    );
+}
+
+#[test]
+fn test_with_init_runs_again_after_reset() {
+    let a: i32 = 0;
+    let behavior = WithInit(Box::new(Action(Dec)), Box::new(Action(Inc)));
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h);
+
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 0);
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 2);
+
+    bt.reset_bt();
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 2);
+    assert_eq!(s, Running);
+}
+
+#[test]
+fn test_actions_with_tag() {
+    let behavior = Sequence(vec![
+        Action(Inc).tagged("count"),
+        Select(vec![Action(LessThan(2)), Sequence(vec![Action(Dec), Action(Dec)]).tagged("count")]),
+        Action(Inc).named("inc"),
+    ]);
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let bt = BT::new(behavior, h);
+
+    assert_eq!(bt.actions_with_tag("count"), vec![vec![0], vec![1, 1, 0], vec![1, 1, 1]]);
+    assert!(bt.actions_with_tag("other").is_empty());
+}
+
+#[test]
+fn test_disable_tag() {
+    let a: i32 = 0;
+    let behavior = Sequence(vec![
+        Sequence(vec![Period(1.0), Action(Inc)]).tagged("combat"),
+        Action(Inc),
+    ]);
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h);
+
+    // the disabled behavior is skipped as if it succeeded
+    bt.disable_tag("combat", Success);
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 1);
+    assert_eq!(s, Success);
+
+    // disabled tags are kept when resetting
+    bt.reset_bt();
+    bt.disable_tag("combat", Failure);
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 1);
+    assert_eq!(s, Failure);
+
+    // a running behavior that gets disabled starts over when enabled again
+    let behavior = Sequence(vec![Period(1.0), Action(Inc)]).tagged("combat");
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h);
+
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(s, Running);
+    bt.disable_tag("combat", Failure);
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(s, Failure);
+    bt.enable_tag("combat");
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 2);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_integer_time_is_exact() {
+    let mut a: i32 = 0;
+    // time counted in milliseconds
+    let seq = Sequence(vec![Period(1000.0), Action(Inc)]);
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(seq, h);
+
+    for _ in 0..62 {
+        let (acc, s, _) = tick(a, 16.0, &mut bt);
+        assert_eq!(s, Running);
+        a = acc;
+    }
+    let (a, s, dt) = tick(a, 16.0, &mut bt);
+    assert_eq!(a, 1);
+    assert_eq!(s, Success);
+    assert_eq!(dt, 8.0);
+}
+
+#[test]
+fn test_long_running_timers_keep_firing() {
+    let mut a: i32 = 0;
+    let mut handler = |args: ActionArgs<Event, TestActions>, _: &mut _| {
+        a += 1;
+        (Success, args.dt)
+    };
+    let repeat = While(Box::new(Forever), vec![Sequence(vec![Period(1.0), Action(Inc)])]);
+    let mut bt = BT::new(repeat, ());
+
+    // after a billion seconds, the timer still fires once every four ticks
+    // of a quarter second, as it counts from zero each time
+    let e: Event = UpdateArgs { dt: 1e9 }.into();
+    bt.tick(&e, &mut handler);
+    bt.tick_n(40, 0.25, &mut handler);
+    assert_eq!(a, 11);
+    assert!(bt.active_timers().iter().all(|timer| timer.elapsed < 1.0));
+}
+
+#[test]
+fn test_reset_aborts_background_behaviors() {
+    let cleanup = Finally(Box::new(Forever), Box::new(Action(Dec)));
+    let behavior = Sequence(vec![EarlySuccessParallel(1, vec![Action(Inc), cleanup]), Forever]);
+    let mut bt = BT::new(behavior, HashMap::new());
+
+    let (a, s, _) = tick(0, 0.5, &mut bt);
+    assert_eq!((a, s), (1, Running));
+    // the behavior left in the background is aborted with the tree, and its
+    // finalizer runs on the next tick
+    bt.reset_bt();
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!((a, s), (1, Running));
+}
+
+#[test]
+fn test_tick_memoized() {
+    let behavior = WhenAll(vec![
+        Sequence(vec![Action(LessThan(5)), Period(1.0)]),
+        Sequence(vec![Action(LessThan(5)), Action(LessThan(3))]),
+    ]);
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h);
+
+    let mut calls = 0;
+    let e: Event = UpdateArgs { dt: 0.5 }.into();
+    let mut handler = |args: ActionArgs<Event, TestActions>, _: &mut _| {
+        calls += 1;
+        (Success, args.dt)
+    };
+    // LessThan(5) is only evaluated once
+    let (s, _) = bt.tick_memoized(&e, &mut handler);
+    assert_eq!(s, Running);
+    assert_eq!(calls, 2);
+
+    // the results are forgotten between ticks
+    let seq = Sequence(vec![Action(LessThan(5)), Period(1.0), Action(LessThan(5))]);
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(seq, h);
+    let mut calls = 0;
+    let mut handler = |args: ActionArgs<Event, TestActions>, _: &mut _| {
+        calls += 1;
+        (Success, args.dt)
+    };
+    bt.tick_memoized(&e, &mut handler);
+    let (s, _) = bt.tick_memoized(&e, &mut handler);
+    assert_eq!(s, Success);
+    assert_eq!(calls, 2);
+}
+
+#[test]
+fn test_tick_memoized_uses_no_more_time_than_left() {
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(Sequence(vec![Action(Inc), Action(Inc)]), h);
+
+    let mut calls = 0;
+    let e: Event = UpdateArgs { dt: 1.0 }.into();
+    let mut handler = |args: ActionArgs<Event, TestActions>, _: &mut _| {
+        calls += 1;
+        (Success, args.dt - 0.6)
+    };
+    // the reused result would use 0.6 seconds, but only 0.4 are left
+    let (s, dt) = bt.tick_memoized(&e, &mut handler);
+    assert_eq!((s, dt), (Success, 0.0));
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn test_tick_cancellable() {
+    let behavior = Sequence(vec![Action(Inc), Action(Inc), Period(1.0)]);
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h);
+
+    let token = CancelToken::new();
+    let mut acc = 0;
+    let e: Event = UpdateArgs { dt: 0.5 }.into();
+    let mut handler = |args: ActionArgs<Event, TestActions>, _: &mut _| {
+        acc += 1;
+        // cancelled by another system while the tree is running
+        if acc == 1 {
+            token.cancel();
+        }
+        (Success, args.dt)
+    };
+    // the second action is never called
+    let (s, _) = bt.tick_cancellable(&e, &token, &mut handler);
+    assert_eq!(s, Failure);
+    assert!(token.is_cancelled());
+
+    // the tree was reset, and starts over once the token is reset
+    token.reset();
+    let (s, _) = bt.tick_cancellable(&e, &token, &mut |args, _| (Success, args.dt));
+    assert_eq!(s, Running);
+    assert_eq!(acc, 1);
+    token.cancel();
+    let (s, dt) = bt.tick_cancellable(&e, &token, &mut |args, _| (Success, args.dt));
+    assert_eq!(s, Failure);
+    assert_eq!(dt, 0.5);
+}
+
+#[test]
+fn test_replay_event_log() {
+    let behavior = While(Box::new(Period(5.0)), vec![Period(1.0), Action(Inc), Action(LessThan(3))]);
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior.clone(), h.clone());
+    bt.record_events();
+
+    let mut a = 0;
+    let mut results = vec![];
+    for dt in [0.3, 0.9, 0.1, 1.5, 0.2, 0.7, 1.0, 0.4, 1.3] {
+        let (acc, s, t) = tick(a, dt, &mut bt);
+        a = acc;
+        results.push((s, t));
+    }
+    assert_eq!(results.last().unwrap().0, Success);
+    let log = bt.take_event_log().unwrap();
+    assert_eq!(log.len(), 9);
+    assert!(bt.event_log().is_none());
+
+    // the same session, in a fresh tree
+    let mut b = 0;
+    let mut handler = |args: ActionArgs<Event, TestActions>, _: &mut _| match *args.action {
+        Inc => {
+            b += 1;
+            (Success, args.dt)
+        }
+        Dec => {
+            b -= 1;
+            (Success, args.dt)
+        }
+        LessThan(v) if b < v => (Success, args.dt),
+        LessThan(_) => (Failure, args.dt),
+    };
+    let mut fresh = BT::new(behavior, h);
+    assert_eq!(replay(&log, &mut fresh, &mut handler), results);
+    assert_eq!(b, a);
+}
+
+// Waits for the door to open, then walks through.
+fn walk_through_door(walked: &mut u32, args: ActionArgs<Event, &str>) -> (bonsai_bt::Status, f64) {
+    match *args.action {
+        "door opened" => match args.event.signal() {
+            Some(signal) if signal.name == "door_opened" => (Success, args.dt),
+            _ => (Running, 0.0),
+        },
+        _ => {
+            *walked += 1;
+            (Success, args.dt)
+        }
+    }
+}
+
+#[test]
+fn test_replay_event_log_keeps_signals() {
+    let behavior = Sequence(vec![Action("door opened"), Action("walk through")]);
+    let mut bt = BT::new(behavior.clone(), ());
+    bt.record_events();
+    let mut walked = 0;
+    let update: Event = UpdateArgs { dt: 0.1 }.into();
+    let signal: Event = Signal::new("door_opened").into();
+    let mut results = vec![];
+    for e in [&update, &signal, &update] {
+        results.push(bt.tick(e, &mut |args, _| walk_through_door(&mut walked, args)));
+    }
+    assert_eq!(results.last().unwrap().0, Success);
+    let log = bt.take_event_log().unwrap();
+    assert_eq!(log.events(), &[update.clone(), signal, update]);
+
+    // the signal opens the door again when replayed
+    let mut fresh = BT::new(behavior, ());
+    let mut replayed = 0;
+    let results_replayed = replay(&log, &mut fresh, &mut |args, _| walk_through_door(&mut replayed, args));
+    assert_eq!(results_replayed, results);
+    assert_eq!(replayed, walked);
+}
+
+#[test]
+fn test_finally_runs_finalizer_when_aborted() {
+    let a: i32 = 0;
+    let finally = Finally(Box::new(Forever), Box::new(Sequence(vec![Period(0.5), Action(Dec)])));
+    let behavior = Sequence(vec![WhenAny(vec![finally.clone(), Action(Inc)]), Forever]);
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h.clone());
+
+    // the finalizer goes on after the `WhenAny` is done
+    let (a, s, _) = tick(a, 0.25, &mut bt);
+    assert_eq!(a, 1);
+    assert_eq!(s, Running);
+    let (a, _, _) = tick(a, 0.25, &mut bt);
+    assert_eq!(a, 0);
+    let (a, _, _) = tick(a, 0.25, &mut bt);
+    assert_eq!(a, 0);
+
+    // the finalizer runs after a reset, along with the tree
+    let mut bt = BT::new(finally, h);
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, 0);
+    bt.reset_bt();
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!(a, -1);
+    assert_eq!(s, Running);
+}
+
+#[test]
+fn test_tick_budgeted_defers_actions() {
+    let behavior = Sequence(vec![Action(Inc), Action(Inc), Period(1.0), Action(Inc)]);
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h);
+
+    // each action uses up the budget, so that one action runs per tick
+    let mut a = 0;
+    let e: Event = UpdateArgs { dt: 0.5 }.into();
+    let mut slow_inc = |args: ActionArgs<Event, TestActions>, _: &mut _| {
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        a += 1;
+        (Success, args.dt)
+    };
+    assert_eq!(bt.tick_budgeted(&e, 1000, &mut slow_inc), (Running, 0.0));
+    // the second action is called on the next tick, and the tick stops before the period
+    assert_eq!(bt.tick_budgeted(&e, 1000, &mut slow_inc), (Running, 0.0));
+    // so that the period only starts counting on the tick after
+    assert_eq!(bt.tick_budgeted(&e, 1000, &mut slow_inc), (Running, 0.0));
+    assert_eq!(bt.tick_budgeted(&e, 1000, &mut slow_inc), (Success, 0.0));
+    assert_eq!(a, 3);
+}
+
+#[test]
+fn test_tick_budgeted_keeps_reactive_select_running() {
+    use bonsai_bt::SelectReactive;
+
+    let behavior = SelectReactive(vec![Action(LessThan(0)), Sequence(vec![Period(1.0), Action(Inc)])]);
+    let mut bt = BT::new(behavior, HashMap::new());
+    let (a, s, _) = tick(0, 0.5, &mut bt);
+    assert_eq!((a, s), (0, Running));
+
+    // the first behavior is not checked again before the deadline, and does
+    // not take over from the running one
+    let e: Event = UpdateArgs { dt: 0.5 }.into();
+    let mut idle = |args: ActionArgs<Event, TestActions>, _: &mut _| (Running, args.dt);
+    assert_eq!(bt.tick_budgeted(&e, 0, &mut idle), (Running, 0.0));
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!((a, s), (1, Success));
+}
+
+#[test]
+fn test_tick_budgeted_checks_interval_guard_later() {
+    use bonsai_bt::IntervalGuard;
+
+    let count = While(Box::new(Forever), vec![Action(Inc)]);
+    let behavior = IntervalGuard(1.0, Box::new(Action(LessThan(0))), Box::new(count));
+    let mut bt = BT::new(behavior, HashMap::new());
+
+    // the condition is not checked before the deadline, so it is checked on
+    // the next tick instead of counting as passed
+    let e: Event = UpdateArgs { dt: 0.5 }.into();
+    let mut idle = |args: ActionArgs<Event, TestActions>, _: &mut _| (Running, args.dt);
+    assert_eq!(bt.tick_budgeted(&e, 0, &mut idle), (Running, 0.0));
+    let (a, s, _) = tick(0, 0.5, &mut bt);
+    assert_eq!((a, s), (0, Failure));
+}
+
+#[test]
+fn test_with_subtrees() {
+    use bonsai_bt::{Behavior, BuildError, SubtreeError};
+
+    let subtree = |key: &str| Behavior::Subtree(key.to_string());
+    let mut subtrees = HashMap::new();
+    subtrees.insert("twice".to_string(), Sequence(vec![subtree("once"), subtree("once")]));
+    subtrees.insert("once".to_string(), Action(Inc));
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::with_subtrees(Sequence(vec![subtree("twice"), subtree("once")]), h.clone(), &subtrees).unwrap();
+    let (a, s, _) = tick(0, 0.0, &mut bt);
+    assert_eq!(a, 3);
+    assert_eq!(s, Success);
+
+    let unknown = BT::with_subtrees(subtree("thrice"), h.clone(), &subtrees);
+    assert_eq!(unknown.err(), Some(SubtreeError::Unknown("thrice".to_string())));
+
+    // a subtree referring to itself through another one
+    subtrees.insert("ping".to_string(), Select(vec![Action(Dec), subtree("pong")]));
+    subtrees.insert("pong".to_string(), Select(vec![Action(Inc), subtree("ping")]));
+    let recursive = BT::with_subtrees(subtree("ping"), h.clone(), &subtrees);
+    let keys = vec!["ping".to_string(), "pong".to_string(), "ping".to_string()];
+    assert_eq!(recursive.err(), Some(SubtreeError::Recursive(keys)));
+
+    // a tree with an unresolved subtree is rejected rather than panicking
+    let unresolved = BT::try_new(Sequence(vec![Action(Inc), subtree("once")]), h.clone());
+    assert_eq!(unresolved.err(), Some(BuildError::Subtree(SubtreeError::Unknown("once".to_string()))));
+}
+
+#[test]
+fn test_try_new_rejects_learning_rate_out_of_range() {
+    use bonsai_bt::{BuildError, InvalidParameter};
+
+    let select = AdaptiveSelect("attack".to_string(), 1.5, vec![Action(Inc)]);
+    let behavior = Sequence(vec![Action(Dec), select]);
+    let h: HashMap<String, i32> = HashMap::new();
+    let expected = BuildError::Parameter(InvalidParameter::LearningRate(vec![1]));
+    assert_eq!(BT::try_new(behavior.clone(), h.clone()).err(), Some(expected.clone()));
+    assert_eq!(BT::builder(behavior, h).build().err(), Some(expected));
+}
+
+#[test]
+fn test_try_new_rejects_cost_never_met() {
+    use bonsai_bt::{BuildError, InvalidParameter};
+
+    let parallel = ResourceParallel("ap".to_string(), vec![(1.0, Action(Inc)), (f64::NAN, Action(Dec))]);
+    let behavior = Sequence(vec![Action(Dec), parallel]);
+    let h: HashMap<String, i32> = HashMap::new();
+    let expected = BuildError::Parameter(InvalidParameter::Cost(vec![1]));
+    assert_eq!(BT::try_new(behavior.clone(), h.clone()).err(), Some(expected.clone()));
+    assert_eq!(BT::builder(behavior, h).build().err(), Some(expected));
+}
+
+#[test]
+fn test_with_subtrees_too_many_nodes() {
+    use bonsai_bt::{Behavior, SubtreeError};
+
+    // each level refers to the next one twice, which doubles the size of the tree
+    let mut subtrees = HashMap::new();
+    for level in 0..30 {
+        let next = Behavior::Subtree(format!("level {}", level + 1));
+        subtrees.insert(format!("level {}", level), Sequence(vec![next.clone(), next]));
+    }
+    subtrees.insert("level 30".to_string(), Action(Inc));
+    let root = Behavior::Subtree("level 0".to_string());
+
+    let h: HashMap<String, i32> = HashMap::new();
+    let error = BT::with_subtrees(root.clone(), h, &subtrees).err();
+    assert_eq!(error, Some(SubtreeError::TooManyNodes));
+    assert_eq!(root.resolve_subtrees_within(&subtrees, 100).err(), Some(SubtreeError::TooManyNodes));
+
+    // a level with 4 actions has 7 nodes
+    let resolved = Behavior::Subtree("level 28".to_string()).resolve_subtrees_within(&subtrees, 7);
+    assert!(resolved.is_ok());
+}
+
+#[test]
+fn test_with_limits() {
+    use bonsai_bt::{LimitError, Limits};
+
+    let behavior = || {
+        Sequence(vec![
+            Action(Inc),
+            Select(vec![Action(LessThan(0)), Action(Inc), Action(Dec)]).named("fallback"),
+        ])
+    };
+    let h: HashMap<String, i32> = HashMap::new();
+
+    // no limits by default
+    assert!(BT::with_limits(behavior(), h.clone(), Limits::default()).is_ok());
+
+    let limits = Limits {
+        max_children: 2,
+        ..Limits::default()
+    };
+    let error = BT::with_limits(behavior(), h.clone(), limits).unwrap_err();
+    assert_eq!(error, LimitError::TooManyChildren { path: vec![1], count: 3 });
+
+    // the name counts as a node
+    let limits = Limits {
+        max_nodes: 6,
+        ..Limits::default()
+    };
+    let error = BT::with_limits(behavior(), h.clone(), limits).unwrap_err();
+    assert_eq!(error, LimitError::TooManyNodes);
+    let limits = Limits {
+        max_nodes: 7,
+        ..Limits::default()
+    };
+    let mut bt = BT::with_limits(behavior(), h, limits).unwrap();
+    let (a, s, _) = tick(0, 0.0, &mut bt);
+    assert_eq!(a, 2);
+    assert_eq!(s, Success);
+}
+
+#[test]
+fn test_builder_combines_checks() {
+    use bonsai_bt::{Behavior, BuildError, InvalidAction, LimitError, Limits, SubtreeError};
+
+    let mut subtrees = HashMap::new();
+    subtrees.insert("fallback".to_string(), Select(vec![Action(LessThan(0)), Action(Inc), Action(Dec)]));
+    let behavior = || Sequence(vec![Action(Inc), Behavior::Subtree("fallback".to_string())]);
+    let h: HashMap<String, i32> = HashMap::new();
+
+    // the limits apply to the resolved tree
+    let limits = Limits {
+        max_children: 2,
+        ..Limits::default()
+    };
+    let error = BT::builder(behavior(), h.clone()).subtrees(&subtrees).limits(limits).build();
+    assert_eq!(error.err(), Some(BuildError::Limit(LimitError::TooManyChildren { path: vec![1], count: 3 })));
+    let limits = Limits {
+        max_nodes: 5,
+        ..Limits::default()
+    };
+    let error = BT::builder(behavior(), h.clone()).subtrees(&subtrees).limits(limits).build();
+    assert_eq!(error.err(), Some(BuildError::Limit(LimitError::TooManyNodes)));
+
+    // and so does the validation
+    let no_dec = |action: &TestActions| match action {
+        Dec => Err("no Dec".to_string()),
+        _ => Ok(()),
+    };
+    let error = BT::builder(behavior(), h.clone()).subtrees(&subtrees).validation(no_dec).build();
+    let invalid = InvalidAction {
+        path: vec![1, 2],
+        message: "no Dec".to_string(),
+    };
+    assert_eq!(error.err(), Some(BuildError::InvalidActions(vec![invalid])));
+
+    // the subtrees have to be resolved
+    let error = BT::builder(behavior(), h.clone()).build();
+    assert_eq!(error.err(), Some(BuildError::Subtree(SubtreeError::Unknown("fallback".to_string()))));
+
+    let limits = Limits {
+        max_children: 3,
+        max_nodes: 6,
+    };
+    let mut bt = BT::builder(behavior(), h)
+        .subtrees(&subtrees)
+        .limits(limits)
+        .validation(|_: &TestActions| Ok(()))
+        .build()
+        .unwrap();
+    let (a, s, _) = tick(0, 0.0, &mut bt);
+    assert_eq!(a, 2);
+    assert_eq!(s, Success);
+}
+
+#[cfg(feature = "node-stats")]
+#[test]
+fn test_node_stats() {
+    use bonsai_bt::NodeStats;
+
+    let behavior = While(
+        Box::new(Forever),
+        vec![Select(vec![
+            Action(LessThan(0)),
+            Sequence(vec![Action(Inc), Period(1.0)]),
+        ])],
+    );
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h);
+
+    // the select runs again once the period is over
+    let mut a = 0;
+    for _ in 0..3 {
+        (a, _, _) = tick(a, 0.5, &mut bt);
+    }
+    assert_eq!(a, 2);
+
+    let stats = |path: Vec<usize>, activations, successes, failures| NodeStats {
+        path,
+        activations,
+        successes,
+        failures,
+    };
+    assert_eq!(
+        bt.node_stats(),
+        vec![
+            stats(vec![], 1, 0, 0),
+            stats(vec![0], 1, 0, 0),
+            stats(vec![1], 2, 1, 0),
+            stats(vec![1, 0], 2, 0, 2),
+            stats(vec![1, 1], 2, 1, 0),
+            stats(vec![1, 1, 0], 2, 2, 0),
+            stats(vec![1, 1, 1], 2, 1, 0),
+        ]
+    );
+
+    // the counts are kept when the tree is reset
+    bt.reset_bt();
+    tick(a, 0.5, &mut bt);
+    assert_eq!(bt.node_stats()[0], stats(vec![], 2, 0, 0));
+}
+
+#[cfg(feature = "node-stats")]
+#[test]
+fn test_last_selection() {
+    use bonsai_bt::{Candidate, CooldownSelect, SelectionInfo};
+
+    let behavior = While(
+        Box::new(Forever),
+        vec![
+            Select(vec![
+                Action(LessThan(0)),
+                Sequence(vec![Action(Inc), Period(1.0)]),
+            ]),
+            CooldownSelect(10.0, vec![Action(LessThan(0)), Action(Dec)]),
+        ],
+    );
+    let h: HashMap<String, i32> = HashMap::new();
+    let mut bt = BT::new(behavior, h);
+
+    let (a, _, _) = tick(0, 0.5, &mut bt);
+    assert_eq!(a, 1);
+    assert_eq!(
+        bt.last_selection(&[1]),
+        Some(SelectionInfo {
+            chosen: Some(1),
+            candidates: vec![Candidate::Failed, Candidate::Chosen],
+        })
+    );
+    assert_eq!(bt.last_selection(&[2]), None);
+    assert_eq!(bt.last_selection(&[0]), None);
+
+    // the select is done, then the cooldown select tries its behaviors
+    let (a, _, _) = tick(a, 0.5, &mut bt);
+    let (a, _, _) = tick(a, 1.0, &mut bt);
+    assert_eq!(a, 0);
+    assert_eq!(
+        bt.last_selection(&[2]),
+        Some(SelectionInfo {
+            chosen: Some(1),
+            candidates: vec![Candidate::Failed, Candidate::Chosen],
+        })
+    );
+
+    // the failed condition is on cooldown the next time around
+    let (a, _, _) = tick(a, 1.0, &mut bt);
+    let (a, _, _) = tick(a, 1.0, &mut bt);
+    assert_eq!(a, 0);
+    assert_eq!(
+        bt.last_selection(&[2]),
+        Some(SelectionInfo {
+            chosen: Some(1),
+            candidates: vec![Candidate::OnCooldown, Candidate::Chosen],
+        })
+    );
+}
+
+#[cfg(feature = "node-stats")]
+#[test]
+fn test_action_timings() {
+    let behavior = Sequence(vec![Action(Inc), Action(Dec)]);
+    let mut bt = BT::new(behavior, HashMap::new());
+    let e: Event = UpdateArgs { dt: 0.5 }.into();
+    let mut ticks = 0;
+    let mut handler = |args: ActionArgs<Event, TestActions>, _: &mut _| match *args.action {
+        // runs for 0.5 + 0.5 + 0.25 seconds
+        Inc => {
+            ticks += 1;
+            if ticks < 3 {
+                (Running, 0.0)
+            } else {
+                (Success, 0.25)
+            }
+        }
+        _ => (Success, args.dt),
+    };
+    for _ in 0..3 {
+        bt.tick(&e, &mut handler);
+    }
+
+    let timings = bt.action_timings();
+    assert_eq!(timings.len(), 2);
+    assert_eq!(timings[&vec![0]].bounds, vec![0.0, 0.01, 0.1, 1.0, 10.0, 60.0]);
+    assert_eq!(timings[&vec![0]].counts, vec![0, 0, 0, 0, 1, 0, 0]);
+    assert_eq!(timings[&vec![1]].counts, vec![1, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(timings[&vec![1]].total(), 1);
+
+    bt.set_timing_buckets(vec![1.0]);
+    assert!(bt.action_timings().is_empty());
+}
+
+#[cfg(feature = "node-stats")]
+#[test]
+fn test_selector_depths() {
+    use bonsai_bt::SelectorDepth;
+
+    let behavior = While(
+        Box::new(Forever),
+        vec![Select(vec![
+            Sequence(vec![Action(LessThan(2)), Action(Inc)]),
+            Action(Dec),
+        ])],
+    );
+    let mut bt = BT::new(behavior, HashMap::new());
+
+    // the first behavior succeeds twice, then fails once
+    let mut a = 0;
+    for _ in 0..3 {
+        (a, _, _) = tick(a, 0.5, &mut bt);
+    }
+    assert_eq!(a, 1);
+    let depths = bt.selector_depths();
+    assert_eq!(
+        depths,
+        vec![SelectorDepth {
+            path: vec![1],
+            successes: 3,
+            total_tried: 4,
+            max_tried: 2,
+        }]
+    );
+    assert_eq!(depths[0].average(), 4.0 / 3.0);
+}
+
+#[cfg(feature = "node-stats")]
+#[test]
+fn test_mermaid_heatmap() {
+    use bonsai_bt::Tagged;
+
+    let behavior = While(
+        Box::new(Forever),
+        vec![
+            Select(vec![Action(Inc), Action(Dec)]),
+            Tagged("wait".to_string(), Box::new(Period(1.0))),
+        ],
+    );
+    let mut bt = BT::new(behavior, HashMap::new());
+    tick(0, 0.5, &mut bt);
+
+    // `Dec` never runs, and the sequence drawn for the body is not counted
+    let mermaid = bt.to_mermaid_heatmap();
+    assert!(mermaid.contains("    class 1,2,4,5,7,8 heat5\n"));
+    assert!(mermaid.contains("    class 6 cold\n"));
+    assert!(!mermaid.contains("class 0"));
+}
+
+#[test]
+fn test_save_and_restore_state() {
+    let behavior = Sequence(vec![Period(1.0), Action(Inc)]);
+    let mut bt = BT::new(behavior.clone(), HashMap::new());
+    let (a, s, _) = tick(0, 0.5, &mut bt);
+    assert_eq!(s, Running);
+    let json = serde_json::to_string(&bt.save_state()).unwrap();
+    // only the runtime state is saved, without the behavior
+    assert!(!json.contains("Inc"));
+
+    // a tree built from the same behavior goes on from where the saved one was
+    let mut loaded = BT::new(behavior, HashMap::new());
+    loaded.restore_state(serde_json::from_str(&json).unwrap()).unwrap();
+    let (a, s, _) = tick(a, 0.5, &mut loaded);
+    assert_eq!(a, 1);
+    assert_eq!(s, Success);
+
+    // a tree built from another behavior rejects the state
+    let mut other = BT::new(Sequence(vec![Period(2.0), Action(Inc)]), HashMap::<String, i32>::new());
+    let error = other.restore_state(serde_json::from_str(&json).unwrap()).unwrap_err();
+    assert_eq!(error.found, bt.save_state().structure_hash());
+    assert_ne!(error.expected, error.found);
+}
+
+#[test]
+fn test_restore_state_keeps_finalizers() {
+    // the `WhenAny` aborts the `Finally` once the first behavior succeeds
+    let finally = Finally(Box::new(Period(10.0)), Box::new(Sequence(vec![Period(1.0), Action(Dec)])));
+    let any = WhenAny(vec![Sequence(vec![Period(0.5), Action(Inc)]), finally]);
+    let behavior = Sequence(vec![any, While(Box::new(Forever), vec![Period(1.0)])]);
+    let mut bt = BT::new(behavior.clone(), HashMap::new());
+    let (a, _, _) = tick(0, 0.25, &mut bt);
+    let (a, s, _) = tick(a, 0.5, &mut bt);
+    assert_eq!((a, s), (1, Running));
+    let json = serde_json::to_string(&bt.save_state()).unwrap();
+
+    // the finalizer goes on in the loaded tree, as do the nodes of the tree
+    let mut loaded = BT::new(behavior, HashMap::new());
+    loaded.restore_state(serde_json::from_str(&json).unwrap()).unwrap();
+    assert_eq!(loaded.active_timers(), bt.active_timers());
+    let (a, s, _) = tick(a, 0.5, &mut loaded);
+    assert_eq!((a, s), (0, Running));
+}
+
+#[test]
+fn test_template_instantiates_fresh_trees() {
+    use bonsai_bt::Tagged;
+
+    let behavior = Sequence(vec![Tagged("combat".to_string(), Box::new(Action(Dec))), Action(Inc)]);
+    let mut bt = BT::new(behavior, HashMap::new());
+    bt.disable_tag("combat", Success);
+    bt.get_blackboard_mut().get_db_mut().insert("hp".to_string(), 3);
+    let (a, s, _) = tick(0, 0.1, &mut bt);
+    assert_eq!((a, s), (1, Success));
+
+    let template = bt.template();
+    let mut agent = template.instantiate(HashMap::new(), 7);
+    assert!(agent.get_blackboard().get_db().is_empty());
+    assert!(agent.is_running());
+
+    // the disabled tag is kept, so `Dec` is skipped again
+    let (a, s, _) = tick(0, 0.1, &mut agent);
+    assert_eq!((a, s), (1, Success));
+}
+
+#[test]
+fn test_adaptive_select_learns_success_rates() {
+    let behavior = AdaptiveSelect("attack".to_string(), 0.5, vec![Action(LessThan(0)), Action(Inc)]);
+    let mut bt = BT::new(behavior, HashMap::new());
+    bt.set_seed(7);
+    assert_eq!(bt.success_estimates("attack"), None);
+
+    // the failing behavior is given up on within the tick, so the node always succeeds
+    let mut a = 0;
+    for _ in 0..10 {
+        let (new_a, s, _) = tick(a, 0.1, &mut bt);
+        assert_eq!(s, Success);
+        a = new_a;
+    }
+    assert_eq!(a, 10);
+    let estimates = bt.success_estimates("attack").unwrap().to_vec();
+    assert!(estimates[0] <= 0.5);
+    assert!(estimates[1] > 0.99);
+
+    bt.reset_bt();
+    assert_eq!(bt.success_estimates("attack"), None);
+}
+
+#[test]
+fn test_seeded_forks_random_stream() {
+    use bonsai_bt::{Behavior, Seeded};
+
+    let pick = || AdaptiveSelect("pick".to_string(), 0.0, vec![Action(Inc), Action(Dec)]);
+    // the actions picked over a few runs of the tree, seeded with the same seed
+    let picks = |behavior: Behavior<TestActions>| {
+        let e: Event = UpdateArgs { dt: 0.1 }.into();
+        let mut bt = BT::new(behavior, HashMap::<String, i32>::new());
+        bt.set_seed(42);
+        let mut picked = vec![];
+        for _ in 0..8 {
+            bt.tick(&e, &mut |args: ActionArgs<Event, TestActions>, _: &mut _| {
+                picked.push(args.action.clone());
+                (Success, args.dt)
+            });
+            bt.reset_bt();
+        }
+        picked
+    };
+
+    let seeded = picks(Seeded(1, Box::new(pick())));
+    assert_ne!(seeded, picks(Seeded(2, Box::new(pick()))));
+
+    // draws made outside of the seeded behavior do not shift its stream
+    let behavior = Sequence(vec![
+        AdaptiveSelect("other".to_string(), 0.0, vec![Action(LessThan(1)), Action(LessThan(2))]),
+        Seeded(1, Box::new(pick())),
+    ]);
+    let inner: Vec<_> = picks(behavior).into_iter().filter(|a| *a == Inc || *a == Dec).collect();
+    assert_eq!(inner, seeded);
+}
+
+#[test]
+fn test_tie_break_select_orders() {
+    use bonsai_bt::{TieBreak, TieBreakSelect};
+
+    // the action picked on each of a few runs of the loop, where every action succeeds
+    let picks = |tie_break: TieBreak| {
+        let e: Event = UpdateArgs { dt: 0.1 }.into();
+        let select = TieBreakSelect(tie_break, vec![Action(Inc), Action(Dec), Action(LessThan(0))]);
+        let mut bt = BT::new(While(Box::new(Forever), vec![select]), HashMap::<String, i32>::new());
+        bt.set_seed(7);
+        let mut picked = vec![];
+        for _ in 0..12 {
+            bt.tick(&e, &mut |args: ActionArgs<Event, TestActions>, _: &mut _| {
+                picked.push(args.action.clone());
+                (Success, args.dt)
+            });
+        }
+        picked
+    };
+
+    assert!(picks(TieBreak::DeclarationOrder).iter().all(|a| *a == Inc));
+
+    let rotated = picks(TieBreak::RoundRobin("patrol".to_string()));
+    assert_eq!(rotated[..4], [Inc, Dec, LessThan(0), Inc]);
+
+    let shuffled = picks(TieBreak::Random);
+    assert_eq!(shuffled.len(), 12);
+    assert!(shuffled.iter().any(|a| *a != shuffled[0]));
+}
+
+#[test]
+fn test_resource_parallel_shares_pool() {
+    let e: Event = UpdateArgs { dt: 0.5 }.into();
+    let mut inc_ticks = 0;
+    // `Inc` runs for two ticks, `LessThan` succeeds for positive values
+    let mut handler = |args: ActionArgs<Event, TestActions>, _: &mut _| match *args.action {
+        Inc => {
+            inc_ticks += 1;
+            if inc_ticks % 2 == 1 {
+                (Running, 0.0)
+            } else {
+                (Success, args.dt)
+            }
+        }
+        Dec => (Success, args.dt),
+        LessThan(v) if v > 0 => (Success, args.dt),
+        LessThan(_) => (Failure, args.dt),
+    };
+
+    let behavior = ResourceParallel(
+        "ap".to_string(),
+        vec![(2.0, Action(Inc)), (2.0, Action(Dec)), (1.0, Action(LessThan(10)))],
+    );
+    let mut bt = BT::new(behavior, HashMap::new());
+    bt.set_resource_pool("ap", 3.0);
+
+    // `Dec` waits for the pool, while the cheaper behavior after it runs
+    assert_eq!(bt.tick(&e, &mut handler).0, Running);
+    assert_eq!(bt.resource_pool("ap"), 0.0);
+    assert_eq!(bt.tick(&e, &mut handler).0, Running);
+    assert_eq!(bt.resource_pool("ap"), 0.0);
+    bt.set_resource_pool("ap", 2.0);
+    assert_eq!(bt.tick(&e, &mut handler).0, Success);
+    assert_eq!(bt.resource_pool("ap"), 0.0);
+
+    // the costs of the failed and the aborted behaviors are given back
+    let behavior = ResourceParallel("ap".to_string(), vec![(2.0, Action(Inc)), (1.0, Action(LessThan(0)))]);
+    let mut bt = BT::new(behavior, HashMap::new());
+    bt.set_resource_pool("ap", 3.0);
+    assert_eq!(bt.tick(&e, &mut handler).0, Failure);
+    assert_eq!(bt.resource_pool("ap"), 3.0);
+}
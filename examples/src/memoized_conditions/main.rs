@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use bonsai_bt::{
+    Action, ActionArgs, Behavior, Event, Failure, Select, Sequence, Status, Success, UpdateArgs, WhenAll, BT,
+};
+
+/// Conditions are expensive to evaluate, e.g. they ray cast into the world.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Npc {
+    /// Is an enemy within a distance.
+    EnemyWithin(u32),
+    /// Is health below a threshold.
+    HealthBelow(u32),
+    Attack,
+    Flee,
+    Heal,
+}
+
+const TICKS: usize = 10_000;
+
+// Several reactive branches ticked in parallel, all checking the same conditions.
+fn behavior() -> Behavior<Npc> {
+    let branch = |n: u32| {
+        Select(vec![
+            Sequence(vec![
+                Action(Npc::HealthBelow(20)),
+                Action(Npc::EnemyWithin(50)),
+                Action(Npc::Flee),
+            ]),
+            Sequence(vec![Action(Npc::HealthBelow(50)), Action(Npc::Heal)]),
+            Sequence(vec![Action(Npc::EnemyWithin(10 * n)), Action(Npc::Attack)]),
+        ])
+    };
+    WhenAll((1..=8).map(branch).collect())
+}
+
+// Runs the tree for a number of ticks and returns the number of condition
+// evaluations and the time it took.
+fn run(memoized: bool) -> (usize, Duration) {
+    let mut bt = BT::new(behavior(), HashMap::<String, u32>::new());
+    let e: Event = UpdateArgs { dt: 0.016 }.into();
+
+    let mut evaluations = 0;
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        if memoized {
+            bt.tick_memoized(&e, &mut |args, _| evaluate(args, &mut evaluations));
+        } else {
+            bt.tick(&e, &mut |args, _| evaluate(args, &mut evaluations));
+        }
+        bt.reset_bt();
+    }
+    (evaluations, start.elapsed())
+}
+
+fn evaluate(args: ActionArgs<Event, Npc>, evaluations: &mut usize) -> (Status, f64) {
+    let holds = match *args.action {
+        Npc::EnemyWithin(distance) => {
+            *evaluations += 1;
+            expensive_check(distance)
+        }
+        Npc::HealthBelow(threshold) => {
+            *evaluations += 1;
+            expensive_check(threshold)
+        }
+        Npc::Attack | Npc::Flee | Npc::Heal => true,
+    };
+    (if holds { Success } else { Failure }, args.dt)
+}
+
+fn expensive_check(input: u32) -> bool {
+    let mut x = u64::from(input);
+    for _ in 0..1_000 {
+        x = x.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        // Keeps the loop from being optimized away.
+        x = unsafe { std::ptr::read_volatile(&x) };
+    }
+    x % 2 == 0
+}
+
+fn main() {
+    let (plain_evaluations, plain_time) = run(false);
+    let (memoized_evaluations, memoized_time) = run(true);
+
+    println!("{} ticks of a condition-heavy tree:", TICKS);
+    println!("  tick:           {:>7} evaluations in {:?}", plain_evaluations, plain_time);
+    println!("  tick_memoized:  {:>7} evaluations in {:?}", memoized_evaluations, memoized_time);
+}
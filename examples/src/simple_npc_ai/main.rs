@@ -178,7 +178,7 @@ fn main() {
     );
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct BlackBoardData {
     times_shot: usize,
 }